@@ -12,3 +12,7 @@ cargo deny check --hide-inclusion-graph
 
 # Checks enabled everywhere, including tests, benchmarks
 cargo clippy
+
+# Ensure optional features and no_std builds are not broken
+cargo clippy --all-features --all-targets
+cargo clippy --no-default-features
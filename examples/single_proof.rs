@@ -43,6 +43,7 @@ type TreeNode = [u8; 32];
 #[derive(Clone)]
 pub struct HashAlgo(Blake2bHasher);
 
+#[allow(clippy::new_without_default)]
 impl HashAlgo {
     pub fn new() -> Self {
         Self(Blake2bHasher::new())
@@ -0,0 +1,204 @@
+#ifndef MERKLETREE_MINTLAYER_H
+#define MERKLETREE_MINTLAYER_H
+
+/* Generated with cbindgen:0.26.0 */
+
+/* Warning: this file is autogenerated by cbindgen. Don't modify it manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The size of a node in bytes
+ */
+#define MERKLETREE_NODE_SIZE 32
+
+/**
+ * The result of every function in this module
+ */
+typedef enum MerkletreeStatus {
+  MerkletreeStatus_Ok = 0,
+  /**
+   * A required pointer argument is null
+   */
+  MerkletreeStatus_NullPointer = 1,
+  /**
+   * No leaves were provided, where at least one is required
+   */
+  MerkletreeStatus_EmptyInput = 2,
+  /**
+   * One or more leaf indices are out of range for the tree, or refer to padding leaves
+   */
+  MerkletreeStatus_IndexOutOfRange = 3,
+  /**
+   * Leaf indices must be sorted in ascending order and unique
+   */
+  MerkletreeStatus_UnsortedOrDuplicateIndices = 4,
+  /**
+   * The output buffer is too small; the required length was written to the output length argument
+   */
+  MerkletreeStatus_BufferTooSmall = 5,
+  /**
+   * The tree leaf count or the node indices of the proof are invalid
+   */
+  MerkletreeStatus_InvalidProof = 6,
+  /**
+   * A node required to verify the proof is missing
+   */
+  MerkletreeStatus_MissingProofNode = 7,
+  /**
+   * An unexpected internal error happened
+   */
+  MerkletreeStatus_InternalError = 8,
+  /**
+   * Too many leaves were provided for a supported tree
+   */
+  MerkletreeStatus_TooManyLeaves = 9,
+} MerkletreeStatus;
+
+/**
+ * The result of verifying a proof. See `ProofVerifyResult` for more details.
+ */
+typedef enum MerkletreeVerifyResult {
+  MerkletreeVerifyResult_Failed = 0,
+  MerkletreeVerifyResult_PassedDecisively = 1,
+  MerkletreeVerifyResult_PassedTrivially = 2,
+} MerkletreeVerifyResult;
+
+/**
+ * An opaque handle to a merkle tree with 32-byte nodes, hashed with Blake2b
+ */
+typedef struct MerkletreeHandle MerkletreeHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Build a merkle tree from `leaf_count` leaves, each of which is `MERKLETREE_NODE_SIZE` bytes,
+ * stored contiguously in `leaves`. The leaves are padded incrementally to the next power of two.
+ * On success, the tree is written to `out_tree`, and must be freed with `merkletree_free`.
+ *
+ * # Safety
+ *
+ * `leaves` must point to `leaf_count * MERKLETREE_NODE_SIZE` readable bytes,
+ * and `out_tree` must be a valid pointer to write to.
+ */
+enum MerkletreeStatus merkletree_build(const uint8_t *leaves,
+                                       size_t leaf_count,
+                                       struct MerkletreeHandle **out_tree);
+
+/**
+ * Free a tree created with `merkletree_build`. Passing null is a no-op.
+ *
+ * # Safety
+ *
+ * `tree` must be either null or a pointer returned by `merkletree_build` that was not freed before.
+ */
+void merkletree_free(struct MerkletreeHandle *tree);
+
+/**
+ * Write the root of the tree, `MERKLETREE_NODE_SIZE` bytes, to `out_root`.
+ *
+ * # Safety
+ *
+ * `tree` must be a valid tree handle, and `out_root` must point to `MERKLETREE_NODE_SIZE` writable bytes.
+ */
+enum MerkletreeStatus merkletree_root(const struct MerkletreeHandle *tree,
+                                      uint8_t *out_root);
+
+/**
+ * Write the number of leaves in the tree, including padding, to `out_leaf_count`.
+ *
+ * # Safety
+ *
+ * `tree` must be a valid tree handle, and `out_leaf_count` must be a valid pointer to write to.
+ */
+enum MerkletreeStatus merkletree_leaf_count(const struct MerkletreeHandle *tree,
+                                            uint32_t *out_leaf_count);
+
+/**
+ * Extract the single proof of the leaf at `leaf_index`. The branch nodes, from bottom to top, are written to
+ * `out_branch`, which has space for `branch_capacity` nodes, and the number of nodes is written to `out_branch_len`.
+ * If the capacity is not enough, `MerkletreeStatus::BufferTooSmall` is returned, with the required number of nodes
+ * written to `out_branch_len`.
+ *
+ * # Safety
+ *
+ * `tree` must be a valid tree handle, `out_branch` must point to `branch_capacity * MERKLETREE_NODE_SIZE`
+ * writable bytes, and `out_branch_len` must be a valid pointer to write to.
+ */
+enum MerkletreeStatus merkletree_single_proof(const struct MerkletreeHandle *tree,
+                                              uint32_t leaf_index,
+                                              uint8_t *out_branch,
+                                              size_t branch_capacity,
+                                              size_t *out_branch_len);
+
+/**
+ * Verify a single proof of `leaf`, at `leaf_index`, with `branch_len` branch nodes, against `root`.
+ * The verification result is written to `out_result`.
+ *
+ * # Safety
+ *
+ * `leaf` and `root` must point to `MERKLETREE_NODE_SIZE` readable bytes, `branch` must point to
+ * `branch_len * MERKLETREE_NODE_SIZE` readable bytes, and `out_result` must be a valid pointer to write to.
+ */
+enum MerkletreeStatus merkletree_verify_single_proof(const uint8_t *leaf,
+                                                     uint32_t leaf_index,
+                                                     const uint8_t *branch,
+                                                     size_t branch_len,
+                                                     const uint8_t *root,
+                                                     enum MerkletreeVerifyResult *out_result);
+
+/**
+ * Extract a multi-proof for the leaves at `leaf_indices`, which must be sorted and unique.
+ * The absolute indices of the proof nodes are written to `out_node_indices`, and the nodes themselves to `out_nodes`,
+ * both of which have space for `nodes_capacity` elements. The number of proof nodes is written to `out_nodes_len`.
+ * If the capacity is not enough, `MerkletreeStatus::BufferTooSmall` is returned, with the required number of nodes
+ * written to `out_nodes_len`.
+ *
+ * # Safety
+ *
+ * `tree` must be a valid tree handle, `leaf_indices` must point to `leaf_indices_len` readable indices,
+ * `out_node_indices` must point to `nodes_capacity` writable indices, `out_nodes` must point to
+ * `nodes_capacity * MERKLETREE_NODE_SIZE` writable bytes, and `out_nodes_len` must be a valid pointer to write to.
+ */
+enum MerkletreeStatus merkletree_multi_proof(const struct MerkletreeHandle *tree,
+                                             const uint32_t *leaf_indices,
+                                             size_t leaf_indices_len,
+                                             uint32_t *out_node_indices,
+                                             uint8_t *out_nodes,
+                                             size_t nodes_capacity,
+                                             size_t *out_nodes_len);
+
+/**
+ * Verify a multi-proof against `root`. The proof is made of `nodes_len` nodes, at the absolute indices
+ * `node_indices`, of a tree with `tree_leaf_count` leaves. The leaves to verify are `leaves_len` nodes in `leaves`,
+ * at the leaf indices `leaf_indices`, which must be sorted and unique, like in `merkletree_multi_proof`, otherwise
+ * `MerkletreeStatus::UnsortedOrDuplicateIndices` is returned. The verification result is written to `out_result`.
+ *
+ * # Safety
+ *
+ * `node_indices` must point to `nodes_len` readable indices, `nodes` must point to
+ * `nodes_len * MERKLETREE_NODE_SIZE` readable bytes, `leaf_indices` must point to `leaves_len` readable indices,
+ * `leaves` must point to `leaves_len * MERKLETREE_NODE_SIZE` readable bytes, `root` must point to
+ * `MERKLETREE_NODE_SIZE` readable bytes, and `out_result` must be a valid pointer to write to.
+ */
+enum MerkletreeStatus merkletree_verify_multi_proof(uint32_t tree_leaf_count,
+                                                    const uint32_t *node_indices,
+                                                    const uint8_t *nodes,
+                                                    size_t nodes_len,
+                                                    const uint32_t *leaf_indices,
+                                                    const uint8_t *leaves,
+                                                    size_t leaves_len,
+                                                    const uint8_t *root,
+                                                    enum MerkletreeVerifyResult *out_result);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MERKLETREE_MINTLAYER_H */
@@ -0,0 +1,260 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A command-line tool for building merkle trees from leaves, printing them, and creating and verifying proofs.
+//! Leaves are 32-byte nodes in hex, separated by whitespace, and the tree is hashed with Blake2b.
+
+mod proof_format;
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use merkletree_mintlayer::{
+    hasher::blake2b::{Blake2bHasher, Blake2bNode},
+    proof::{multi::MultiProofNodes, single::SingleProofNodes, verify_result::ProofVerifyResult},
+    tree::MerkleTree,
+    MerkleProofVerificationError, MerkleTreeFormError, MerkleTreeProofExtractionError,
+};
+
+use proof_format::{ProofFormat, ProofFormatError};
+
+type Tree = MerkleTree<Blake2bNode, Blake2bHasher>;
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the root of the tree
+    Root {
+        /// The file with the leaves in hex; stdin is used if not provided
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+    },
+    /// Print all the nodes of the tree, level by level, starting from the root
+    Tree {
+        /// The file with the leaves in hex; stdin is used if not provided
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+    },
+    /// Print the proof of a single leaf
+    SingleProof {
+        /// The file with the leaves in hex; stdin is used if not provided
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// The index of the leaf to prove
+        #[arg(long)]
+        index: u32,
+        #[arg(short, long, value_enum, default_value_t = ProofFormat::Hex)]
+        format: ProofFormat,
+    },
+    /// Print the proof of multiple leaves
+    MultiProof {
+        /// The file with the leaves in hex; stdin is used if not provided
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// The indices of the leaves to prove, comma separated, sorted and unique
+        #[arg(long, value_delimiter = ',', required = true)]
+        indices: Vec<u32>,
+        #[arg(short, long, value_enum, default_value_t = ProofFormat::Hex)]
+        format: ProofFormat,
+    },
+    /// Verify a single proof from a file against a root. Exits with a non-zero code if verification fails.
+    VerifySingle {
+        /// The file that contains the proof
+        #[arg(short, long)]
+        proof: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = ProofFormat::Hex)]
+        format: ProofFormat,
+        /// The leaf to verify, in hex
+        #[arg(long)]
+        leaf: String,
+        /// The root of the tree, in hex
+        #[arg(long)]
+        root: String,
+    },
+    /// Verify a multi-proof from a file against a root. Exits with a non-zero code if verification fails.
+    VerifyMulti {
+        /// The file that contains the proof
+        #[arg(short, long)]
+        proof: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = ProofFormat::Hex)]
+        format: ProofFormat,
+        /// A leaf to verify, as index:hex; can be repeated
+        #[arg(long = "leaf", required = true)]
+        leaves: Vec<String>,
+        /// The root of the tree, in hex
+        #[arg(long)]
+        root: String,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+enum CliError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid hex node `{0}`; nodes must be 32 bytes in hex")]
+    InvalidNode(String),
+    #[error("Invalid leaf `{0}`; leaves must be provided as index:hex")]
+    InvalidIndexedLeaf(String),
+    #[error("Tree creation error: {0}")]
+    TreeForm(#[from] MerkleTreeFormError),
+    #[error("Proof extraction error: {0}")]
+    ProofExtraction(#[from] MerkleTreeProofExtractionError),
+    #[error("Proof verification error: {0}")]
+    ProofVerification(#[from] MerkleProofVerificationError),
+    #[error("Proof format error: {0}")]
+    ProofFormat(#[from] ProofFormatError),
+}
+
+fn parse_node(s: &str) -> Result<Blake2bNode, CliError> {
+    let s = s.trim();
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    hex::decode(s)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| CliError::InvalidNode(s.to_string()))
+}
+
+fn parse_indexed_leaf(s: &str) -> Result<(u32, Blake2bNode), CliError> {
+    let (index, node) = s
+        .split_once(':')
+        .ok_or_else(|| CliError::InvalidIndexedLeaf(s.to_string()))?;
+    let index = index
+        .trim()
+        .parse()
+        .map_err(|_| CliError::InvalidIndexedLeaf(s.to_string()))?;
+    Ok((index, parse_node(node)?))
+}
+
+fn parse_leaves(text: &str) -> Result<Vec<Blake2bNode>, CliError> {
+    text.split_whitespace().map(parse_node).collect()
+}
+
+fn read_input(input: Option<&PathBuf>) -> Result<String, CliError> {
+    match input {
+        Some(path) => Ok(std::fs::read_to_string(path)?),
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn read_tree(input: Option<&PathBuf>) -> Result<Tree, CliError> {
+    let leaves = parse_leaves(&read_input(input)?)?;
+    Ok(Tree::from_leaves(leaves)?)
+}
+
+/// Writes the tree level by level, starting from the root. Padding leaves are marked.
+fn write_tree(out: &mut impl Write, tree: &Tree) -> std::io::Result<()> {
+    for (level, nodes) in tree.levels().enumerate().rev() {
+        writeln!(out, "level {level}:")?;
+        for (index, node) in nodes.iter().enumerate() {
+            let padding = level == 0 && tree.is_padding_leaf(index as u32);
+            let suffix = if padding { " (padding)" } else { "" };
+            writeln!(out, "  {index}: {}{suffix}", hex::encode(node))?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the command, and writes the output. Returns the verification result for verification commands.
+fn run(command: Command, out: &mut impl Write) -> Result<Option<ProofVerifyResult>, CliError> {
+    match command {
+        Command::Root { input } => {
+            let tree = read_tree(input.as_ref())?;
+            writeln!(out, "{}", hex::encode(tree.root()))?;
+            Ok(None)
+        }
+        Command::Tree { input } => {
+            let tree = read_tree(input.as_ref())?;
+            write_tree(out, &tree)?;
+            Ok(None)
+        }
+        Command::SingleProof {
+            input,
+            index,
+            format,
+        } => {
+            let tree = read_tree(input.as_ref())?;
+            let proof = SingleProofNodes::from_tree_leaf(&tree, index)?.into_values();
+            out.write_all(&format.encode_single(&proof))?;
+            Ok(None)
+        }
+        Command::MultiProof {
+            input,
+            indices,
+            format,
+        } => {
+            let tree = read_tree(input.as_ref())?;
+            let proof = MultiProofNodes::from_tree_leaves(&tree, &indices)?.into_values();
+            out.write_all(&format.encode_multi(&proof))?;
+            Ok(None)
+        }
+        Command::VerifySingle {
+            proof,
+            format,
+            leaf,
+            root,
+        } => {
+            let proof = format.decode_single(&std::fs::read(proof)?)?;
+            let result = proof.verify(parse_node(&leaf)?, parse_node(&root)?);
+            writeln!(out, "{result:?}")?;
+            Ok(Some(result))
+        }
+        Command::VerifyMulti {
+            proof,
+            format,
+            leaves,
+            root,
+        } => {
+            let proof = format.decode_multi(&std::fs::read(proof)?)?;
+            let leaves = leaves
+                .iter()
+                .map(|s| parse_indexed_leaf(s))
+                .collect::<Result<BTreeMap<_, _>, _>>()?;
+            let result = proof.verify_by_levels(leaves, parse_node(&root)?)?;
+            writeln!(out, "{result:?}")?;
+            Ok(Some(result))
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command, &mut std::io::stdout().lock()) {
+        Ok(Some(ProofVerifyResult::Failed)) => ExitCode::FAILURE,
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
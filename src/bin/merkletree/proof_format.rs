@@ -0,0 +1,177 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use merkletree_mintlayer::{
+    hasher::blake2b::{Blake2bHasher, Blake2bNode},
+    proof::{multi::MultiProofHashes, single::SingleProofHashes},
+};
+use parity_scale_codec::{DecodeAll, Encode};
+use serde_json::{json, Value};
+
+pub type SingleProof = SingleProofHashes<Blake2bNode, Blake2bHasher>;
+pub type MultiProof = MultiProofHashes<Blake2bNode, Blake2bHasher>;
+
+/// The formats in which proofs are written and read
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofFormat {
+    /// The SCALE encoding of the proof, in hex
+    Hex,
+    /// A human readable JSON representation of the proof, with nodes in hex
+    Json,
+    /// The SCALE encoding of the proof, in binary
+    Scale,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ProofFormatError {
+    #[error("Proof is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("Invalid hex: {0}")]
+    InvalidHex(#[from] hex::FromHexError),
+    #[error("SCALE decoding error: {0}")]
+    Scale(String),
+    #[error("JSON error: {0}")]
+    Json(String),
+    #[error("Invalid or missing JSON field `{0}`")]
+    InvalidJsonField(&'static str),
+}
+
+fn node_to_json(node: &Blake2bNode) -> Value {
+    Value::String(hex::encode(node))
+}
+
+fn node_from_json(value: &Value, field: &'static str) -> Result<Blake2bNode, ProofFormatError> {
+    value
+        .as_str()
+        .and_then(|s| hex::decode(s).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ProofFormatError::InvalidJsonField(field))
+}
+
+fn u32_from_json(value: &Value, field: &'static str) -> Result<u32, ProofFormatError> {
+    value
+        .as_u64()
+        .and_then(|v| v.try_into().ok())
+        .ok_or(ProofFormatError::InvalidJsonField(field))
+}
+
+fn text(bytes: &[u8]) -> Result<&str, ProofFormatError> {
+    std::str::from_utf8(bytes)
+        .map(str::trim)
+        .map_err(|_| ProofFormatError::InvalidUtf8)
+}
+
+fn json_from_bytes(bytes: &[u8]) -> Result<Value, ProofFormatError> {
+    serde_json::from_str(text(bytes)?).map_err(|e| ProofFormatError::Json(e.to_string()))
+}
+
+fn scale_from_bytes<P: DecodeAll>(bytes: &[u8]) -> Result<P, ProofFormatError> {
+    P::decode_all(&mut &bytes[..]).map_err(|e| ProofFormatError::Scale(e.to_string()))
+}
+
+fn json_to_bytes(value: Value) -> Vec<u8> {
+    let mut result = serde_json::to_vec_pretty(&value).expect("Serializing a value cannot fail");
+    result.push(b'\n');
+    result
+}
+
+fn hex_to_bytes(encoded: &[u8]) -> Vec<u8> {
+    let mut result = hex::encode(encoded).into_bytes();
+    result.push(b'\n');
+    result
+}
+
+impl ProofFormat {
+    pub fn encode_single(&self, proof: &SingleProof) -> Vec<u8> {
+        match self {
+            ProofFormat::Hex => hex_to_bytes(&proof.encode()),
+            ProofFormat::Scale => proof.encode(),
+            ProofFormat::Json => json_to_bytes(json!({
+                "level_from_bottom": proof.level_from_bottom(),
+                "leaf_index_in_level": proof.leaf_index_in_level(),
+                "branch": proof.branch().iter().map(node_to_json).collect::<Vec<_>>(),
+            })),
+        }
+    }
+
+    pub fn decode_single(&self, bytes: &[u8]) -> Result<SingleProof, ProofFormatError> {
+        match self {
+            ProofFormat::Hex => scale_from_bytes(&hex::decode(text(bytes)?)?),
+            ProofFormat::Scale => scale_from_bytes(bytes),
+            ProofFormat::Json => {
+                let value = json_from_bytes(bytes)?;
+                // Proofs of leaves may leave out the level
+                let level = match &value["level_from_bottom"] {
+                    Value::Null => 0,
+                    level => u32_from_json(level, "level_from_bottom")?,
+                };
+                let leaf_index =
+                    u32_from_json(&value["leaf_index_in_level"], "leaf_index_in_level")?;
+                let branch = value["branch"]
+                    .as_array()
+                    .ok_or(ProofFormatError::InvalidJsonField("branch"))?
+                    .iter()
+                    .map(|node| node_from_json(node, "branch"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(SingleProof::from_position_and_branch(
+                    level, leaf_index, branch,
+                ))
+            }
+        }
+    }
+
+    pub fn encode_multi(&self, proof: &MultiProof) -> Vec<u8> {
+        match self {
+            ProofFormat::Hex => hex_to_bytes(&proof.encode()),
+            ProofFormat::Scale => proof.encode(),
+            ProofFormat::Json => json_to_bytes(json!({
+                "tree_leaf_count": proof.tree_leaf_count(),
+                "nodes": proof
+                    .nodes()
+                    .iter()
+                    .map(|(index, node)| json!({"index": index, "node": node_to_json(node)}))
+                    .collect::<Vec<_>>(),
+            })),
+        }
+    }
+
+    pub fn decode_multi(&self, bytes: &[u8]) -> Result<MultiProof, ProofFormatError> {
+        match self {
+            ProofFormat::Hex => scale_from_bytes(&hex::decode(text(bytes)?)?),
+            ProofFormat::Scale => scale_from_bytes(bytes),
+            ProofFormat::Json => {
+                let value = json_from_bytes(bytes)?;
+                let tree_leaf_count = u32_from_json(&value["tree_leaf_count"], "tree_leaf_count")?;
+                let nodes = value["nodes"]
+                    .as_array()
+                    .ok_or(ProofFormatError::InvalidJsonField("nodes"))?
+                    .iter()
+                    .map(|entry| {
+                        Ok((
+                            u32_from_json(&entry["index"], "index")?,
+                            node_from_json(&entry["node"], "node")?,
+                        ))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, ProofFormatError>>()?;
+                Ok(MultiProof::from_leaf_count_and_nodes(
+                    tree_leaf_count,
+                    nodes,
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use merkletree_mintlayer::{pos::NodePosition, proof::multi::MultiProofHashes};
+
+use super::*;
+
+fn gen_leaves(n: u32) -> Vec<Blake2bNode> {
+    (0..n)
+        .map(|i| Blake2bHasher::hash_data(i.to_be_bytes()))
+        .collect()
+}
+
+/// A file in the temp directory that's removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &[u8]) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("merkletree-cli-test-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        Self(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn leaves_file(name: &str, leaves: &[Blake2bNode]) -> TempFile {
+    let text = leaves
+        .iter()
+        .map(hex::encode)
+        .collect::<Vec<_>>()
+        .join("\n");
+    TempFile::new(name, text.as_bytes())
+}
+
+fn run_to_vec(command: Command) -> (Result<Option<ProofVerifyResult>, CliError>, Vec<u8>) {
+    let mut out = Vec::new();
+    let result = run(command, &mut out);
+    (result, out)
+}
+
+#[test]
+fn parsing_nodes() {
+    let node = Blake2bHasher::hash_data("a");
+    assert_eq!(parse_node(&hex::encode(node)).unwrap(), node);
+    assert_eq!(
+        parse_node(&format!("0x{}", hex::encode(node))).unwrap(),
+        node
+    );
+    assert!(matches!(
+        parse_node(&hex::encode(&node[1..])),
+        Err(CliError::InvalidNode(_))
+    ));
+    assert!(matches!(parse_node("zz"), Err(CliError::InvalidNode(_))));
+
+    let leaves = gen_leaves(5);
+    let text = format!(
+        "{}\n{} {}\n\n{}\t{}\n",
+        hex::encode(leaves[0]),
+        hex::encode(leaves[1]),
+        hex::encode(leaves[2]),
+        hex::encode(leaves[3]),
+        hex::encode(leaves[4])
+    );
+    assert_eq!(parse_leaves(&text).unwrap(), leaves);
+
+    assert_eq!(
+        parse_indexed_leaf(&format!("3:{}", hex::encode(node))).unwrap(),
+        (3, node)
+    );
+    assert!(matches!(
+        parse_indexed_leaf(&hex::encode(node)),
+        Err(CliError::InvalidIndexedLeaf(_))
+    ));
+}
+
+#[test]
+fn root_and_tree() {
+    let leaves = gen_leaves(5);
+    let tree = Tree::from_leaves(leaves.clone()).unwrap();
+    let file = leaves_file("root_and_tree", &leaves);
+
+    let (result, out) = run_to_vec(Command::Root {
+        input: Some(file.path().to_path_buf()),
+    });
+    assert!(result.unwrap().is_none());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!("{}\n", hex::encode(tree.root()))
+    );
+
+    let (result, out) = run_to_vec(Command::Tree {
+        input: Some(file.path().to_path_buf()),
+    });
+    assert!(result.unwrap().is_none());
+    let out = String::from_utf8(out).unwrap();
+    let lines = out.lines().collect::<Vec<_>>();
+    // 4 levels headers + 15 nodes
+    assert_eq!(lines.len(), 4 + 15);
+    assert_eq!(lines[0], "level 3:");
+    assert_eq!(lines[1], format!("  0: {}", hex::encode(tree.root())));
+    assert_eq!(lines[10], "level 0:");
+    assert_eq!(lines[11], format!("  0: {}", hex::encode(leaves[0])));
+    assert_eq!(lines[15], format!("  4: {}", hex::encode(leaves[4])));
+    assert!(lines[16..].iter().all(|line| line.ends_with(" (padding)")));
+}
+
+#[test]
+fn single_proof_in_all_formats() {
+    let leaves = gen_leaves(6);
+    let tree = Tree::from_leaves(leaves.clone()).unwrap();
+    let root = hex::encode(tree.root());
+    let file = leaves_file("single_proof", &leaves);
+
+    for format in [ProofFormat::Hex, ProofFormat::Json, ProofFormat::Scale] {
+        for (index, leaf) in leaves.iter().enumerate() {
+            let (result, out) = run_to_vec(Command::SingleProof {
+                input: Some(file.path().to_path_buf()),
+                index: index as u32,
+                format,
+            });
+            assert!(result.unwrap().is_none());
+
+            let expected = SingleProofNodes::from_tree_leaf(&tree, index as u32)
+                .unwrap()
+                .into_values();
+            assert_eq!(format.decode_single(&out).unwrap(), expected);
+
+            let proof_file = TempFile::new(&format!("single_proof_{format:?}_{index}"), &out);
+            let (result, out) = run_to_vec(Command::VerifySingle {
+                proof: proof_file.path().to_path_buf(),
+                format,
+                leaf: hex::encode(leaf),
+                root: root.clone(),
+            });
+            assert_eq!(result.unwrap(), Some(ProofVerifyResult::PassedDecisively));
+            assert_eq!(out, b"PassedDecisively\n");
+
+            let (result, _) = run_to_vec(Command::VerifySingle {
+                proof: proof_file.path().to_path_buf(),
+                format,
+                leaf: hex::encode(Blake2bHasher::hash_data(leaf)),
+                root: root.clone(),
+            });
+            assert_eq!(result.unwrap(), Some(ProofVerifyResult::Failed));
+        }
+
+        // Proofs of nodes above the leaves keep their level
+        let position = NodePosition::from_position(tree.total_node_count(), 1, 1).unwrap();
+        let node_proof = SingleProofNodes::from_tree_node(&tree, position)
+            .unwrap()
+            .into_values();
+        let decoded = format
+            .decode_single(&format.encode_single(&node_proof))
+            .unwrap();
+        assert_eq!(decoded, node_proof);
+    }
+
+    // The level of leaves may be left out in JSON
+    let decoded = ProofFormat::Json
+        .decode_single(br#"{"leaf_index_in_level": 3, "branch": []}"#)
+        .unwrap();
+    assert_eq!(
+        decoded,
+        proof_format::SingleProof::from_leaf_index_and_branch(3, vec![])
+    );
+}
+
+#[test]
+fn multi_proof_in_all_formats() {
+    let leaves = gen_leaves(11);
+    let tree = Tree::from_leaves(leaves.clone()).unwrap();
+    let root = hex::encode(tree.root());
+    let file = leaves_file("multi_proof", &leaves);
+    let indices = vec![1, 2, 7, 10];
+    let indexed_leaves = indices
+        .iter()
+        .map(|i| format!("{i}:{}", hex::encode(leaves[*i as usize])))
+        .collect::<Vec<_>>();
+
+    for format in [ProofFormat::Hex, ProofFormat::Json, ProofFormat::Scale] {
+        let (result, out) = run_to_vec(Command::MultiProof {
+            input: Some(file.path().to_path_buf()),
+            indices: indices.clone(),
+            format,
+        });
+        assert!(result.unwrap().is_none());
+
+        let expected: MultiProofHashes<_, _> = MultiProofNodes::from_tree_leaves(&tree, &indices)
+            .unwrap()
+            .into_values();
+        let decoded = format.decode_multi(&out).unwrap();
+        assert_eq!(decoded.nodes(), expected.nodes());
+        assert_eq!(decoded.tree_leaf_count(), expected.tree_leaf_count());
+
+        let proof_file = TempFile::new(&format!("multi_proof_{format:?}"), &out);
+        let (result, _) = run_to_vec(Command::VerifyMulti {
+            proof: proof_file.path().to_path_buf(),
+            format,
+            leaves: indexed_leaves.clone(),
+            root: root.clone(),
+        });
+        assert_eq!(result.unwrap(), Some(ProofVerifyResult::PassedDecisively));
+
+        let mut wrong_leaves = indexed_leaves.clone();
+        wrong_leaves[0] = format!("1:{}", hex::encode(leaves[0]));
+        let (result, _) = run_to_vec(Command::VerifyMulti {
+            proof: proof_file.path().to_path_buf(),
+            format,
+            leaves: wrong_leaves,
+            root: root.clone(),
+        });
+        assert_eq!(result.unwrap(), Some(ProofVerifyResult::Failed));
+
+        // A proof with a missing leaf cannot be verified
+        let (result, _) = run_to_vec(Command::VerifyMulti {
+            proof: proof_file.path().to_path_buf(),
+            format,
+            leaves: indexed_leaves[1..].to_vec(),
+            root: root.clone(),
+        });
+        assert!(matches!(
+            result.unwrap_err(),
+            CliError::ProofVerification(MerkleProofVerificationError::RequiredNodeMissing(_))
+        ));
+    }
+}
+
+#[test]
+fn invalid_proof_files() {
+    let garbage = TempFile::new("invalid_proof", b"not a proof");
+    for format in [ProofFormat::Hex, ProofFormat::Json, ProofFormat::Scale] {
+        let (result, _) = run_to_vec(Command::VerifySingle {
+            proof: garbage.path().to_path_buf(),
+            format,
+            leaf: hex::encode(Blake2bNode::default()),
+            root: hex::encode(Blake2bNode::default()),
+        });
+        assert!(matches!(result.unwrap_err(), CliError::ProofFormat(_)));
+    }
+}
+
+#[test]
+fn multi_proof_with_huge_leaf_count() {
+    // A crafted proof must be reported as invalid, rather than abort the tool
+    let proof = MultiProofHashes::from_leaf_count_and_nodes(1 << 31, BTreeMap::new());
+    let leaf = hex::encode(Blake2bNode::default());
+    for format in [ProofFormat::Hex, ProofFormat::Json, ProofFormat::Scale] {
+        let encoded = format.encode_multi(&proof);
+        assert_eq!(
+            format.decode_multi(&encoded).unwrap().tree_leaf_count(),
+            1 << 31
+        );
+
+        let proof_file = TempFile::new(&format!("huge_multi_proof_{format:?}"), &encoded);
+        let (result, _) = run_to_vec(Command::VerifyMulti {
+            proof: proof_file.path().to_path_buf(),
+            format,
+            leaves: vec![format!("0:{leaf}")],
+            root: leaf.clone(),
+        });
+        assert!(matches!(
+            result.unwrap_err(),
+            CliError::ProofVerification(MerkleProofVerificationError::InvalidTreeLeavesCount(
+                count
+            )) if count == 1 << 31
+        ));
+    }
+}
+
+#[test]
+fn cli_definition() {
+    use clap::CommandFactory;
+    Cli::command().debug_assert();
+}
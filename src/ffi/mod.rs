@@ -0,0 +1,424 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A C ABI for building merkle trees and verifying proofs, with 32-byte nodes and the built-in Blake2b hasher.
+//! All nodes are passed as contiguous buffers of 32-byte nodes, from left to right.
+//! No function in this module takes ownership of the buffers provided by the caller. Trees created with
+//! `merkletree_build` must be freed with `merkletree_free`.
+
+use std::{collections::BTreeMap, panic::AssertUnwindSafe};
+
+use crate::merkle::{
+    hasher::blake2b::{Blake2bHasher, Blake2bNode},
+    proof::{
+        multi::{MultiProofHashes, MultiProofNodes},
+        single::{proof_ref::SingleProofRef, SingleProofNodes},
+        verify_result::ProofVerifyResult,
+    },
+    tree::MerkleTree,
+    MerkleProofVerificationError, MerkleTreeFormError, MerkleTreeProofExtractionError,
+};
+
+/// The size of a node in bytes
+pub const MERKLETREE_NODE_SIZE: usize = 32;
+
+/// The result of every function in this module
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkletreeStatus {
+    Ok = 0,
+    /// A required pointer argument is null
+    NullPointer = 1,
+    /// No leaves were provided, where at least one is required
+    EmptyInput = 2,
+    /// One or more leaf indices are out of range for the tree, or refer to padding leaves
+    IndexOutOfRange = 3,
+    /// Leaf indices must be sorted in ascending order and unique
+    UnsortedOrDuplicateIndices = 4,
+    /// The output buffer is too small; the required length was written to the output length argument
+    BufferTooSmall = 5,
+    /// The tree leaf count or the node indices of the proof are invalid
+    InvalidProof = 6,
+    /// A node required to verify the proof is missing
+    MissingProofNode = 7,
+    /// An unexpected internal error happened
+    InternalError = 8,
+    /// Too many leaves were provided for a supported tree
+    TooManyLeaves = 9,
+}
+
+/// The result of verifying a proof. See `ProofVerifyResult` for more details.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkletreeVerifyResult {
+    Failed = 0,
+    PassedDecisively = 1,
+    PassedTrivially = 2,
+}
+
+impl From<ProofVerifyResult> for MerkletreeVerifyResult {
+    fn from(result: ProofVerifyResult) -> Self {
+        match result {
+            ProofVerifyResult::Failed => MerkletreeVerifyResult::Failed,
+            ProofVerifyResult::PassedDecisively => MerkletreeVerifyResult::PassedDecisively,
+            ProofVerifyResult::PassedTrivially => MerkletreeVerifyResult::PassedTrivially,
+        }
+    }
+}
+
+impl From<MerkleTreeFormError> for MerkletreeStatus {
+    fn from(err: MerkleTreeFormError) -> Self {
+        match err {
+            MerkleTreeFormError::TooSmall(_) => MerkletreeStatus::EmptyInput,
+            MerkleTreeFormError::TooLarge(_) => MerkletreeStatus::TooManyLeaves,
+            MerkleTreeFormError::StoreError(_)
+            | MerkleTreeFormError::InvalidStoreSize(_)
+            | MerkleTreeFormError::RealLeafCountMismatch(_, _)
+            | MerkleTreeFormError::RealLeafCountMismatch64(_, _) => MerkletreeStatus::InternalError,
+        }
+    }
+}
+
+impl From<MerkleTreeProofExtractionError> for MerkletreeStatus {
+    fn from(err: MerkleTreeProofExtractionError) -> Self {
+        match err {
+            MerkleTreeProofExtractionError::NoLeavesToCreateProof => MerkletreeStatus::EmptyInput,
+            MerkleTreeProofExtractionError::IndexOutOfRange(_, _)
+            | MerkleTreeProofExtractionError::LeafIndexOutOfRange(_, _)
+            | MerkleTreeProofExtractionError::PaddingLeaf(_, _)
+            | MerkleTreeProofExtractionError::TreeSizeMismatch(_, _)
+            | MerkleTreeProofExtractionError::AccessError(_)
+            | MerkleTreeProofExtractionError::IndexOutOfRange64(_, _)
+            | MerkleTreeProofExtractionError::LeafIndexOutOfRange64(_, _)
+            | MerkleTreeProofExtractionError::PaddingLeaf64(_, _) => {
+                MerkletreeStatus::IndexOutOfRange
+            }
+            MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices(_)
+            | MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices64(_) => {
+                MerkletreeStatus::UnsortedOrDuplicateIndices
+            }
+        }
+    }
+}
+
+impl From<MerkleProofVerificationError> for MerkletreeStatus {
+    fn from(err: MerkleProofVerificationError) -> Self {
+        match err {
+            MerkleProofVerificationError::LeavesContainerProvidedIsEmpty => {
+                MerkletreeStatus::EmptyInput
+            }
+            MerkleProofVerificationError::LeavesIndicesOutOfRange(_, _)
+            | MerkleProofVerificationError::LeafIndexOutOfRange(_, _)
+            | MerkleProofVerificationError::NodeIndexOutOfRange(_, _)
+            | MerkleProofVerificationError::LeavesIndicesOutOfRange64(_, _) => {
+                MerkletreeStatus::IndexOutOfRange
+            }
+            MerkleProofVerificationError::InvalidTreeLeavesCount(_)
+            | MerkleProofVerificationError::NodesIndicesOutOfRange(_, _)
+            | MerkleProofVerificationError::TreeSizeArithmeticError(_)
+            | MerkleProofVerificationError::BranchTooLong(_)
+            | MerkleProofVerificationError::BranchLengthMismatch(_, _)
+            | MerkleProofVerificationError::NotLeafLevel(_)
+            | MerkleProofVerificationError::InvalidTreeLeavesCount64(_)
+            | MerkleProofVerificationError::NodesIndicesOutOfRange64(_, _) => {
+                MerkletreeStatus::InvalidProof
+            }
+            MerkleProofVerificationError::RequiredNodeMissing(_)
+            | MerkleProofVerificationError::RequiredNodeMissing64(_) => {
+                MerkletreeStatus::MissingProofNode
+            }
+        }
+    }
+}
+
+/// An opaque handle to a merkle tree with 32-byte nodes, hashed with Blake2b
+pub struct MerkletreeHandle(MerkleTree<Blake2bNode, Blake2bHasher>);
+
+/// Panics must never cross the FFI boundary, so we convert them to an error status
+fn guard(f: impl FnOnce() -> Result<(), MerkletreeStatus>) -> MerkletreeStatus {
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => MerkletreeStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => MerkletreeStatus::InternalError,
+    }
+}
+
+/// Create a slice from a pointer and a length. A null pointer is accepted only if the length is zero.
+unsafe fn slice_from_raw<'a, T>(ptr: *const T, len: usize) -> Result<&'a [T], MerkletreeStatus> {
+    if len == 0 {
+        Ok(&[])
+    } else if ptr.is_null() {
+        Err(MerkletreeStatus::NullPointer)
+    } else {
+        Ok(std::slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// Create a mutable slice from a pointer and a length. A null pointer is accepted only if the length is zero.
+unsafe fn slice_from_raw_mut<'a, T>(
+    ptr: *mut T,
+    len: usize,
+) -> Result<&'a mut [T], MerkletreeStatus> {
+    if len == 0 {
+        Ok(&mut [])
+    } else if ptr.is_null() {
+        Err(MerkletreeStatus::NullPointer)
+    } else {
+        Ok(std::slice::from_raw_parts_mut(ptr, len))
+    }
+}
+
+unsafe fn ref_from_raw<'a, T>(ptr: *const T) -> Result<&'a T, MerkletreeStatus> {
+    ptr.as_ref().ok_or(MerkletreeStatus::NullPointer)
+}
+
+unsafe fn write_out<T>(ptr: *mut T, value: T) -> Result<(), MerkletreeStatus> {
+    if ptr.is_null() {
+        return Err(MerkletreeStatus::NullPointer);
+    }
+    ptr.write(value);
+    Ok(())
+}
+
+/// Build a merkle tree from `leaf_count` leaves, each of which is `MERKLETREE_NODE_SIZE` bytes,
+/// stored contiguously in `leaves`. The leaves are padded incrementally to the next power of two.
+/// On success, the tree is written to `out_tree`, and must be freed with `merkletree_free`.
+///
+/// # Safety
+///
+/// `leaves` must point to `leaf_count * MERKLETREE_NODE_SIZE` readable bytes,
+/// and `out_tree` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_build(
+    leaves: *const u8,
+    leaf_count: usize,
+    out_tree: *mut *mut MerkletreeHandle,
+) -> MerkletreeStatus {
+    guard(|| {
+        let leaves = slice_from_raw(leaves as *const Blake2bNode, leaf_count)?;
+        let tree = MerkleTree::from_leaves(leaves.iter().copied())?;
+        if out_tree.is_null() {
+            return Err(MerkletreeStatus::NullPointer);
+        }
+        out_tree.write(Box::into_raw(Box::new(MerkletreeHandle(tree))));
+        Ok(())
+    })
+}
+
+/// Free a tree created with `merkletree_build`. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `tree` must be either null or a pointer returned by `merkletree_build` that was not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_free(tree: *mut MerkletreeHandle) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Write the root of the tree, `MERKLETREE_NODE_SIZE` bytes, to `out_root`.
+///
+/// # Safety
+///
+/// `tree` must be a valid tree handle, and `out_root` must point to `MERKLETREE_NODE_SIZE` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_root(
+    tree: *const MerkletreeHandle,
+    out_root: *mut u8,
+) -> MerkletreeStatus {
+    guard(|| {
+        let tree = ref_from_raw(tree)?;
+        write_out(out_root as *mut Blake2bNode, tree.0.root())
+    })
+}
+
+/// Write the number of leaves in the tree, including padding, to `out_leaf_count`.
+///
+/// # Safety
+///
+/// `tree` must be a valid tree handle, and `out_leaf_count` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_leaf_count(
+    tree: *const MerkletreeHandle,
+    out_leaf_count: *mut u32,
+) -> MerkletreeStatus {
+    guard(|| {
+        let tree = ref_from_raw(tree)?;
+        write_out(out_leaf_count, tree.0.leaf_count().get())
+    })
+}
+
+/// Extract the single proof of the leaf at `leaf_index`. The branch nodes, from bottom to top, are written to
+/// `out_branch`, which has space for `branch_capacity` nodes, and the number of nodes is written to `out_branch_len`.
+/// If the capacity is not enough, `MerkletreeStatus::BufferTooSmall` is returned, with the required number of nodes
+/// written to `out_branch_len`.
+///
+/// # Safety
+///
+/// `tree` must be a valid tree handle, `out_branch` must point to `branch_capacity * MERKLETREE_NODE_SIZE`
+/// writable bytes, and `out_branch_len` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_single_proof(
+    tree: *const MerkletreeHandle,
+    leaf_index: u32,
+    out_branch: *mut u8,
+    branch_capacity: usize,
+    out_branch_len: *mut usize,
+) -> MerkletreeStatus {
+    guard(|| {
+        let tree = ref_from_raw(tree)?;
+        let proof = SingleProofNodes::from_tree_leaf(&tree.0, leaf_index)?.into_values();
+        let branch = proof.branch();
+
+        write_out(out_branch_len, branch.len())?;
+        if branch.len() > branch_capacity {
+            return Err(MerkletreeStatus::BufferTooSmall);
+        }
+
+        let out_branch = slice_from_raw_mut(out_branch as *mut Blake2bNode, branch.len())?;
+        out_branch.copy_from_slice(branch);
+        Ok(())
+    })
+}
+
+/// Verify a single proof of `leaf`, at `leaf_index`, with `branch_len` branch nodes, against `root`.
+/// The verification result is written to `out_result`.
+///
+/// # Safety
+///
+/// `leaf` and `root` must point to `MERKLETREE_NODE_SIZE` readable bytes, `branch` must point to
+/// `branch_len * MERKLETREE_NODE_SIZE` readable bytes, and `out_result` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_verify_single_proof(
+    leaf: *const u8,
+    leaf_index: u32,
+    branch: *const u8,
+    branch_len: usize,
+    root: *const u8,
+    out_result: *mut MerkletreeVerifyResult,
+) -> MerkletreeStatus {
+    guard(|| {
+        let leaf = ref_from_raw(leaf as *const Blake2bNode)?;
+        let root = ref_from_raw(root as *const Blake2bNode)?;
+        let branch = slice_from_raw(branch as *const Blake2bNode, branch_len)?;
+
+        let proof =
+            SingleProofRef::<_, Blake2bHasher>::from_leaf_index_and_branch(leaf_index, branch);
+        write_out(out_result, proof.verify(*leaf, *root).into())
+    })
+}
+
+/// Extract a multi-proof for the leaves at `leaf_indices`, which must be sorted and unique.
+/// The absolute indices of the proof nodes are written to `out_node_indices`, and the nodes themselves to `out_nodes`,
+/// both of which have space for `nodes_capacity` elements. The number of proof nodes is written to `out_nodes_len`.
+/// If the capacity is not enough, `MerkletreeStatus::BufferTooSmall` is returned, with the required number of nodes
+/// written to `out_nodes_len`.
+///
+/// # Safety
+///
+/// `tree` must be a valid tree handle, `leaf_indices` must point to `leaf_indices_len` readable indices,
+/// `out_node_indices` must point to `nodes_capacity` writable indices, `out_nodes` must point to
+/// `nodes_capacity * MERKLETREE_NODE_SIZE` writable bytes, and `out_nodes_len` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn merkletree_multi_proof(
+    tree: *const MerkletreeHandle,
+    leaf_indices: *const u32,
+    leaf_indices_len: usize,
+    out_node_indices: *mut u32,
+    out_nodes: *mut u8,
+    nodes_capacity: usize,
+    out_nodes_len: *mut usize,
+) -> MerkletreeStatus {
+    guard(|| {
+        let tree = ref_from_raw(tree)?;
+        let leaf_indices = slice_from_raw(leaf_indices, leaf_indices_len)?;
+        let proof = MultiProofNodes::from_tree_leaves(&tree.0, leaf_indices)?.into_values();
+        let nodes = proof.nodes();
+
+        write_out(out_nodes_len, nodes.len())?;
+        if nodes.len() > nodes_capacity {
+            return Err(MerkletreeStatus::BufferTooSmall);
+        }
+
+        let out_node_indices = slice_from_raw_mut(out_node_indices, nodes.len())?;
+        let out_nodes = slice_from_raw_mut(out_nodes as *mut Blake2bNode, nodes.len())?;
+        for ((index, node), (out_index, out_node)) in nodes
+            .iter()
+            .zip(out_node_indices.iter_mut().zip(out_nodes.iter_mut()))
+        {
+            *out_index = *index;
+            *out_node = *node;
+        }
+        Ok(())
+    })
+}
+
+/// Verify a multi-proof against `root`. The proof is made of `nodes_len` nodes, at the absolute indices
+/// `node_indices`, of a tree with `tree_leaf_count` leaves. The leaves to verify are `leaves_len` nodes in `leaves`,
+/// at the leaf indices `leaf_indices`, which must be sorted and unique, like in `merkletree_multi_proof`, otherwise
+/// `MerkletreeStatus::UnsortedOrDuplicateIndices` is returned. The verification result is written to `out_result`.
+///
+/// # Safety
+///
+/// `node_indices` must point to `nodes_len` readable indices, `nodes` must point to
+/// `nodes_len * MERKLETREE_NODE_SIZE` readable bytes, `leaf_indices` must point to `leaves_len` readable indices,
+/// `leaves` must point to `leaves_len * MERKLETREE_NODE_SIZE` readable bytes, `root` must point to
+/// `MERKLETREE_NODE_SIZE` readable bytes, and `out_result` must be a valid pointer to write to.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn merkletree_verify_multi_proof(
+    tree_leaf_count: u32,
+    node_indices: *const u32,
+    nodes: *const u8,
+    nodes_len: usize,
+    leaf_indices: *const u32,
+    leaves: *const u8,
+    leaves_len: usize,
+    root: *const u8,
+    out_result: *mut MerkletreeVerifyResult,
+) -> MerkletreeStatus {
+    guard(|| {
+        let node_indices = slice_from_raw(node_indices, nodes_len)?;
+        let nodes = slice_from_raw(nodes as *const Blake2bNode, nodes_len)?;
+        let leaf_indices = slice_from_raw(leaf_indices, leaves_len)?;
+        let leaves = slice_from_raw(leaves as *const Blake2bNode, leaves_len)?;
+        let root = ref_from_raw(root as *const Blake2bNode)?;
+        // Duplicates would otherwise collapse into a single leaf of the map
+        if leaf_indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(MerkletreeStatus::UnsortedOrDuplicateIndices);
+        }
+
+        let proof = MultiProofHashes::<_, Blake2bHasher>::from_leaf_count_and_nodes(
+            tree_leaf_count,
+            node_indices
+                .iter()
+                .copied()
+                .zip(nodes.iter().copied())
+                .collect(),
+        );
+        let leaves = leaf_indices
+            .iter()
+            .copied()
+            .zip(leaves.iter().copied())
+            .collect::<BTreeMap<_, _>>();
+
+        let result = proof.verify_by_levels(leaves, *root)?;
+        write_out(out_result, result.into())
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,378 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ptr;
+
+use crate::merkle::proof::single::SingleProofHashes;
+
+use super::*;
+
+fn gen_leaves(n: u32) -> Vec<Blake2bNode> {
+    (0..n)
+        .map(|i| Blake2bHasher::hash_data(i.to_be_bytes()))
+        .collect()
+}
+
+/// Build a tree through the C interface
+fn build(leaves: &[Blake2bNode]) -> *mut MerkletreeHandle {
+    let mut tree = ptr::null_mut();
+    let status = unsafe { merkletree_build(leaves.as_ptr() as *const u8, leaves.len(), &mut tree) };
+    assert_eq!(status, MerkletreeStatus::Ok);
+    assert!(!tree.is_null());
+    tree
+}
+
+fn root(tree: *const MerkletreeHandle) -> Blake2bNode {
+    let mut root = [0u8; MERKLETREE_NODE_SIZE];
+    let status = unsafe { merkletree_root(tree, root.as_mut_ptr()) };
+    assert_eq!(status, MerkletreeStatus::Ok);
+    root
+}
+
+fn single_proof(tree: *const MerkletreeHandle, leaf_index: u32) -> Vec<Blake2bNode> {
+    let mut branch_len = 0;
+    let status =
+        unsafe { merkletree_single_proof(tree, leaf_index, ptr::null_mut(), 0, &mut branch_len) };
+    if branch_len == 0 {
+        assert_eq!(status, MerkletreeStatus::Ok);
+        return Vec::new();
+    }
+    assert_eq!(status, MerkletreeStatus::BufferTooSmall);
+
+    let mut branch = vec![[0u8; MERKLETREE_NODE_SIZE]; branch_len];
+    let status = unsafe {
+        merkletree_single_proof(
+            tree,
+            leaf_index,
+            branch.as_mut_ptr() as *mut u8,
+            branch.len(),
+            &mut branch_len,
+        )
+    };
+    assert_eq!(status, MerkletreeStatus::Ok);
+    assert_eq!(branch_len, branch.len());
+    branch
+}
+
+fn verify_single_proof(
+    leaf: &Blake2bNode,
+    leaf_index: u32,
+    branch: &[Blake2bNode],
+    root: &Blake2bNode,
+) -> MerkletreeVerifyResult {
+    let mut result = MerkletreeVerifyResult::Failed;
+    let status = unsafe {
+        merkletree_verify_single_proof(
+            leaf.as_ptr(),
+            leaf_index,
+            branch.as_ptr() as *const u8,
+            branch.len(),
+            root.as_ptr(),
+            &mut result,
+        )
+    };
+    assert_eq!(status, MerkletreeStatus::Ok);
+    result
+}
+
+fn multi_proof(tree: *const MerkletreeHandle, leaf_indices: &[u32]) -> Vec<(u32, Blake2bNode)> {
+    let mut nodes_len = 0;
+    let status = unsafe {
+        merkletree_multi_proof(
+            tree,
+            leaf_indices.as_ptr(),
+            leaf_indices.len(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+            &mut nodes_len,
+        )
+    };
+    if nodes_len == 0 {
+        assert_eq!(status, MerkletreeStatus::Ok);
+        return Vec::new();
+    }
+    assert_eq!(status, MerkletreeStatus::BufferTooSmall);
+
+    let mut indices = vec![0u32; nodes_len];
+    let mut nodes = vec![[0u8; MERKLETREE_NODE_SIZE]; nodes_len];
+    let status = unsafe {
+        merkletree_multi_proof(
+            tree,
+            leaf_indices.as_ptr(),
+            leaf_indices.len(),
+            indices.as_mut_ptr(),
+            nodes.as_mut_ptr() as *mut u8,
+            nodes_len,
+            &mut nodes_len,
+        )
+    };
+    assert_eq!(status, MerkletreeStatus::Ok);
+    indices.into_iter().zip(nodes).collect()
+}
+
+fn verify_multi_proof(
+    tree_leaf_count: u32,
+    proof: &[(u32, Blake2bNode)],
+    leaves: &[(u32, Blake2bNode)],
+    root: &Blake2bNode,
+) -> Result<MerkletreeVerifyResult, MerkletreeStatus> {
+    let (node_indices, nodes): (Vec<_>, Vec<_>) = proof.iter().copied().unzip();
+    let (leaf_indices, leaves): (Vec<_>, Vec<_>) = leaves.iter().copied().unzip();
+    let mut result = MerkletreeVerifyResult::Failed;
+    let status = unsafe {
+        merkletree_verify_multi_proof(
+            tree_leaf_count,
+            node_indices.as_ptr(),
+            nodes.as_ptr() as *const u8,
+            nodes.len(),
+            leaf_indices.as_ptr(),
+            leaves.as_ptr() as *const u8,
+            leaves.len(),
+            root.as_ptr(),
+            &mut result,
+        )
+    };
+    match status {
+        MerkletreeStatus::Ok => Ok(result),
+        _ => Err(status),
+    }
+}
+
+#[test]
+fn build_and_root() {
+    for leaf_count in 1..40 {
+        let leaves = gen_leaves(leaf_count);
+        let expected =
+            MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+
+        let tree = build(&leaves);
+        assert_eq!(root(tree), expected.root());
+
+        let mut tree_leaf_count = 0;
+        let status = unsafe { merkletree_leaf_count(tree, &mut tree_leaf_count) };
+        assert_eq!(status, MerkletreeStatus::Ok);
+        assert_eq!(tree_leaf_count, expected.leaf_count().get());
+
+        unsafe { merkletree_free(tree) };
+    }
+}
+
+#[test]
+fn build_errors() {
+    let mut tree = ptr::null_mut();
+    let status = unsafe { merkletree_build(ptr::null(), 0, &mut tree) };
+    assert_eq!(status, MerkletreeStatus::EmptyInput);
+    assert!(tree.is_null());
+
+    let status = unsafe { merkletree_build(ptr::null(), 5, &mut tree) };
+    assert_eq!(status, MerkletreeStatus::NullPointer);
+    assert!(tree.is_null());
+
+    let leaves = gen_leaves(3);
+    let status =
+        unsafe { merkletree_build(leaves.as_ptr() as *const u8, leaves.len(), ptr::null_mut()) };
+    assert_eq!(status, MerkletreeStatus::NullPointer);
+
+    // Freeing null is fine
+    unsafe { merkletree_free(ptr::null_mut()) };
+}
+
+#[test]
+fn single_proofs() {
+    for leaf_count in 1..20 {
+        let leaves = gen_leaves(leaf_count);
+        let expected =
+            MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+        let tree = build(&leaves);
+        let tree_root = root(tree);
+
+        for leaf_index in 0..leaf_count {
+            let leaf = expected.node_value_from_bottom(0, leaf_index).unwrap();
+            let branch = single_proof(tree, leaf_index);
+            let expected_proof: SingleProofHashes<_, Blake2bHasher> =
+                SingleProofNodes::from_tree_leaf(&expected, leaf_index)
+                    .unwrap()
+                    .into_values();
+            assert_eq!(branch, expected_proof.branch());
+
+            let expected_result =
+                MerkletreeVerifyResult::from(expected_proof.verify(leaf, tree_root));
+            assert_eq!(
+                verify_single_proof(&leaf, leaf_index, &branch, &tree_root),
+                expected_result
+            );
+            assert_ne!(expected_result, MerkletreeVerifyResult::Failed);
+
+            let wrong_leaf = Blake2bHasher::hash_data(leaf);
+            assert_eq!(
+                verify_single_proof(&wrong_leaf, leaf_index, &branch, &tree_root),
+                MerkletreeVerifyResult::Failed
+            );
+        }
+
+        // Both padding leaves and indices beyond the tree are refused
+        for leaf_index in leaf_count..=expected.leaf_count().get() {
+            let mut branch_len = 0;
+            let status = unsafe {
+                merkletree_single_proof(tree, leaf_index, ptr::null_mut(), 0, &mut branch_len)
+            };
+            assert_eq!(status, MerkletreeStatus::IndexOutOfRange);
+        }
+
+        unsafe { merkletree_free(tree) };
+    }
+}
+
+#[test]
+fn multi_proofs() {
+    let leaves = gen_leaves(13);
+    let expected = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+    let tree = build(&leaves);
+    let tree_root = root(tree);
+    let tree_leaf_count = expected.leaf_count().get();
+
+    let cases: &[&[u32]] = &[
+        &[0],
+        &[12],
+        &[0, 1],
+        &[1, 2, 7],
+        &[3, 8, 12],
+        &[0, 5, 6, 11, 12],
+    ];
+    for leaf_indices in cases {
+        let proof = multi_proof(tree, leaf_indices);
+        let expected_proof = MultiProofNodes::from_tree_leaves(&expected, leaf_indices)
+            .unwrap()
+            .into_values();
+        assert_eq!(
+            proof,
+            expected_proof
+                .nodes()
+                .iter()
+                .map(|(i, n)| (*i, *n))
+                .collect::<Vec<_>>()
+        );
+
+        let proven_leaves = leaf_indices
+            .iter()
+            .map(|i| (*i, expected.node_value_from_bottom(0, *i).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            verify_multi_proof(tree_leaf_count, &proof, &proven_leaves, &tree_root),
+            Ok(MerkletreeVerifyResult::PassedDecisively)
+        );
+
+        let mut tampered_leaves = proven_leaves.clone();
+        tampered_leaves[0].1 = Blake2bHasher::hash_data(tampered_leaves[0].1);
+        assert_eq!(
+            verify_multi_proof(tree_leaf_count, &proof, &tampered_leaves, &tree_root),
+            Ok(MerkletreeVerifyResult::Failed)
+        );
+
+        assert_eq!(
+            verify_multi_proof(tree_leaf_count, &proof[1..], &proven_leaves, &tree_root),
+            Err(MerkletreeStatus::MissingProofNode)
+        );
+        assert_eq!(
+            verify_multi_proof(tree_leaf_count + 1, &proof, &proven_leaves, &tree_root),
+            Err(MerkletreeStatus::InvalidProof)
+        );
+
+        // A wrong leaf must not be hidden by a duplicate index with the right leaf
+        let garbage = (proven_leaves[0].0, Blake2bNode::default());
+        let with_duplicate = [&[garbage], proven_leaves.as_slice()].concat();
+        assert_eq!(
+            verify_multi_proof(tree_leaf_count, &proof, &with_duplicate, &tree_root),
+            Err(MerkletreeStatus::UnsortedOrDuplicateIndices)
+        );
+        if proven_leaves.len() > 1 {
+            let mut unsorted = proven_leaves.clone();
+            unsorted.reverse();
+            assert_eq!(
+                verify_multi_proof(tree_leaf_count, &proof, &unsorted, &tree_root),
+                Err(MerkletreeStatus::UnsortedOrDuplicateIndices)
+            );
+        }
+    }
+
+    let mut nodes_len = 0;
+    let with_padding = [3u32, 13];
+    let status = unsafe {
+        merkletree_multi_proof(
+            tree,
+            with_padding.as_ptr(),
+            with_padding.len(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+            &mut nodes_len,
+        )
+    };
+    assert_eq!(status, MerkletreeStatus::IndexOutOfRange);
+
+    let unsorted = [3u32, 1];
+    let status = unsafe {
+        merkletree_multi_proof(
+            tree,
+            unsorted.as_ptr(),
+            unsorted.len(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+            &mut nodes_len,
+        )
+    };
+    assert_eq!(status, MerkletreeStatus::UnsortedOrDuplicateIndices);
+
+    let status = unsafe {
+        merkletree_multi_proof(
+            tree,
+            ptr::null(),
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+            &mut nodes_len,
+        )
+    };
+    assert_eq!(status, MerkletreeStatus::EmptyInput);
+
+    unsafe { merkletree_free(tree) };
+}
+
+#[test]
+fn multi_proof_of_huge_tree() {
+    let leaf = Blake2bHasher::hash_data("leaf");
+    let single_proof =
+        SingleProofHashes::<_, Blake2bHasher>::from_leaf_index_and_branch(7, gen_leaves(30));
+    let root = single_proof.compute_root(leaf);
+    let proof = MultiProofHashes::from_single_proofs(&[single_proof])
+        .unwrap()
+        .nodes()
+        .iter()
+        .map(|(i, n)| (*i, *n))
+        .collect::<Vec<_>>();
+
+    // Only the nodes of the proof are visited, not the whole tree
+    assert_eq!(
+        verify_multi_proof(1 << 30, &proof, &[(7, leaf)], &root),
+        Ok(MerkletreeVerifyResult::PassedDecisively)
+    );
+    assert_eq!(
+        verify_multi_proof(1 << 31, &proof, &[(7, leaf)], &root),
+        Err(MerkletreeStatus::InvalidProof)
+    );
+}
@@ -19,7 +19,7 @@
 use blake2::digest::{generic_array::GenericArray, typenum, Digest};
 use fixed_hash::construct_fixed_hash;
 
-use crate::hasher::PairHasher;
+use crate::hasher::{LeafCountCommitter, PairHasher};
 
 construct_fixed_hash! {
     pub struct HashedData(32);
@@ -74,3 +74,12 @@ impl PairHasher for HashAlgo {
         h.finalize_reset().into()
     }
 }
+
+impl LeafCountCommitter for HashAlgo {
+    fn commit_leaf_count(root: &Self::NodeType, leaf_count: u32) -> Self::NodeType {
+        let mut h = Blake2bHasher::new();
+        Digest::update(&mut h, root);
+        Digest::update(&mut h, leaf_count.to_le_bytes());
+        h.finalize_reset().into()
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use blake2::{digest::typenum, Digest};
+
+use super::{LeafCountCommitter, PairHasher};
+
+type Blake2b32 = blake2::Blake2b<typenum::U32>;
+
+/// The node type of trees that use `Blake2bHasher`.
+pub type Blake2bNode = [u8; 32];
+
+/// A hasher that uses Blake2b with 32 bytes output, which is the hasher used by the Mintlayer blockchain.
+/// A pair of nodes is hashed by concatenating them, and padding nodes are created by hashing the previous leaf.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Blake2bHasher;
+
+impl Blake2bHasher {
+    /// Hash arbitrary data into a node. This can be used to create leaves from data.
+    pub fn hash_data<D: AsRef<[u8]>>(data: D) -> Blake2bNode {
+        Blake2b32::digest(data).into()
+    }
+}
+
+impl PairHasher for Blake2bHasher {
+    type NodeType = Blake2bNode;
+
+    fn hash_pair(left: &Self::NodeType, right: &Self::NodeType) -> Self::NodeType {
+        let mut h = Blake2b32::new();
+        Digest::update(&mut h, left);
+        Digest::update(&mut h, right);
+        h.finalize().into()
+    }
+
+    fn hash_single(data: &Self::NodeType) -> Self::NodeType {
+        Self::hash_data(data)
+    }
+}
+
+impl LeafCountCommitter for Blake2bHasher {
+    /// The root is concatenated with the leaf count in little endian, which makes the input 36 bytes,
+    /// unlike the inputs of `hash_pair` and `hash_single`.
+    fn commit_leaf_count(root: &Self::NodeType, leaf_count: u32) -> Self::NodeType {
+        let mut h = Blake2b32::new();
+        Digest::update(&mut h, root);
+        Digest::update(&mut h, leaf_count.to_le_bytes());
+        h.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        internal::{HashAlgo, HashedData},
+        tree::MerkleTree,
+    };
+
+    use super::*;
+
+    #[test]
+    fn same_as_reference_hasher() {
+        for leaf_count in 1..20u64 {
+            let leaves = (0..leaf_count)
+                .map(|i| Blake2bHasher::hash_data(i.to_be_bytes()))
+                .collect::<Vec<_>>();
+
+            let t = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+            let reference_t = MerkleTree::<HashedData, HashAlgo>::from_leaves(
+                leaves.into_iter().map(HashedData::from),
+            )
+            .unwrap();
+
+            assert_eq!(HashedData::from(t.root()), reference_t.root());
+            assert_eq!(
+                HashedData::from(t.count_committed_root()),
+                reference_t.count_committed_root()
+            );
+        }
+    }
+
+    #[test]
+    fn count_committed_root_depends_on_leaf_count() {
+        let leaves = (0..5u64)
+            .map(|i| Blake2bHasher::hash_data(i.to_be_bytes()))
+            .collect::<Vec<_>>();
+        let t5 = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves).unwrap();
+        let t8 = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(
+            (0..8).map(|i| t5.node_value_from_bottom(0, i).unwrap()),
+        )
+        .unwrap();
+
+        assert_eq!(t5.root(), t8.root());
+        assert_ne!(t5.count_committed_root(), t8.count_committed_root());
+        assert_eq!(
+            t5.count_committed_root(),
+            Blake2bHasher::commit_leaf_count(&t5.root(), 5)
+        );
+    }
+}
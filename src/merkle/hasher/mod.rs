@@ -27,3 +27,16 @@ pub trait PairHasher: Sized + Clone {
     /// Hash a pair of nodes and return the hash value.
     fn hash_pair(left: &Self::NodeType, right: &Self::NodeType) -> Self::NodeType;
 }
+
+/// An extension of `PairHasher` that can commit to the number of leaves of a tree in its root.
+/// Since padding is deterministic, the root of a tree alone doesn't tell how many of its leaves are real.
+/// Mixing the real leaf count into the root makes it possible to prove it, e.g., that a leaf is the last one.
+pub trait LeafCountCommitter: PairHasher {
+    /// Combine the root of a tree with the number of its leaves before padding.
+    /// The result must not be computable by `hash_pair` or `hash_single` from other nodes,
+    /// so that a count-committed root can't be mistaken for a node of a tree.
+    fn commit_leaf_count(root: &Self::NodeType, leaf_count: u32) -> Self::NodeType;
+}
+
+#[cfg(feature = "blake2b")]
+pub mod blake2b;
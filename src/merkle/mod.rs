@@ -13,15 +13,51 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::vec::Vec;
+
 pub mod hasher;
 pub mod pos;
 pub mod proof;
+pub mod reconcile;
+pub mod render;
+#[cfg(feature = "stream")]
+pub mod stream;
 pub mod tree;
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 pub enum MerkleTreeFormError {
     #[error("Merkle tree input too small: {0}")]
     TooSmall(usize),
+    #[error("Merkle tree input too large: {0} leaves after padding")]
+    TooLarge(usize),
+    #[error("Node store error: {0}")]
+    StoreError(#[from] NodeStoreError),
+    #[error("Node store length {0} isn't the size of a supported tree")]
+    InvalidStoreSize(usize),
+    #[error("Real leaf count {0} doesn't match the {1} leaves of the tree in the store")]
+    RealLeafCountMismatch(u32, u32),
+    #[error("Real leaf count {0} doesn't match the {1} leaves of the tree in the store (64-bit)")]
+    RealLeafCountMismatch64(u64, u64),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum NodeStoreError {
+    #[error("Nodes must be written contiguously: write at index {0} vs store length {1}")]
+    NonContiguousWrite(usize, usize),
+    #[error("Node store must be empty to create a tree in it, but it has {0} nodes")]
+    NotEmpty(usize),
+    #[error("File length {0} isn't the size of a supported tree of {1}-byte nodes")]
+    InvalidFileLength(u64, usize),
+    #[cfg(feature = "std")]
+    #[error("Node store I/O error: {0}")]
+    Io(std::io::ErrorKind),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for NodeStoreError {
+    fn from(err: std::io::Error) -> Self {
+        NodeStoreError::Io(err.kind())
+    }
 }
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
@@ -34,8 +70,20 @@ pub enum MerkleTreeProofExtractionError {
     LeafIndexOutOfRange(u32, u32),
     #[error("Leaves indices must be sorted in ascending: {0:?}")]
     UnsortedOrUniqueLeavesIndices(Vec<u32>),
+    #[error("Leaf index {0} is a padding leaf, as the tree has {1} real leaves")]
+    PaddingLeaf(u32, u32),
+    #[error("The node position is in a tree of {0} nodes, but the tree has {1} nodes")]
+    TreeSizeMismatch(u32, u32),
     #[error("Access error: {0}")]
     AccessError(#[from] MerkleTreeAccessError),
+    #[error("One or more indexes are larger than the number of leaves in the tree (64-bit): {0:?} vs leaf-count {1}")]
+    IndexOutOfRange64(Vec<u64>, u64),
+    #[error("Leaf index out of range (64-bit): {0} vs leaves count {1}")]
+    LeafIndexOutOfRange64(u64, u64),
+    #[error("Leaves indices must be sorted in ascending (64-bit): {0:?}")]
+    UnsortedOrUniqueLeavesIndices64(Vec<u64>),
+    #[error("Leaf index {0} is a padding leaf, as the tree has {1} real leaves (64-bit)")]
+    PaddingLeaf64(u64, u64),
 }
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
@@ -48,6 +96,109 @@ pub enum MerkleTreeAccessError {
     IterStartIndexOutOfRange(u32, u32),
 }
 
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MerkleTreeDiffError {
+    #[error("Only trees of the same size can be compared: {0} vs {1} leaves")]
+    LeafCountMismatch(u32, u32),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReconcileError {
+    #[error("Only trees of the same size can be reconciled: {0} vs {1} leaves")]
+    LeafCountMismatch(u32, u32),
+    #[error("The response doesn't match the request that was sent")]
+    UnexpectedResponse,
+    #[error("Expected {0} nodes in the response, but got {1}")]
+    NodeCountMismatch(usize, usize),
+    #[error("The children of the node at index {0} don't hash to it")]
+    InvalidChildren(u32),
+    #[error("The root and the real leaf count don't match the count-committed root")]
+    CountCommittedRootMismatch,
+    #[error("The request was rejected by the peer: {0}")]
+    Rejected(ReconcileRejection),
+}
+
+/// The reason for which a peer rejects a reconciliation request, which is sent back to the requesting peer.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReconcileRejection {
+    #[error("The root must be requested first")]
+    RootNotRequested,
+    #[error("More rounds of node requests than the levels of the tree")]
+    TooManyRounds,
+    #[error("More nodes were requested than the tree has: {0}")]
+    TooManyNodes(usize),
+    #[error("Node index out of range: {0} vs tree size {1}")]
+    IndexOutOfRange(u32, u32),
+}
+
+#[cfg(feature = "stream")]
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum StreamError {
+    #[error("Stream I/O error: {0}")]
+    Io(std::io::ErrorKind),
+    #[error("Chunk size must be larger than zero")]
+    ZeroChunkSize,
+    #[error("A stream of {0} bytes has too many chunks of {1} bytes for a supported tree")]
+    TooManyChunks(u64, u32),
+    #[error("Tree of chunks error: {0}")]
+    TreeFormError(#[from] MerkleTreeFormError),
+    #[error("The header of the stream doesn't match the expected root")]
+    RootMismatch,
+    #[error("Chunk {0} doesn't match its hash")]
+    ChunkHashMismatch(u32),
+    #[error("The children of the node at index {0} don't hash to it")]
+    NodeHashMismatch(u32),
+    #[error("The source changed while it was being encoded, at chunk {0}")]
+    SourceChanged(u32),
+    #[error("Invalid chunk range {0}..{1} for a stream of {2} chunks")]
+    InvalidChunkRange(u32, u32, u32),
+}
+
+#[cfg(feature = "stream")]
+impl From<std::io::Error> for StreamError {
+    fn from(err: std::io::Error) -> Self {
+        StreamError::Io(err.kind())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MerkleTreeUpdateError {
+    #[error("Leaf index out of range: {0} vs leaves count {1}")]
+    LeafIndexOutOfRange(u32, u32),
+    #[error("Leaf index {0} is a padding leaf, as the tree has {1} real leaves; padding leaves can't be updated")]
+    PaddingLeaf(u32, u32),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MerkleProofConversionError {
+    #[error("Proof branch length mismatch: expected {0} vs found {1}")]
+    BranchLengthMismatch(usize, usize),
+    #[error("Only proofs of leaves can be converted, but the proof is of a node at level {0}")]
+    NotLeafLevel(u32),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum MerkleProofCombinationError {
+    #[error("No proofs were provided to be combined")]
+    NoProofsProvided,
+    #[error(
+        "Proofs branches have different lengths, hence they're for different trees: {0} vs {1}"
+    )]
+    BranchLengthMismatch(usize, usize),
+    #[error("Proof branch is too long for a supported tree: {0}")]
+    BranchTooLong(usize),
+    #[error("Leaf index out of range: {0} vs leaves count {1}")]
+    LeafIndexOutOfRange(u32, u32),
+    #[error("Proofs have conflicting hashes for the node at absolute index {0}")]
+    ConflictingNodes(u32),
+    #[error("Proofs are for trees with different leaf counts: {0} vs {1}")]
+    TreeLeafCountMismatch(u32, u32),
+    #[error("Invalid tree leaf count in proof: {0}")]
+    InvalidTreeLeafCount(u32),
+    #[error("Only proofs of leaves can be combined, but a proof is of a node at level {0}")]
+    NotLeafLevel(u32),
+}
+
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 pub enum MerkleProofVerificationError {
     #[error("No leaves provided")]
@@ -62,4 +213,22 @@ pub enum MerkleProofVerificationError {
     RequiredNodeMissing(u32),
     #[error("Tree size arithmetic error: {0}")]
     TreeSizeArithmeticError(u32),
+    #[error("Proof branch is too long for a supported tree: {0}")]
+    BranchTooLong(usize),
+    #[error("Leaf index out of range: {0} vs leaves count {1}")]
+    LeafIndexOutOfRange(u32, u32),
+    #[error("Proof branch length doesn't match the leaves count: expected {0} vs found {1}")]
+    BranchLengthMismatch(usize, usize),
+    #[error("Node index out of range: {0} vs {1} nodes in its level")]
+    NodeIndexOutOfRange(u32, u32),
+    #[error("Only proofs of leaves are accepted, but the proof is of a node at level {0}")]
+    NotLeafLevel(u32),
+    #[error("Invalid tree size (64-bit)")]
+    InvalidTreeLeavesCount64(u64),
+    #[error("One or more leaves have indices out of range (64-bit): {0:?} vs leaves count {1}")]
+    LeavesIndicesOutOfRange64(Vec<u64>, u64),
+    #[error("One or more nodes have indices out of range (64-bit): {0:?} vs tree size {1}")]
+    NodesIndicesOutOfRange64(Vec<u64>, u64),
+    #[error("A required node is missing (64-bit). Index of node: {0}")]
+    RequiredNodeMissing64(u64),
 }
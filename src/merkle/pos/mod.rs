@@ -14,8 +14,9 @@
 // limitations under the License.
 
 pub mod node_kind;
+pub mod position64;
 
-use std::num::NonZeroU32;
+use core::num::NonZeroU32;
 
 use self::node_kind::NodeKind;
 
@@ -155,6 +156,88 @@ impl NodePosition {
     pub fn into_iter_parents(self) -> MerkleTreeNodePositionParentIterator {
         MerkleTreeNodePositionParentIterator { node: Some(self) }
     }
+
+    /// Returns the left child of the node, or None if it's a leaf.
+    pub fn left_child(&self) -> Option<Self> {
+        let (level, index) = self.position();
+        if level == 0 {
+            return None;
+        }
+
+        let child = NodePosition::from_position(self.tree_size, level - 1, index * 2)
+            .expect("Child index must be in range");
+        Some(child)
+    }
+
+    /// Returns the right child of the node, or None if it's a leaf.
+    pub fn right_child(&self) -> Option<Self> {
+        let left = self.left_child()?;
+        left.sibling()
+    }
+
+    /// Returns the left and right children of the node, or None if it's a leaf.
+    pub fn children(&self) -> Option<(Self, Self)> {
+        let left = self.left_child()?;
+        let right = left.sibling().expect("A child is never the root");
+        Some((left, right))
+    }
+
+    /// Returns the range of the indices of the leaves that are under this node.
+    /// For a leaf, that's only itself, and for the root, that's all the leaves.
+    pub fn leaf_range(&self) -> core::ops::Range<u32> {
+        let (level, index) = self.position();
+        (index << level)..((index + 1) << level)
+    }
+
+    /// Returns the nodes under this node at the given level, from left to right. If the level is the
+    /// level of this node, only this node is returned. Returns None if the level is above this node.
+    pub fn descendants_at_level(
+        &self,
+        level_from_bottom: u32,
+    ) -> Option<impl Iterator<Item = NodePosition>> {
+        let (level, index) = self.position();
+        if level_from_bottom > level {
+            return None;
+        }
+
+        let levels_down = level - level_from_bottom;
+        let level_start = self
+            .tree_size
+            .level_start(level_from_bottom)
+            .expect("Level is below a valid level");
+        let first = level_start + (index << levels_down);
+        let tree_size = self.tree_size;
+
+        Some(
+            (first..first + (1 << levels_down)).map(move |absolute_index| Self {
+                tree_size,
+                absolute_index,
+            }),
+        )
+    }
+
+    /// Returns the lowest node that has both nodes under it (or is one of them).
+    /// Returns None if the nodes are in trees of different sizes.
+    pub fn lowest_common_ancestor(a: &Self, b: &Self) -> Option<Self> {
+        if a.tree_size != b.tree_size {
+            return None;
+        }
+
+        let (level_a, index_a) = a.position();
+        let (level_b, index_b) = b.position();
+
+        // Move both nodes to the same level, then move up until their indices are equal,
+        // which is the number of bits of the indices after the highest different bit.
+        let level = core::cmp::max(level_a, level_b);
+        let index_a = index_a >> (level - level_a);
+        let index_b = index_b >> (level - level_b);
+        let levels_up = u32::BITS - (index_a ^ index_b).leading_zeros();
+
+        let ancestor =
+            NodePosition::from_position(a.tree_size, level + levels_up, index_a >> levels_up)
+                .expect("The ancestor is at most the root");
+        Some(ancestor)
+    }
 }
 
 /// An iterator over the parents of a node, given its position.
@@ -0,0 +1,223 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::merkle::tree::tree_size::TreeSize64;
+
+use super::{node_kind::NodeKind, NodePosition};
+
+/// Same as `NodePosition`, but with 64-bit indices, for trees of size `TreeSize64`.
+/// See `NodePosition` for the math of converting position representations.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodePosition64 {
+    tree_size: TreeSize64,
+    absolute_index: u64,
+}
+
+impl NodePosition64 {
+    pub fn from_abs_index(tree_size: TreeSize64, absolute_index: u64) -> Option<Self> {
+        if absolute_index >= tree_size.get() {
+            return None;
+        }
+
+        Some(Self {
+            tree_size,
+            absolute_index,
+        })
+    }
+
+    pub fn from_position(
+        tree_size: TreeSize64,
+        level_from_bottom: u32,
+        index_in_level: u64,
+    ) -> Option<Self> {
+        let level_start = tree_size.level_start(level_from_bottom)?;
+        let level_from_top = tree_size.level_count().get() - level_from_bottom - 1;
+
+        // number of nodes in the level
+        let index_in_level_size = 1u64 << level_from_top;
+
+        if index_in_level >= index_in_level_size {
+            return None;
+        }
+
+        Some(Self {
+            tree_size,
+            absolute_index: level_start + index_in_level,
+        })
+    }
+
+    pub fn tree_size(&self) -> TreeSize64 {
+        self.tree_size
+    }
+
+    pub fn abs_index(&self) -> u64 {
+        self.absolute_index
+    }
+
+    /// Returns the level and index in the level of the node, as in (level, index).
+    pub fn position(&self) -> (u32, u64) {
+        let level_from_top = (self.tree_size.get() - self.abs_index() + 1)
+            .next_power_of_two()
+            .trailing_zeros();
+
+        let level = self.tree_size.level_count().get() - level_from_top;
+        let level_start = self
+            .tree_size
+            .level_start(level)
+            .expect("Abs index is valid");
+        (level, self.abs_index() - level_start)
+    }
+
+    pub fn node_kind(&self) -> NodeKind {
+        if self.abs_index() == self.tree_size.get() - 1 {
+            return NodeKind::Root;
+        }
+
+        if self.abs_index() % 2 == 0 {
+            NodeKind::LeftChild
+        } else {
+            NodeKind::RightChild
+        }
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        let (level, index) = self.position();
+        if level == self.tree_size.level_count().get() - 1 {
+            return None;
+        }
+
+        let parent = NodePosition64::from_position(self.tree_size, level + 1, index / 2)
+            .expect("Parent index must be in range");
+        Some(parent)
+    }
+
+    pub fn sibling(&self) -> Option<Self> {
+        if self.node_kind().is_root() {
+            return None;
+        }
+
+        Some(Self {
+            tree_size: self.tree_size,
+            absolute_index: self.absolute_index ^ 1,
+        })
+    }
+
+    pub fn into_iter_parents(self) -> NodePosition64ParentIterator {
+        NodePosition64ParentIterator { node: Some(self) }
+    }
+}
+
+impl From<NodePosition> for NodePosition64 {
+    fn from(position: NodePosition) -> Self {
+        Self {
+            tree_size: position.tree_size().into(),
+            absolute_index: position.abs_index() as u64,
+        }
+    }
+}
+
+impl TryFrom<NodePosition64> for NodePosition {
+    type Error = NodePosition64;
+
+    /// Fails, returning the position back, if the tree is too large for `NodePosition`.
+    fn try_from(position: NodePosition64) -> Result<Self, Self::Error> {
+        let tree_size = position.tree_size().try_into().map_err(|_| position)?;
+        let absolute_index = u32::try_from(position.abs_index()).map_err(|_| position)?;
+        NodePosition::from_abs_index(tree_size, absolute_index).ok_or(position)
+    }
+}
+
+/// An iterator over the parents of a node, given its position, starting with the node itself.
+#[must_use]
+#[derive(Debug)]
+pub struct NodePosition64ParentIterator {
+    node: Option<NodePosition64>,
+}
+
+impl Iterator for NodePosition64ParentIterator {
+    type Item = NodePosition64;
+
+    fn next(&mut self) -> Option<NodePosition64> {
+        let res = self.node?;
+        self.node = res.parent();
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_as_32_bit_position() {
+        for tree_log_size in 1..10u32 {
+            let tree_size =
+                crate::tree::tree_size::TreeSize::from_u32((1 << tree_log_size) - 1).unwrap();
+            let tree_size_64 = TreeSize64::from(tree_size);
+            for abs_index in 0..tree_size.get() {
+                let pos = NodePosition::from_abs_index(tree_size, abs_index).unwrap();
+                let pos64 = NodePosition64::from_abs_index(tree_size_64, abs_index as u64).unwrap();
+                assert_eq!(NodePosition64::from(pos), pos64);
+                assert_eq!(NodePosition::try_from(pos64), Ok(pos));
+
+                let (level, index) = pos.position();
+                assert_eq!(pos64.position(), (level, index as u64));
+                assert_eq!(
+                    NodePosition64::from_position(tree_size_64, level, index as u64),
+                    Some(pos64)
+                );
+                assert_eq!(pos64.node_kind().is_left(), pos.node_kind().is_left());
+                assert_eq!(pos64.node_kind().is_root(), pos.node_kind().is_root());
+                assert_eq!(pos64.parent(), pos.parent().map(NodePosition64::from));
+                assert_eq!(pos64.sibling(), pos.sibling().map(NodePosition64::from));
+                assert!(pos64
+                    .into_iter_parents()
+                    .eq(pos.into_iter_parents().map(NodePosition64::from)));
+            }
+            assert_eq!(
+                NodePosition64::from_abs_index(tree_size_64, tree_size.get() as u64),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn beyond_32_bit_trees() {
+        let leaf_count = 1u64 << 40;
+        let tree_size = TreeSize64::from_leaf_count(leaf_count).unwrap();
+
+        let last_leaf = NodePosition64::from_position(tree_size, 0, leaf_count - 1).unwrap();
+        assert_eq!(last_leaf.abs_index(), leaf_count - 1);
+        assert_eq!(last_leaf.position(), (0, leaf_count - 1));
+        assert_eq!(
+            NodePosition64::from_position(tree_size, 0, leaf_count),
+            None
+        );
+        assert_eq!(NodePosition::try_from(last_leaf), Err(last_leaf));
+
+        let path = last_leaf.into_iter_parents().collect::<Vec<_>>();
+        assert_eq!(path.len(), 41);
+        for (level, node) in path.iter().enumerate() {
+            assert_eq!(node.position(), (level as u32, (leaf_count - 1) >> level));
+        }
+        let root = path.last().unwrap();
+        assert_eq!(root.abs_index(), tree_size.get() - 1);
+        assert!(root.node_kind().is_root());
+        assert_eq!(root.parent(), None);
+        assert_eq!(root.sibling(), None);
+        assert_eq!(last_leaf.sibling().unwrap().abs_index(), leaf_count - 2);
+    }
+}
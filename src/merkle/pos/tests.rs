@@ -404,3 +404,114 @@ fn absolute_index_from_bottom() {
         14
     );
 }
+
+#[test]
+fn children_exhaustive() {
+    for tree_log_size in 1..10u32 {
+        let tree_size: TreeSize = ((1 << tree_log_size) - 1u32).try_into().unwrap();
+        for abs_index in 0..tree_size.get() {
+            let pos = NodePosition::from_abs_index(tree_size, abs_index).unwrap();
+            let (level, index) = pos.position();
+
+            if level == 0 {
+                assert_eq!(pos.left_child(), None);
+                assert_eq!(pos.right_child(), None);
+                assert_eq!(pos.children(), None);
+                continue;
+            }
+
+            let (left, right) = pos.children().unwrap();
+            assert_eq!(pos.left_child(), Some(left));
+            assert_eq!(pos.right_child(), Some(right));
+            assert_eq!(left.position(), (level - 1, index * 2));
+            assert_eq!(right.position(), (level - 1, index * 2 + 1));
+            assert!(matches!(left.node_kind(), node_kind::NodeKind::LeftChild));
+            assert_eq!(left.sibling(), Some(right));
+            assert_eq!(left.parent(), Some(pos));
+            assert_eq!(right.parent(), Some(pos));
+        }
+    }
+}
+
+#[test]
+fn leaf_range_and_descendants_exhaustive() {
+    for tree_log_size in 1..10u32 {
+        let tree_size: TreeSize = ((1 << tree_log_size) - 1u32).try_into().unwrap();
+        let level_count = tree_size.level_count().get();
+        for abs_index in 0..tree_size.get() {
+            let pos = NodePosition::from_abs_index(tree_size, abs_index).unwrap();
+            let (level, index) = pos.position();
+
+            // The leaves whose parents pass through this node
+            let expected_leaves = (0..tree_size.leaf_count().get())
+                .filter(|leaf_index| {
+                    NodePosition::from_position(tree_size, 0, *leaf_index)
+                        .unwrap()
+                        .into_iter_parents()
+                        .any(|p| p == pos)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(pos.leaf_range().collect::<Vec<_>>(), expected_leaves);
+
+            for descendants_level in 0..level_count {
+                let descendants = pos.descendants_at_level(descendants_level);
+                if descendants_level > level {
+                    assert!(descendants.is_none());
+                    continue;
+                }
+                let descendants = descendants.unwrap().collect::<Vec<_>>();
+                assert_eq!(descendants.len(), 1 << (level - descendants_level));
+                assert!(descendants
+                    .windows(2)
+                    .all(|w| w[0].abs_index() + 1 == w[1].abs_index()));
+                for descendant in &descendants {
+                    assert_eq!(descendant.position().0, descendants_level);
+                    assert_eq!(
+                        descendant
+                            .into_iter_parents()
+                            .nth((level - descendants_level) as usize),
+                        Some(pos)
+                    );
+                }
+            }
+            assert_eq!(
+                pos.descendants_at_level(level).unwrap().collect::<Vec<_>>(),
+                vec![pos]
+            );
+            assert_eq!(
+                pos.descendants_at_level(0)
+                    .unwrap()
+                    .map(|p| p.position().1)
+                    .collect::<Vec<_>>(),
+                pos.leaf_range().collect::<Vec<_>>()
+            );
+            assert_eq!(pos.leaf_range().len(), 1 << level);
+            assert_eq!(pos.leaf_range().start, index << level);
+        }
+    }
+}
+
+#[test]
+fn lowest_common_ancestor_exhaustive() {
+    for tree_log_size in 1..7u32 {
+        let tree_size: TreeSize = ((1 << tree_log_size) - 1u32).try_into().unwrap();
+        for a in 0..tree_size.get() {
+            let a = NodePosition::from_abs_index(tree_size, a).unwrap();
+            for b in 0..tree_size.get() {
+                let b = NodePosition::from_abs_index(tree_size, b).unwrap();
+
+                // The first of the parents of `a` (including itself) that is also a parent of `b`
+                let expected = a
+                    .into_iter_parents()
+                    .find(|p| b.into_iter_parents().any(|q| q == *p))
+                    .unwrap();
+                assert_eq!(NodePosition::lowest_common_ancestor(&a, &b), Some(expected));
+                assert_eq!(NodePosition::lowest_common_ancestor(&b, &a), Some(expected));
+            }
+        }
+    }
+
+    let a = NodePosition::from_abs_index(3u32.try_into().unwrap(), 0).unwrap();
+    let b = NodePosition::from_abs_index(7u32.try_into().unwrap(), 0).unwrap();
+    assert_eq!(NodePosition::lowest_common_ancestor(&a, &b), None);
+}
@@ -0,0 +1,228 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opt-in diagnostics for proof verification. When a proof fails to verify, the result doesn't say
+//! which node is wrong. Given a reference of the expected nodes (e.g., the tree from which the proof was
+//! supposedly extracted), the path from a leaf to the root can be compared node by node, to find
+//! where it diverged.
+
+use alloc::collections::BTreeMap;
+
+use crate::merkle::{
+    hasher::PairHasher,
+    pos::{node_kind::NodeKind, NodePosition},
+    tree::{store::NodeStore, MerkleTree},
+    MerkleProofVerificationError,
+};
+
+use super::verify_result::ProofVerifyResult;
+
+/// A source of the expected nodes of a tree, to compare a proof against.
+pub trait ExpectedNodes<T> {
+    /// Returns the expected hash of the node at the given position, or None if it's not known.
+    fn expected_node(&self, position: NodePosition) -> Option<T>;
+}
+
+/// A reference tree provides all its nodes, as long as it has the same size as the proof's tree.
+impl<T: Clone, H, S: NodeStore<T>> ExpectedNodes<T> for MerkleTree<T, H, S> {
+    fn expected_node(&self, position: NodePosition) -> Option<T> {
+        if position.tree_size() != self.total_node_count() {
+            return None;
+        }
+        let (level, index_in_level) = position.position();
+        self.node_value_from_bottom(level, index_in_level)
+    }
+}
+
+/// Expected intermediate nodes, by their absolute index in the tree.
+impl<T: Clone> ExpectedNodes<T> for BTreeMap<u32, T> {
+    fn expected_node(&self, position: NodePosition) -> Option<T> {
+        self.get(&position.abs_index()).cloned()
+    }
+}
+
+/// Where a node on the path from a leaf to the root came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeSource {
+    /// The leaf that's being proven, or another proven leaf used as a sibling
+    Leaf,
+    /// A node that was provided in the proof
+    Proof,
+    /// A node that was computed by hashing its children
+    Computed,
+}
+
+/// The first node on the path from a leaf to the root that's different from the expected node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeMismatch<T> {
+    level: u32,
+    abs_index: u32,
+    source: NodeSource,
+    found: T,
+    expected: T,
+}
+
+impl<T> NodeMismatch<T> {
+    /// The level of the node, where zero is the level of the leaves
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn abs_index(&self) -> u32 {
+        self.abs_index
+    }
+
+    pub fn source(&self) -> NodeSource {
+        self.source
+    }
+
+    pub fn found(&self) -> &T {
+        &self.found
+    }
+
+    pub fn expected(&self) -> &T {
+        &self.expected
+    }
+}
+
+/// The result of verifying a single proof with diagnostics.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SingleProofDiagnostics<T> {
+    result: ProofVerifyResult,
+    mismatch: Option<NodeMismatch<T>>,
+}
+
+impl<T> SingleProofDiagnostics<T> {
+    pub(super) fn new(result: ProofVerifyResult, mismatch: Option<NodeMismatch<T>>) -> Self {
+        Self { result, mismatch }
+    }
+
+    /// The same result that `verify` returns
+    pub fn result(&self) -> ProofVerifyResult {
+        self.result
+    }
+
+    /// The first node, from the leaf up to the root, that's different from the expected one
+    pub fn mismatch(&self) -> Option<&NodeMismatch<T>> {
+        self.mismatch.as_ref()
+    }
+}
+
+/// The result of verifying a multi-proof with diagnostics.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiProofDiagnostics<T> {
+    result: ProofVerifyResult,
+    mismatches: BTreeMap<u32, NodeMismatch<T>>,
+}
+
+impl<T> MultiProofDiagnostics<T> {
+    pub(super) fn new(
+        result: ProofVerifyResult,
+        mismatches: BTreeMap<u32, NodeMismatch<T>>,
+    ) -> Self {
+        Self { result, mismatches }
+    }
+
+    /// The same result that `verify` returns
+    pub fn result(&self) -> ProofVerifyResult {
+        self.result
+    }
+
+    /// The leaves whose path to the root has a node that's different from the expected one,
+    /// by leaf index, with the first such node for each leaf.
+    pub fn mismatches(&self) -> &BTreeMap<u32, NodeMismatch<T>> {
+        &self.mismatches
+    }
+
+    /// The smallest index of a leaf whose path diverged from the expected nodes
+    pub fn first_failed_leaf(&self) -> Option<u32> {
+        self.mismatches.keys().next().copied()
+    }
+}
+
+/// Compares the node with the expected one, unless a mismatch was already found lower in the path.
+fn check_node<T: Clone + Eq, E: ExpectedNodes<T>>(
+    mismatch: &mut Option<NodeMismatch<T>>,
+    expected: &E,
+    position: NodePosition,
+    source: NodeSource,
+    found: &T,
+) {
+    if mismatch.is_some() {
+        return;
+    }
+    if let Some(expected_node) = expected.expected_node(position) {
+        if expected_node != *found {
+            *mismatch = Some(NodeMismatch {
+                level: position.position().0,
+                abs_index: position.abs_index(),
+                source,
+                found: found.clone(),
+                expected: expected_node,
+            });
+        }
+    }
+}
+
+/// Goes up from the leaf to the root, hashing the nodes with their siblings, and compares every node
+/// (the leaf, the siblings and the computed nodes) with the expected one, if it's known.
+/// Returns the computed root and the first mismatch, from the bottom up.
+pub(super) fn diagnose_path<T, H, E>(
+    leaf_position: NodePosition,
+    leaf: T,
+    expected: &E,
+    mut sibling_of: impl FnMut(NodePosition) -> Result<(T, NodeSource), MerkleProofVerificationError>,
+) -> Result<(T, Option<NodeMismatch<T>>), MerkleProofVerificationError>
+where
+    T: Clone + Eq,
+    H: PairHasher<NodeType = T>,
+    E: ExpectedNodes<T>,
+{
+    let mut mismatch = None;
+    check_node(
+        &mut mismatch,
+        expected,
+        leaf_position,
+        NodeSource::Leaf,
+        &leaf,
+    );
+
+    let mut hash = leaf;
+    let mut position = leaf_position;
+    while let Some(sibling_position) = position.sibling() {
+        let (sibling, source) = sibling_of(sibling_position)?;
+        check_node(&mut mismatch, expected, sibling_position, source, &sibling);
+
+        hash = match position.node_kind() {
+            NodeKind::LeftChild => H::hash_pair(&hash, &sibling),
+            NodeKind::RightChild => H::hash_pair(&sibling, &hash),
+            NodeKind::Root => unreachable!("Root has no sibling"),
+        };
+        position = position
+            .parent()
+            .expect("A node with a sibling has a parent");
+        check_node(
+            &mut mismatch,
+            expected,
+            position,
+            NodeSource::Computed,
+            &hash,
+        );
+    }
+
+    Ok((hash, mismatch))
+}
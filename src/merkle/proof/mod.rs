@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod diagnostics;
 pub mod multi;
 pub mod single;
 pub mod verify_result;
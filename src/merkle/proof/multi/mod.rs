@@ -13,25 +13,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod ordered_node;
+pub mod proof64;
 
-use std::{
+use alloc::{
     collections::{BTreeMap, BTreeSet},
-    fmt::Debug,
+    vec::Vec,
 };
+use core::fmt::Debug;
 
 use itertools::Itertools;
 
 use crate::merkle::{
-    hasher::PairHasher,
+    hasher::{LeafCountCommitter, PairHasher},
     pos::{node_kind::NodeKind, NodePosition},
-    tree::{tree_size::TreeSize, MerkleTree, Node},
-    MerkleProofVerificationError, MerkleTreeProofExtractionError,
+    tree::{store::NodeStore, tree_size::TreeSize, MerkleTree, Node},
+    MerkleProofCombinationError, MerkleProofVerificationError, MerkleTreeProofExtractionError,
 };
 
-use self::ordered_node::NodeWithAbsOrder;
-
-use super::{single::SingleProofNodes, verify_result::ProofVerifyResult};
+use super::{
+    diagnostics::{diagnose_path, ExpectedNodes, MultiProofDiagnostics, NodeSource},
+    single::SingleProofHashes,
+    verify_result::ProofVerifyResult,
+};
 
 /// Merkle proofs for multiple leaves.
 /// An object that contains the information required to prove that multiple leaves are in a Merkle tree.
@@ -41,17 +44,17 @@ use super::{single::SingleProofNodes, verify_result::ProofVerifyResult};
 /// using the `MultiProofNodes::into_values()` method.
 #[must_use]
 #[derive(Clone)]
-pub struct MultiProofNodes<'a, T, H> {
+pub struct MultiProofNodes<'a, T, H, S = Vec<T>> {
     /// The leaves where the calculation upwards to the root hash will start
-    proof_leaves: Vec<Node<'a, T, H>>,
+    proof_leaves: Vec<Node<'a, T, H, S>>,
     /// The minimal set of nodes needed to recreate the root hash (in addition to the leaves)
-    nodes: Vec<Node<'a, T, H>>,
+    nodes: Vec<Node<'a, T, H, S>>,
     /// The number of leaves in the tree, from which this proof was extracted
     tree_leaf_count: u32,
 }
 
-impl<T: Debug, H> Debug for MultiProofNodes<'_, T, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<T: Debug, H, S: NodeStore<T>> Debug for MultiProofNodes<'_, T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("MultiProofNodes")
             .field("proof_leaves", &self.proof_leaves)
             .field("nodes", &self.nodes)
@@ -68,28 +71,12 @@ fn is_sorted_and_unique(leaves_indices: &[u32]) -> bool {
         .all(|(i, j)| i < j)
 }
 
-fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    if v.is_empty() {
-        return Vec::new();
-    }
-    let len = v[0].len();
-    let mut iters: Vec<_> = v.into_iter().map(|n| n.into_iter()).collect();
-    (0..len)
-        .map(|_| {
-            iters
-                .iter_mut()
-                .map(|n| n.next().expect("Iter is in tandem. Should never happen."))
-                .collect::<Vec<T>>()
-        })
-        .collect()
-}
-
-impl<'a, T, H> MultiProofNodes<'a, T, H> {
-    pub fn nodes(&self) -> &[Node<'a, T, H>] {
+impl<'a, T, H, S> MultiProofNodes<'a, T, H, S> {
+    pub fn nodes(&self) -> &[Node<'a, T, H, S>] {
         &self.nodes
     }
 
-    pub fn proof_leaves(&self) -> &[Node<'a, T, H>] {
+    pub fn proof_leaves(&self) -> &[Node<'a, T, H, S>] {
         &self.proof_leaves
     }
 
@@ -98,9 +85,29 @@ impl<'a, T, H> MultiProofNodes<'a, T, H> {
     }
 }
 
-impl<'a, T: Clone, H: PairHasher<NodeType = T>> MultiProofNodes<'a, T, H> {
+impl<'a, T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> MultiProofNodes<'a, T, H, S> {
+    /// Creates a proof for the leaves with the given indices, which must be sorted and unique.
+    /// Padding leaves are refused, since a proof for them could be mistaken for a proof of real leaves.
     pub fn from_tree_leaves(
-        tree: &'a MerkleTree<T, H>,
+        tree: &'a MerkleTree<T, H, S>,
+        leaves_indices: &[u32],
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        if let Some(padding_leaf_index) = leaves_indices
+            .iter()
+            .find(|index| tree.is_padding_leaf(**index))
+        {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf(
+                *padding_leaf_index,
+                tree.real_leaf_count().get(),
+            ));
+        }
+
+        Self::from_tree_leaves_including_padding(tree, leaves_indices)
+    }
+
+    /// Same as `from_tree_leaves`, but allows creating proofs for padding leaves.
+    pub fn from_tree_leaves_including_padding(
+        tree: &'a MerkleTree<T, H, S>,
         leaves_indices: &[u32],
     ) -> Result<Self, MerkleTreeProofExtractionError> {
         if leaves_indices.is_empty() {
@@ -126,64 +133,29 @@ impl<'a, T: Clone, H: PairHasher<NodeType = T>> MultiProofNodes<'a, T, H> {
             }
         }
 
-        let single_proofs = leaves_indices
-            .iter()
-            .map(|i| SingleProofNodes::from_tree_leaf(tree, *i))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let mut computed_from_prev_level = vec![];
-        let mut proof = vec![];
-
-        let single_proofs_branches = single_proofs
-            .clone()
-            .into_iter()
-            .map(|sp| sp.branch().to_vec())
-            .collect::<Vec<_>>();
-
-        // We expect all proofs to have the same branch lengths
-        if !single_proofs_branches.is_empty()
-            && !single_proofs_branches
-                .iter()
-                .all(|v| v.len() == single_proofs_branches[0].len())
-        {
-            panic!("Proofs of the same tree are all expected to have the same length")
-        }
-
-        // Proofs have branches that extend from leaves to the root, but they never reach the root.
-        // We want to loop on the tree level, hence we transpose the branches of the single proofs
-        let single_proofs_levels = transpose(single_proofs_branches);
-
-        for nodes_of_level in single_proofs_levels {
-            let sibling_err =
-                "Only root has no sibling. We are never at root here, so this should never happen.";
-            let siblings = nodes_of_level
-                .iter()
-                .map(|node| node.sibling().expect(sibling_err).abs_index())
-                .collect::<BTreeSet<u32>>();
-
-            // We remove leaves that are already in siblings because they will come from the verification input.
-            // This happens when the leaves, for which a proof is requested, are used together to build a parent node
-            // in the tree. In that case, given that the verification will have both as inputs, we don't need to include
-            // them in the proof.
-            // We also remove the nodes that can be computed from the previous level, because they will be included in the proof
-            let proofs_at_level = nodes_of_level
-                .into_iter()
-                .filter(|node| !siblings.contains(&node.abs_index()))
-                .filter(|node| !computed_from_prev_level.contains(&node.abs_index()))
-                .map(NodeWithAbsOrder::from)
-                .collect::<BTreeSet<_>>();
-
-            // We collect all the nodes that can be computed from this level, and will use it in the next iteration
-            let parent_err = "We should never be at root, so there should always be a parent";
-            computed_from_prev_level = proofs_at_level
-                .iter()
-                .map(|n| n.get())
-                .tuple_windows::<(&Node<T, H>, &Node<T, H>)>()
-                .filter(|n| n.0.abs_index() % 2 == 0 && n.0.abs_index() + 1 == n.1.abs_index())
-                .map(|(n1, _n2)| n1.parent().expect(parent_err).abs_index())
-                .collect();
+        // The frontier is the sorted set of indices, in the current level, of the nodes that can be computed
+        // from the proven leaves. Going up level by level, a node of the frontier whose sibling is also in
+        // the frontier needs nothing more, otherwise its sibling must be part of the proof.
+        let level_count = tree.level_count().get();
+        let mut frontier = leaves_indices.to_vec();
+        let mut proof = Vec::new();
+
+        for level in 0..level_count - 1 {
+            let mut next_frontier = Vec::with_capacity(frontier.len().div_ceil(2));
+            let mut frontier_iter = frontier.iter().copied().peekable();
+
+            while let Some(index) = frontier_iter.next() {
+                let sibling = index ^ 1;
+                if frontier_iter.next_if_eq(&sibling).is_none() {
+                    proof.push(
+                        tree.node_from_bottom(level, sibling)
+                            .expect("Sibling of a node in the tree is in the tree"),
+                    );
+                }
+                next_frontier.push(index / 2);
+            }
 
-            proof.extend(proofs_at_level.into_iter().map(Node::from));
+            frontier = next_frontier;
         }
 
         Ok(Self {
@@ -206,8 +178,8 @@ impl<'a, T: Clone, H: PairHasher<NodeType = T>> MultiProofNodes<'a, T, H> {
                 .into_iter()
                 .map(|n| (n.abs_index(), n.hash().clone()))
                 .collect(),
-            tree_leaf_count: self.proof_leaves[0].tree().leaf_count().get(),
-            _phantom: std::marker::PhantomData,
+            tree_leaf_count: self.tree_leaf_count,
+            _phantom: core::marker::PhantomData,
         }
     }
 }
@@ -225,7 +197,7 @@ pub struct MultiProofHashes<T, H> {
     nodes: BTreeMap<u32, T>,
     /// The number of leaves in the tree, from which this proof was extracted
     tree_leaf_count: u32,
-    _phantom: std::marker::PhantomData<H>,
+    _phantom: core::marker::PhantomData<H>,
 }
 
 impl<T, H> MultiProofHashes<T, H> {
@@ -241,12 +213,153 @@ impl<T, H> MultiProofHashes<T, H> {
         Self {
             nodes,
             tree_leaf_count,
-            _phantom: std::marker::PhantomData,
+            _phantom: core::marker::PhantomData,
         }
     }
 }
 
+/// Removes the nodes that can be computed from the given leaves, i.e., the leaves themselves and all their ancestors.
+/// What remains are the nodes that are required, in addition to the leaves, to compute the root.
+fn remove_computable_nodes<T>(
+    tree_size: TreeSize,
+    leaves_indices: &BTreeSet<u32>,
+    nodes: &mut BTreeMap<u32, T>,
+) {
+    let mut computable = BTreeSet::new();
+    for leaf_index in leaves_indices {
+        let leaf = NodePosition::from_position(tree_size, 0, *leaf_index)
+            .expect("Leaves indices must have been checked before");
+        for node in leaf.into_iter_parents() {
+            // If the node was already inserted, then all its ancestors are already there too
+            if !computable.insert(node.abs_index()) {
+                break;
+            }
+        }
+    }
+    nodes.retain(|index, _| !computable.contains(index));
+}
+
+/// Inserts a node into the nodes of a proof, ensuring that there's no conflict with an existing node.
+fn insert_node_without_conflict<T: Eq>(
+    nodes: &mut BTreeMap<u32, T>,
+    index: u32,
+    node: T,
+) -> Result<(), MerkleProofCombinationError> {
+    match nodes.get(&index) {
+        Some(existing) if *existing != node => {
+            Err(MerkleProofCombinationError::ConflictingNodes(index))
+        }
+        Some(_) => Ok(()),
+        None => {
+            nodes.insert(index, node);
+            Ok(())
+        }
+    }
+}
+
+/// In a single-node tree, the only leaf is the root, so there's nothing to hash.
+fn verify_single_node_tree<T: Eq>(leaves: &BTreeMap<u32, T>, root: &T) -> ProofVerifyResult {
+    match leaves.get(&0) == Some(root) {
+        true => ProofVerifyResult::PassedTrivially,
+        false => ProofVerifyResult::Failed,
+    }
+}
+
 impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes<T, H> {
+    /// Combines single proofs of leaves of the same tree into a multi-proof.
+    /// Siblings that are shared between the proofs are deduplicated, and nodes that can be computed
+    /// from the proven leaves are dropped, so that the result has the same nodes that
+    /// `MultiProofNodes::from_tree_leaves` would produce for these leaves.
+    pub fn from_single_proofs(
+        proofs: &[SingleProofHashes<T, H>],
+    ) -> Result<Self, MerkleProofCombinationError> {
+        let branch_length = proofs
+            .first()
+            .ok_or(MerkleProofCombinationError::NoProofsProvided)?
+            .branch()
+            .len();
+
+        let tree_leaf_count = u32::try_from(branch_length)
+            .ok()
+            .and_then(|length| 1u32.checked_shl(length))
+            .ok_or(MerkleProofCombinationError::BranchTooLong(branch_length))?;
+        let tree_size = TreeSize::from_leaf_count(tree_leaf_count)
+            .map_err(|_| MerkleProofCombinationError::BranchTooLong(branch_length))?;
+
+        let mut leaves_indices = BTreeSet::new();
+        let mut nodes = BTreeMap::new();
+
+        for proof in proofs {
+            if proof.level_from_bottom() != 0 {
+                return Err(MerkleProofCombinationError::NotLeafLevel(
+                    proof.level_from_bottom(),
+                ));
+            }
+            if proof.branch().len() != branch_length {
+                return Err(MerkleProofCombinationError::BranchLengthMismatch(
+                    branch_length,
+                    proof.branch().len(),
+                ));
+            }
+
+            let leaf_index = proof.leaf_index_in_level();
+            let leaf = NodePosition::from_position(tree_size, 0, leaf_index).ok_or(
+                MerkleProofCombinationError::LeafIndexOutOfRange(leaf_index, tree_leaf_count),
+            )?;
+            leaves_indices.insert(leaf_index);
+
+            // The branch is made of the siblings of the leaf and its ancestors, from the bottom up
+            let siblings = leaf.into_iter_parents().map_while(|node| node.sibling());
+            for (sibling, hash) in siblings.zip(proof.branch()) {
+                insert_node_without_conflict(&mut nodes, sibling.abs_index(), hash.clone())?;
+            }
+        }
+
+        remove_computable_nodes(tree_size, &leaves_indices, &mut nodes);
+
+        Ok(Self::from_leaf_count_and_nodes(tree_leaf_count, nodes))
+    }
+
+    /// Merges two multi-proofs of the same tree into one that proves the leaves of both, whose indices are given
+    /// in `leaves_indices`, in any order. The nodes of both proofs are combined, then the nodes that are computable
+    /// from these leaves are removed. The proven leaves are taken as an argument, rather than inferred from the proofs,
+    /// so that the work depends on the number of leaves, and not on the tree leaf count of a possibly untrusted proof.
+    pub fn merge(
+        &self,
+        other: &Self,
+        leaves_indices: &[u32],
+    ) -> Result<Self, MerkleProofCombinationError> {
+        if self.tree_leaf_count != other.tree_leaf_count {
+            return Err(MerkleProofCombinationError::TreeLeafCountMismatch(
+                self.tree_leaf_count,
+                other.tree_leaf_count,
+            ));
+        }
+
+        let tree_size = TreeSize::from_leaf_count(self.tree_leaf_count)
+            .map_err(|_| MerkleProofCombinationError::InvalidTreeLeafCount(self.tree_leaf_count))?;
+
+        if let Some(index) = leaves_indices
+            .iter()
+            .find(|index| **index >= self.tree_leaf_count)
+        {
+            return Err(MerkleProofCombinationError::LeafIndexOutOfRange(
+                *index,
+                self.tree_leaf_count,
+            ));
+        }
+        let leaves_indices = leaves_indices.iter().copied().collect::<BTreeSet<_>>();
+
+        let mut nodes = self.nodes.clone();
+        for (index, node) in &other.nodes {
+            insert_node_without_conflict(&mut nodes, *index, node.clone())?;
+        }
+
+        remove_computable_nodes(tree_size, &leaves_indices, &mut nodes);
+
+        Ok(Self::from_leaf_count_and_nodes(self.tree_leaf_count, nodes))
+    }
+
     /// While verifying the multi-proof, we need to precalculate all the possible nodes that are required to build the root hash.
     fn calculate_missing_nodes(tree_size: TreeSize, input: BTreeMap<&u32, &T>) -> BTreeMap<u32, T> {
         let mut result = input
@@ -281,26 +394,19 @@ impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes<T, H> {
         result
     }
 
-    /// Given a set of leaves and their indices, verify that the root hash is correct
-    /// Returns Ok(None) if the proof is empty (i.e. the tree has only one node)
-    /// This choice, to return None, is a security measure to prevent a malicious user from
-    /// circumventing verification by providing a proof of a single node.
-    pub fn verify(
+    /// Checks the indices of the provided leaves and of the proof nodes, and returns the size of the tree.
+    fn checked_tree_size(
         &self,
-        leaves: BTreeMap<u32, T>,
-        root: T,
-    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
-        // in case it's a single-node tree, we don't need to verify or hash anything
-
+        leaves: &BTreeMap<u32, T>,
+    ) -> Result<TreeSize, MerkleProofVerificationError> {
         if leaves.is_empty() {
             return Err(MerkleProofVerificationError::LeavesContainerProvidedIsEmpty);
         }
 
-        if !self.tree_leaf_count.is_power_of_two() {
-            return Err(MerkleProofVerificationError::InvalidTreeLeavesCount(
-                self.tree_leaf_count(),
-            ));
-        }
+        // The leaf count comes from the proof, which may be untrusted, so it's checked without overflowing
+        let tree_size = TreeSize::from_leaf_count(self.tree_leaf_count).map_err(|_| {
+            MerkleProofVerificationError::InvalidTreeLeavesCount(self.tree_leaf_count())
+        })?;
 
         if leaves
             .iter()
@@ -312,9 +418,6 @@ impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes<T, H> {
             ));
         }
 
-        let tree_size =
-            TreeSize::from_u32(self.tree_leaf_count * 2 - 1).expect("Already proven from source");
-
         if self
             .nodes
             .iter()
@@ -326,6 +429,16 @@ impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes<T, H> {
             ));
         }
 
+        Ok(tree_size)
+    }
+
+    /// Checks the proof against the provided leaves and computes all the nodes that can be derived from them.
+    fn checked_nodes_with_leaves(
+        &self,
+        leaves: &BTreeMap<u32, T>,
+    ) -> Result<(TreeSize, BTreeMap<u32, T>), MerkleProofVerificationError> {
+        let tree_size = self.checked_tree_size(leaves)?;
+
         let all_nodes = self
             .nodes
             .iter()
@@ -333,6 +446,28 @@ impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes<T, H> {
             .collect::<BTreeMap<_, _>>();
         let all_nodes = MultiProofHashes::<T, H>::calculate_missing_nodes(tree_size, all_nodes);
 
+        Ok((tree_size, all_nodes))
+    }
+
+    /// Given a set of leaves and their indices, verify that the root hash is correct
+    /// Returns Ok(None) if the proof is empty (i.e. the tree has only one node)
+    /// This choice, to return None, is a security measure to prevent a malicious user from
+    /// circumventing verification by providing a proof of a single node.
+    /// The work done here grows with the size of the tree, which is taken from the proof, so proofs from
+    /// untrusted sources should be verified with `verify_by_levels`, which only visits the nodes of the proof.
+    pub fn verify(
+        &self,
+        leaves: BTreeMap<u32, T>,
+        root: T,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        // in case it's a single-node tree, we don't need to verify or hash anything
+
+        let (tree_size, all_nodes) = self.checked_nodes_with_leaves(&leaves)?;
+
+        if tree_size.get() == 1 {
+            return Ok(verify_single_node_tree(&leaves, &root));
+        }
+
         // Result is Option<bool> because it must pass through the loop inside at least once; otherwise nothing is checked
         let mut result = ProofVerifyResult::PassedTrivially;
 
@@ -380,6 +515,262 @@ impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes<T, H> {
 
         Ok(result)
     }
+
+    /// Same as `verify`, but also requires the proven leaves to be within the real leaves of the tree,
+    /// i.e., none of them is a padding leaf. The real leaf count must be consistent with the tree leaf count.
+    pub fn verify_within_real_leaf_count(
+        &self,
+        leaves: BTreeMap<u32, T>,
+        root: T,
+        real_leaf_count: u32,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        self.check_real_leaf_count(&leaves, real_leaf_count)?;
+        self.verify(leaves, root)
+    }
+
+    /// Checks that the real leaf count is consistent with the tree leaf count, and that none of the leaves
+    /// is a padding leaf.
+    fn check_real_leaf_count(
+        &self,
+        leaves: &BTreeMap<u32, T>,
+        real_leaf_count: u32,
+    ) -> Result<(), MerkleProofVerificationError> {
+        if real_leaf_count == 0
+            || real_leaf_count.checked_next_power_of_two() != Some(self.tree_leaf_count)
+        {
+            return Err(MerkleProofVerificationError::InvalidTreeLeavesCount(
+                real_leaf_count,
+            ));
+        }
+
+        if leaves.keys().any(|index| *index >= real_leaf_count) {
+            return Err(MerkleProofVerificationError::LeavesIndicesOutOfRange(
+                leaves.keys().cloned().collect(),
+                real_leaf_count,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Same as `verify`, but the path of every leaf to the root is compared with the expected nodes
+    /// (e.g., a reference tree), to find which leaves failed and where their paths diverged.
+    pub fn verify_with_diagnostics<E: ExpectedNodes<T>>(
+        &self,
+        leaves: BTreeMap<u32, T>,
+        root: T,
+        expected: &E,
+    ) -> Result<MultiProofDiagnostics<T>, MerkleProofVerificationError> {
+        let (tree_size, all_nodes) = self.checked_nodes_with_leaves(&leaves)?;
+
+        let mut result = ProofVerifyResult::PassedTrivially;
+        let mut mismatches = BTreeMap::new();
+
+        for (leaf_index, leaf) in &leaves {
+            let leaf_position = NodePosition::from_position(tree_size, 0, *leaf_index)
+                .expect("At level zero, leave index be valid");
+
+            let (computed_root, mismatch) =
+                diagnose_path::<T, H, E>(leaf_position, leaf.clone(), expected, |sibling| {
+                    let index = sibling.abs_index();
+                    let node = all_nodes
+                        .get(&index)
+                        .cloned()
+                        .ok_or(MerkleProofVerificationError::RequiredNodeMissing(index))?;
+                    let source = if leaves.contains_key(&index) {
+                        NodeSource::Leaf
+                    } else if self.nodes.contains_key(&index) {
+                        NodeSource::Proof
+                    } else {
+                        NodeSource::Computed
+                    };
+                    Ok((node, source))
+                })?;
+
+            result = result.or(computed_root == root);
+            if let Some(mismatch) = mismatch {
+                mismatches.insert(*leaf_index, mismatch);
+            }
+        }
+
+        if tree_size.get() == 1 {
+            result = verify_single_node_tree(&leaves, &root);
+        }
+
+        Ok(MultiProofDiagnostics::new(result, mismatches))
+    }
+
+    /// Same as `verify`, with the same errors and results, but the tree is processed level by level
+    /// from the bottom up, so that every internal node is hashed once, no matter how many leaves
+    /// are being proven, and only the nodes that can be known from the proof are visited.
+    pub fn verify_by_levels(
+        &self,
+        leaves: BTreeMap<u32, T>,
+        root: T,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        let computed_root = self.compute_by_levels(&leaves, |_, _| {})?;
+
+        if self.tree_leaf_count == 1 {
+            return Ok(verify_single_node_tree(&leaves, &root));
+        }
+
+        Ok(ProofVerifyResult::PassedTrivially.or(computed_root == root))
+    }
+
+    /// Computes the root that the given leaves produce with this proof.
+    pub fn compute_root(
+        &self,
+        leaves: BTreeMap<u32, T>,
+    ) -> Result<T, MerkleProofVerificationError> {
+        self.compute_by_levels(&leaves, |_, _| {})
+    }
+
+    /// Computes all the nodes that can be reconstructed from the given leaves with this proof
+    /// (excluding the leaves), as pairs of absolute index and hash, in ascending order of index.
+    /// Unless the tree has a single node, the last node is the root.
+    pub fn compute_nodes(
+        &self,
+        leaves: BTreeMap<u32, T>,
+    ) -> Result<Vec<(u32, T)>, MerkleProofVerificationError> {
+        let mut nodes = Vec::new();
+        self.compute_by_levels(&leaves, |index, node| nodes.push((index, node.clone())))?;
+        Ok(nodes)
+    }
+
+    /// Computes the root from the leaves, level by level from the bottom up, so that every internal
+    /// node is hashed once. Every computed node is passed to `on_computed`, in ascending order of index.
+    /// The errors are the same as in `verify`.
+    fn compute_by_levels(
+        &self,
+        leaves: &BTreeMap<u32, T>,
+        mut on_computed: impl FnMut(u32, &T),
+    ) -> Result<T, MerkleProofVerificationError> {
+        let tree_size = self.checked_tree_size(leaves)?;
+
+        if tree_size.get() == 1 {
+            return Ok(leaves
+                .get(&0)
+                .expect("The only leaf index was checked")
+                .clone());
+        }
+
+        let level_count = tree_size.level_count().get();
+        let level_range = |level: u32| {
+            let start = tree_size.level_start(level).expect("Level is in range");
+            let end = tree_size.level_start(level + 1).unwrap_or(tree_size.get());
+            start..end
+        };
+        let level_nodes = |level: u32| {
+            let range = level_range(level);
+            self.nodes
+                .range(range.clone())
+                .map(move |(index, node)| (index - range.start, node.clone()))
+        };
+
+        // The known nodes in the current level, by their index in the level.
+        // Leaves override proof nodes, and computed nodes override proof nodes, as in `verify`.
+        let mut known = level_nodes(0)
+            .chain(leaves.iter().map(|(index, leaf)| (*index, leaf.clone())))
+            .collect::<BTreeMap<u32, T>>();
+
+        // The nodes in the current level that are on the path from proven leaves to the root,
+        // mapped to the smallest proven leaf index under them
+        let mut on_path = leaves
+            .keys()
+            .map(|index| (*index, *index))
+            .collect::<BTreeMap<u32, u32>>();
+
+        // To report the same missing node as `verify`, we keep the first missing node
+        // of the path of the smallest leaf that has one, as (leaf index, missing node absolute index)
+        let mut first_missing: Option<(u32, u32)> = None;
+
+        for level in 0..level_count - 1 {
+            let level_start = level_range(level).start;
+
+            let mut next_on_path = BTreeMap::new();
+            for (index, smallest_leaf) in on_path {
+                let sibling = index ^ 1;
+                if known.contains_key(&sibling) {
+                    // Left children come first, so the smallest leaf is inserted first
+                    next_on_path.entry(index / 2).or_insert(smallest_leaf);
+                } else if first_missing.map_or(true, |(leaf, _)| smallest_leaf < leaf) {
+                    first_missing = Some((smallest_leaf, level_start + sibling));
+                }
+            }
+
+            let next_level_start = level_range(level + 1).start;
+            let mut next_known = level_nodes(level + 1).collect::<BTreeMap<u32, T>>();
+            for (index, left) in known.iter().filter(|(index, _)| *index % 2 == 0) {
+                if let Some(right) = known.get(&(index + 1)) {
+                    let parent = H::hash_pair(left, right);
+                    on_computed(next_level_start + index / 2, &parent);
+                    next_known.insert(index / 2, parent);
+                }
+            }
+
+            known = next_known;
+            on_path = next_on_path;
+        }
+
+        if let Some((_, missing_index)) = first_missing {
+            return Err(MerkleProofVerificationError::RequiredNodeMissing(
+                missing_index,
+            ));
+        }
+
+        let computed_root = known
+            .remove(&0)
+            .expect("Root is computed when no node is missing");
+        Ok(computed_root)
+    }
+
+    /// Splits the multi-proof into one single proof per provided leaf, ordered by leaf index.
+    /// The intermediate nodes that the multi-proof omits are recomputed from the leaves, so the
+    /// resulting single proofs are complete on their own.
+    pub fn into_single_proofs(
+        self,
+        leaves: BTreeMap<u32, T>,
+    ) -> Result<Vec<SingleProofHashes<T, H>>, MerkleProofVerificationError> {
+        let (tree_size, all_nodes) = self.checked_nodes_with_leaves(&leaves)?;
+
+        leaves
+            .keys()
+            .map(|leaf_index| {
+                let leaf = NodePosition::from_position(tree_size, 0, *leaf_index)
+                    .expect("At level zero, leave index be valid");
+                let branch = leaf
+                    .into_iter_parents()
+                    .map_while(|node| node.sibling())
+                    .map(|sibling| {
+                        all_nodes.get(&sibling.abs_index()).cloned().ok_or(
+                            MerkleProofVerificationError::RequiredNodeMissing(sibling.abs_index()),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(SingleProofHashes::from_leaf_index_and_branch(
+                    *leaf_index,
+                    branch,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl<T: Eq + Clone, H: LeafCountCommitter<NodeType = T>> MultiProofHashes<T, H> {
+    /// Same as `verify_within_real_leaf_count`, but the leaves, with the real leaf count, are verified against
+    /// the count-committed root (see `MerkleTree::count_committed_root`), which proves the real leaf count.
+    /// Since the commitment is always hashed, passing is always decisive.
+    pub fn verify_count_committed(
+        &self,
+        leaves: BTreeMap<u32, T>,
+        committed_root: T,
+        real_leaf_count: u32,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        self.check_real_leaf_count(&leaves, real_leaf_count)?;
+        let root = self.compute_root(leaves)?;
+        let computed = H::commit_leaf_count(&root, real_leaf_count);
+        Ok(ProofVerifyResult::PassedTrivially.or(computed == committed_root))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,505 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::fmt::Debug;
+
+use crate::merkle::{
+    hasher::PairHasher,
+    proof::verify_result::ProofVerifyResult,
+    tree::{store::NodeStore, tree64::MerkleTree64, tree_size::TreeSize64},
+    MerkleProofVerificationError, MerkleTreeProofExtractionError,
+};
+
+use super::MultiProofHashes;
+
+/// Same as `MultiProofHashes`, but with 64-bit leaf and node indices, for trees that have more leaves
+/// than `MultiProofHashes` supports. Proofs of trees that fit in both verify the same, and the nodes
+/// have the same absolute indices in both.
+#[must_use]
+#[cfg_attr(
+    feature = "scale-codec",
+    derive(parity_scale_codec::Encode, parity_scale_codec::Decode)
+)]
+pub struct MultiProofHashes64<T, H> {
+    /// The minimal set of nodes needed to recreate the root hash (in addition to the leaves)
+    nodes: BTreeMap<u64, T>,
+    /// The number of leaves in the tree, from which this proof was extracted
+    tree_leaf_count: u64,
+    _phantom: core::marker::PhantomData<H>,
+}
+
+impl<T: Debug, H> Debug for MultiProofHashes64<T, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MultiProofHashes64")
+            .field("nodes", &self.nodes)
+            .field("tree_leaf_count", &self.tree_leaf_count)
+            .finish()
+    }
+}
+
+impl<T: Clone, H> Clone for MultiProofHashes64<T, H> {
+    fn clone(&self) -> Self {
+        Self::from_leaf_count_and_nodes(self.tree_leaf_count, self.nodes.clone())
+    }
+}
+
+impl<T: PartialEq, H> PartialEq for MultiProofHashes64<T, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.tree_leaf_count == other.tree_leaf_count && self.nodes == other.nodes
+    }
+}
+
+impl<T: Eq, H> Eq for MultiProofHashes64<T, H> {}
+
+impl<T, H> MultiProofHashes64<T, H> {
+    pub fn nodes(&self) -> &BTreeMap<u64, T> {
+        &self.nodes
+    }
+
+    pub fn tree_leaf_count(&self) -> u64 {
+        self.tree_leaf_count
+    }
+
+    pub fn from_leaf_count_and_nodes(tree_leaf_count: u64, nodes: BTreeMap<u64, T>) -> Self {
+        Self {
+            nodes,
+            tree_leaf_count,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Clone, H> MultiProofHashes64<T, H> {
+    /// Creates a proof for the leaves of a `MerkleTree64` with the given indices, which must be sorted and unique.
+    /// Padding leaves are refused, like in `MultiProofNodes::from_tree_leaves`.
+    pub fn from_tree_leaves<S: NodeStore<T>>(
+        tree: &MerkleTree64<T, H, S>,
+        leaves_indices: &[u64],
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        if let Some(padding_leaf_index) = leaves_indices
+            .iter()
+            .find(|index| tree.is_padding_leaf(**index))
+        {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf64(
+                *padding_leaf_index,
+                tree.real_leaf_count().get(),
+            ));
+        }
+
+        Self::from_tree_leaves_including_padding(tree, leaves_indices)
+    }
+
+    /// Same as `from_tree_leaves`, but allows creating proofs for padding leaves.
+    pub fn from_tree_leaves_including_padding<S: NodeStore<T>>(
+        tree: &MerkleTree64<T, H, S>,
+        leaves_indices: &[u64],
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        if leaves_indices.is_empty() {
+            return Err(MerkleTreeProofExtractionError::NoLeavesToCreateProof);
+        }
+
+        if leaves_indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(
+                MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices64(
+                    leaves_indices.to_vec(),
+                ),
+            );
+        }
+
+        let tree_size = tree.total_node_count();
+        let leaf_count = tree_size.leaf_count().get();
+        if leaves_indices.iter().any(|v| *v >= leaf_count) {
+            return Err(MerkleTreeProofExtractionError::IndexOutOfRange64(
+                leaves_indices.to_vec(),
+                leaf_count,
+            ));
+        }
+
+        // The same frontier walk as in `MultiProofNodes::from_tree_leaves_including_padding`
+        let level_count = tree_size.level_count().get();
+        let mut frontier = leaves_indices.to_vec();
+        let mut nodes = BTreeMap::new();
+
+        for level in 0..level_count - 1 {
+            let level_start = tree_size.level_start(level).expect("Level is in range");
+            let mut next_frontier = Vec::with_capacity(frontier.len().div_ceil(2));
+            let mut frontier_iter = frontier.iter().copied().peekable();
+
+            while let Some(index) = frontier_iter.next() {
+                let sibling = index ^ 1;
+                if frontier_iter.next_if_eq(&sibling).is_none() {
+                    let abs_index = level_start + sibling;
+                    let node = tree
+                        .node(abs_index)
+                        .cloned()
+                        .expect("Sibling of a node in the tree is in the tree");
+                    nodes.insert(abs_index, node);
+                }
+                next_frontier.push(index / 2);
+            }
+
+            frontier = next_frontier;
+        }
+
+        Ok(Self::from_leaf_count_and_nodes(leaf_count, nodes))
+    }
+}
+
+impl<T: Eq + Clone, H: PairHasher<NodeType = T>> MultiProofHashes64<T, H> {
+    /// Same as `MultiProofHashes::verify_by_levels`, with the 64-bit counterparts of its errors.
+    pub fn verify(
+        &self,
+        leaves: BTreeMap<u64, T>,
+        root: T,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        let computed_root = self.compute_root(leaves.clone())?;
+
+        if self.tree_leaf_count == 1 {
+            return match leaves.get(&0) == Some(&root) {
+                true => Ok(ProofVerifyResult::PassedTrivially),
+                false => Ok(ProofVerifyResult::Failed),
+            };
+        }
+
+        Ok(ProofVerifyResult::PassedTrivially.or(computed_root == root))
+    }
+
+    /// Computes the root that the given leaves produce with this proof, level by level from the bottom up,
+    /// so that only the nodes that can be known from the proof are visited.
+    pub fn compute_root(
+        &self,
+        leaves: BTreeMap<u64, T>,
+    ) -> Result<T, MerkleProofVerificationError> {
+        let tree_size = self.checked_tree_size(&leaves)?;
+
+        if tree_size.get() == 1 {
+            return Ok(leaves
+                .get(&0)
+                .expect("The only leaf index was checked")
+                .clone());
+        }
+
+        let level_count = tree_size.level_count().get();
+        let level_range = |level: u32| {
+            let start = tree_size.level_start(level).expect("Level is in range");
+            let end = tree_size.level_start(level + 1).unwrap_or(tree_size.get());
+            start..end
+        };
+        let level_nodes = |level: u32| {
+            let range = level_range(level);
+            self.nodes
+                .range(range.clone())
+                .map(move |(index, node)| (index - range.start, node.clone()))
+        };
+
+        // The nodes in the current level that are on the path from proven leaves to the root,
+        // mapped to the smallest proven leaf index under them
+        let mut on_path = leaves
+            .keys()
+            .map(|index| (*index, *index))
+            .collect::<BTreeMap<u64, u64>>();
+
+        // The known nodes in the current level, by their index in the level.
+        // Leaves override proof nodes, and computed nodes override proof nodes, as in `MultiProofHashes`.
+        let mut known = level_nodes(0).chain(leaves).collect::<BTreeMap<u64, T>>();
+
+        // As in `MultiProofHashes`, the reported missing node is the first one on the path of the smallest
+        // leaf that has one, as (leaf index, missing node absolute index)
+        let mut first_missing: Option<(u64, u64)> = None;
+
+        for level in 0..level_count - 1 {
+            let level_start = level_range(level).start;
+
+            let mut next_on_path = BTreeMap::new();
+            for (index, smallest_leaf) in on_path {
+                let sibling = index ^ 1;
+                if known.contains_key(&sibling) {
+                    next_on_path.entry(index / 2).or_insert(smallest_leaf);
+                } else if first_missing.map_or(true, |(leaf, _)| smallest_leaf < leaf) {
+                    first_missing = Some((smallest_leaf, level_start + sibling));
+                }
+            }
+
+            let mut next_known = level_nodes(level + 1).collect::<BTreeMap<u64, T>>();
+            for (index, left) in known.iter().filter(|(index, _)| *index % 2 == 0) {
+                if let Some(right) = known.get(&(index + 1)) {
+                    next_known.insert(index / 2, H::hash_pair(left, right));
+                }
+            }
+
+            known = next_known;
+            on_path = next_on_path;
+        }
+
+        if let Some((_, missing_index)) = first_missing {
+            return Err(MerkleProofVerificationError::RequiredNodeMissing64(
+                missing_index,
+            ));
+        }
+
+        let computed_root = known
+            .remove(&0)
+            .expect("Root is computed when no node is missing");
+        Ok(computed_root)
+    }
+
+    /// Checks the indices of the provided leaves and of the proof nodes, and returns the size of the tree.
+    fn checked_tree_size(
+        &self,
+        leaves: &BTreeMap<u64, T>,
+    ) -> Result<TreeSize64, MerkleProofVerificationError> {
+        if leaves.is_empty() {
+            return Err(MerkleProofVerificationError::LeavesContainerProvidedIsEmpty);
+        }
+
+        let tree_size = TreeSize64::from_leaf_count(self.tree_leaf_count).map_err(|_| {
+            MerkleProofVerificationError::InvalidTreeLeavesCount64(self.tree_leaf_count)
+        })?;
+
+        if leaves.keys().any(|index| *index >= self.tree_leaf_count) {
+            return Err(MerkleProofVerificationError::LeavesIndicesOutOfRange64(
+                leaves.keys().cloned().collect(),
+                self.tree_leaf_count,
+            ));
+        }
+
+        if self.nodes.keys().any(|index| *index >= tree_size.get()) {
+            return Err(MerkleProofVerificationError::NodesIndicesOutOfRange64(
+                self.nodes.keys().cloned().collect(),
+                tree_size.get(),
+            ));
+        }
+
+        Ok(tree_size)
+    }
+}
+
+impl<T, H> From<MultiProofHashes<T, H>> for MultiProofHashes64<T, H> {
+    fn from(proof: MultiProofHashes<T, H>) -> Self {
+        let nodes = proof
+            .nodes
+            .into_iter()
+            .map(|(index, node)| (index.into(), node))
+            .collect();
+        Self::from_leaf_count_and_nodes(proof.tree_leaf_count.into(), nodes)
+    }
+}
+
+impl<T, H> TryFrom<MultiProofHashes64<T, H>> for MultiProofHashes<T, H> {
+    type Error = MultiProofHashes64<T, H>;
+
+    /// Fails, returning the proof back, if the leaf count or a node index doesn't fit in 32 bits.
+    fn try_from(proof: MultiProofHashes64<T, H>) -> Result<Self, Self::Error> {
+        let fits = u32::try_from(proof.tree_leaf_count).is_ok()
+            && proof
+                .nodes
+                .keys()
+                .next_back()
+                .map_or(true, |index| u32::try_from(*index).is_ok());
+        if !fits {
+            return Err(proof);
+        }
+
+        let nodes = proof
+            .nodes
+            .into_iter()
+            .map(|(index, node)| (index as u32, node))
+            .collect();
+        Ok(MultiProofHashes::from_leaf_count_and_nodes(
+            proof.tree_leaf_count as u32,
+            nodes,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        internal::{hash_data, HashAlgo, HashedData},
+        proof::multi::MultiProofNodes,
+        tree::MerkleTree,
+    };
+
+    use super::*;
+
+    fn to_leaves64(leaves: &BTreeMap<u32, HashedData>) -> BTreeMap<u64, HashedData> {
+        leaves
+            .iter()
+            .map(|(i, leaf)| ((*i).into(), *leaf))
+            .collect()
+    }
+
+    #[test]
+    fn same_as_32_bit_proof() {
+        for leaf_count in 1..=17u32 {
+            let leaves = (0..leaf_count)
+                .map(|i| hash_data(HashedData::from_low_u64_be(i.into())))
+                .collect::<Vec<_>>();
+            let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+            // Every window of up to 3 consecutive leaves
+            for start in 0..leaf_count {
+                for end in (start + 1)..=(start + 3).min(leaf_count) {
+                    let indices = (start..end).collect::<Vec<_>>();
+                    let proof = MultiProofNodes::from_tree_leaves(&t, &indices)
+                        .unwrap()
+                        .into_values();
+                    let proof64 = MultiProofHashes64::from(proof.clone());
+                    assert_eq!(proof64.tree_leaf_count(), proof.tree_leaf_count().into());
+                    assert_eq!(proof64.nodes().len(), proof.nodes().len());
+
+                    let proven = indices
+                        .iter()
+                        .map(|i| (*i, leaves[*i as usize]))
+                        .collect::<BTreeMap<_, _>>();
+                    assert_eq!(
+                        proof64.verify(to_leaves64(&proven), t.root()),
+                        proof.verify_by_levels(proven.clone(), t.root())
+                    );
+                    assert_eq!(
+                        proof64.compute_root(to_leaves64(&proven)),
+                        proof.compute_root(proven.clone())
+                    );
+
+                    let mut tampered = proven.clone();
+                    tampered.insert(start, HashedData::zero());
+                    assert_eq!(
+                        proof64.verify(to_leaves64(&tampered), t.root()),
+                        proof.verify_by_levels(tampered, t.root())
+                    );
+
+                    // Dropping a proof node gives the same result, such as the same missing node
+                    if let Some(index) = proof.nodes().keys().next() {
+                        let mut nodes = proof.nodes().clone();
+                        nodes.remove(index);
+                        let partial = MultiProofHashes::<_, HashAlgo>::from_leaf_count_and_nodes(
+                            proof.tree_leaf_count(),
+                            nodes,
+                        );
+                        let expected =
+                            partial
+                                .verify_by_levels(proven.clone(), t.root())
+                                .map_err(|err| match err {
+                                    MerkleProofVerificationError::RequiredNodeMissing(index) => {
+                                        MerkleProofVerificationError::RequiredNodeMissing64(
+                                            index.into(),
+                                        )
+                                    }
+                                    err => err,
+                                });
+                        assert_eq!(
+                            MultiProofHashes64::from(partial)
+                                .verify(to_leaves64(&proven), t.root()),
+                            expected
+                        );
+                    }
+
+                    let narrowed = MultiProofHashes::try_from(proof64).ok().unwrap();
+                    assert_eq!(narrowed.tree_leaf_count(), proof.tree_leaf_count());
+                    assert_eq!(narrowed.nodes(), proof.nodes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn beyond_32_bit_tree() {
+        // A proof of the first and the last leaves in a tree of 2^40 leaves, where all leaves are the same,
+        // so each level of the tree has a single distinct node
+        let levels = 40;
+        let leaf_count = 1u64 << levels;
+        let tree_size = TreeSize64::from_leaf_count(leaf_count).unwrap();
+        let leaf = hash_data(HashedData::zero());
+        let level_nodes = (0..=levels)
+            .scan(leaf, |node, _| {
+                let current = *node;
+                *node = HashAlgo::hash_pair(node, node);
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+        let root = level_nodes[levels];
+
+        // The second and the second to last nodes of every level below the top two
+        let nodes = (0..levels - 1)
+            .flat_map(|level| {
+                let start = tree_size.level_start(level as u32).unwrap();
+                let level_leaf_count = leaf_count >> level;
+                [
+                    (start + 1, level_nodes[level]),
+                    (start + level_leaf_count - 2, level_nodes[level]),
+                ]
+            })
+            .collect::<BTreeMap<_, _>>();
+        let proof = MultiProofHashes64::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+            leaf_count, nodes,
+        );
+
+        let leaves = BTreeMap::from([(0, leaf), (leaf_count - 1, leaf)]);
+        assert!(proof
+            .verify(leaves.clone(), root)
+            .unwrap()
+            .passed_decisively());
+        let wrong_leaves = BTreeMap::from([(0, leaf), (leaf_count - 1, HashedData::zero())]);
+        assert!(proof.verify(wrong_leaves, root).unwrap().failed());
+
+        let narrowed = MultiProofHashes::try_from(proof.clone()).err().unwrap();
+        assert_eq!(narrowed, proof);
+    }
+
+    #[test]
+    fn invalid_proofs() {
+        let leaf = hash_data(HashedData::zero());
+        let leaves = BTreeMap::from([(0, leaf)]);
+
+        for leaf_count in [0, 3, 1 << 63, u64::MAX] {
+            let proof = MultiProofHashes64::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+                leaf_count,
+                BTreeMap::new(),
+            );
+            assert_eq!(
+                proof.verify(leaves.clone(), leaf),
+                Err(MerkleProofVerificationError::InvalidTreeLeavesCount64(
+                    leaf_count
+                ))
+            );
+        }
+
+        let proof = MultiProofHashes64::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+            1 << 40,
+            BTreeMap::from([((1 << 41) - 1, leaf)]),
+        );
+        assert_eq!(
+            proof.verify(BTreeMap::new(), leaf),
+            Err(MerkleProofVerificationError::LeavesContainerProvidedIsEmpty)
+        );
+        assert_eq!(
+            proof.verify(BTreeMap::from([(1 << 40, leaf)]), leaf),
+            Err(MerkleProofVerificationError::LeavesIndicesOutOfRange64(
+                vec![1 << 40],
+                1 << 40
+            ))
+        );
+        assert_eq!(
+            proof.verify(leaves.clone(), leaf),
+            Err(MerkleProofVerificationError::NodesIndicesOutOfRange64(
+                vec![(1 << 41) - 1],
+                (1 << 41) - 1
+            ))
+        );
+    }
+}
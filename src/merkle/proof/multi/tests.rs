@@ -17,10 +17,16 @@
 #![allow(clippy::unnecessary_lazy_evaluations)]
 
 use crate::rand_tools::{make_seedable_rng, Seed};
-use rand::seq::SliceRandom;
+use rand::{
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
 use rstest::rstest;
 
-use crate::internal::{hash_data, HashAlgo, HashedData};
+use crate::{
+    internal::{hash_data, HashAlgo, HashedData},
+    proof::{diagnostics::NodeSource, single::SingleProofNodes},
+};
 
 use super::*;
 
@@ -482,6 +488,14 @@ fn multi_proof_eight_leaves_with_proof_leaves(#[case] input: &[u32], #[case] nod
     assert_eq!(multi_proof.tree_leaf_count(), t.leaf_count().get());
 }
 
+/// Sorted random non-empty subset of the leaves indices
+fn gen_random_leaves_indices(rng: &mut impl Rng, leaf_count: u32) -> Vec<u32> {
+    let proven_leaf_count = rng.gen_range(1..=leaf_count as usize);
+    let mut leaves_indices = (0..leaf_count).choose_multiple(rng, proven_leaf_count);
+    leaves_indices.sort();
+    leaves_indices
+}
+
 fn gen_leaves_indices_combinations(leaf_count: u32) -> impl Iterator<Item = Vec<u32>> {
     assert!(
         leaf_count.is_power_of_two(),
@@ -877,3 +891,927 @@ fn multi_proof_verification_tampered_tree_size_into_wrong_value(
         );
     }
 }
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1, None)]
+#[trace]
+#[case(Seed::from_entropy(), 2, None)]
+#[trace]
+#[case(Seed::from_entropy(), 4, None)]
+#[trace]
+#[case(Seed::from_entropy(), 8, None)]
+#[trace]
+#[case(Seed::from_entropy(), 16, Some(200))]
+#[trace]
+#[case(Seed::from_entropy(), 32, Some(200))]
+#[trace]
+#[case(Seed::from_entropy(), 64, Some(200))]
+fn multi_proof_from_single_proofs(
+    #[case] seed: Seed,
+    #[case] leaf_count: u32,
+    #[case] max_test_cases: Option<usize>,
+) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    let mut cases = gen_leaves_indices_combinations(leaf_count)
+        .take(max_test_cases.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+    cases.shuffle(&mut rng);
+
+    for leaves_indices in cases {
+        if leaves_indices.is_empty() {
+            continue;
+        }
+
+        // The order of the single proofs, and their duplication, shouldn't matter
+        let mut single_proofs = leaves_indices
+            .iter()
+            .map(|i| {
+                SingleProofNodes::from_tree_leaf(&t, *i)
+                    .unwrap()
+                    .into_values()
+            })
+            .collect::<Vec<_>>();
+        single_proofs.push(single_proofs[0].clone());
+        single_proofs.shuffle(&mut rng);
+
+        let combined = MultiProofHashes::from_single_proofs(&single_proofs).unwrap();
+        let expected = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+            .unwrap()
+            .into_values();
+
+        assert_eq!(combined.tree_leaf_count(), expected.tree_leaf_count());
+        assert_eq!(
+            combined.nodes(),
+            expected.nodes(),
+            "Failed for indices: {:?}",
+            leaves_indices
+        );
+        let verify_result = combined
+            .verify(indices_to_map(&leaves_indices, &leaves), t.root())
+            .unwrap();
+        if leaf_count == 1 {
+            assert!(verify_result.passed_trivially());
+        } else {
+            assert!(verify_result.passed_decisively());
+        }
+    }
+}
+
+#[test]
+fn multi_proof_from_single_proofs_errors() {
+    let leaves = gen_leaves(8);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+    let other_t = MerkleTree::<HashedData, HashAlgo>::from_leaves(gen_leaves(4)).unwrap();
+
+    let proof = |tree: &MerkleTree<HashedData, HashAlgo>, i: u32| {
+        SingleProofNodes::from_tree_leaf(tree, i)
+            .unwrap()
+            .into_values()
+    };
+
+    assert_eq!(
+        MultiProofHashes::<HashedData, HashAlgo>::from_single_proofs(&[])
+            .err()
+            .unwrap(),
+        MerkleProofCombinationError::NoProofsProvided
+    );
+
+    assert_eq!(
+        MultiProofHashes::from_single_proofs(&[proof(&t, 0), proof(&other_t, 1)])
+            .err()
+            .unwrap(),
+        MerkleProofCombinationError::BranchLengthMismatch(3, 2)
+    );
+
+    let out_of_range = SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+        8,
+        proof(&t, 0).branch().to_vec(),
+    );
+    assert_eq!(
+        MultiProofHashes::from_single_proofs(&[proof(&t, 0), out_of_range])
+            .err()
+            .unwrap(),
+        MerkleProofCombinationError::LeafIndexOutOfRange(8, 8)
+    );
+
+    // The proofs of leaves 0 and 2 share the sibling at absolute index 13
+    let mut tampered = proof(&t, 2);
+    let mut branch = tampered.branch().to_vec();
+    branch[2] = HashedData::zero();
+    tampered =
+        SingleProofHashes::from_leaf_index_and_branch(tampered.leaf_index_in_level(), branch);
+    assert_eq!(
+        MultiProofHashes::from_single_proofs(&[proof(&t, 0), tampered])
+            .err()
+            .unwrap(),
+        MerkleProofCombinationError::ConflictingNodes(13)
+    );
+
+    let too_long = SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+        0,
+        vec![HashedData::zero(); 32],
+    );
+    assert_eq!(
+        MultiProofHashes::from_single_proofs(&[too_long])
+            .err()
+            .unwrap(),
+        MerkleProofCombinationError::BranchTooLong(32)
+    );
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1, None)]
+#[trace]
+#[case(Seed::from_entropy(), 2, None)]
+#[trace]
+#[case(Seed::from_entropy(), 4, None)]
+#[trace]
+#[case(Seed::from_entropy(), 8, None)]
+#[trace]
+#[case(Seed::from_entropy(), 16, Some(200))]
+#[trace]
+#[case(Seed::from_entropy(), 32, Some(200))]
+#[trace]
+#[case(Seed::from_entropy(), 64, Some(200))]
+fn multi_proof_into_single_proofs(
+    #[case] seed: Seed,
+    #[case] leaf_count: u32,
+    #[case] max_test_cases: Option<usize>,
+) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    let mut cases = gen_leaves_indices_combinations(leaf_count)
+        .take(max_test_cases.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+    cases.shuffle(&mut rng);
+
+    for leaves_indices in cases {
+        if leaves_indices.is_empty() {
+            continue;
+        }
+
+        let multi_proof = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+            .unwrap()
+            .into_values();
+        let single_proofs = multi_proof
+            .clone()
+            .into_single_proofs(indices_to_map(&leaves_indices, &leaves))
+            .unwrap();
+
+        let expected = leaves_indices
+            .iter()
+            .map(|i| {
+                SingleProofNodes::from_tree_leaf(&t, *i)
+                    .unwrap()
+                    .into_values()
+            })
+            .map(|p| (p.leaf_index_in_level(), p.into_hashes()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            single_proofs
+                .iter()
+                .map(|p| (p.leaf_index_in_level(), p.branch().to_vec()))
+                .collect::<Vec<_>>(),
+            expected,
+            "Failed for indices: {:?}",
+            leaves_indices
+        );
+
+        // Going back and forth gives the original multi-proof
+        let recombined = MultiProofHashes::from_single_proofs(&single_proofs).unwrap();
+        assert_eq!(recombined.nodes(), multi_proof.nodes());
+        assert_eq!(recombined.tree_leaf_count(), multi_proof.tree_leaf_count());
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 4)]
+#[trace]
+#[case(Seed::from_entropy(), 8)]
+#[trace]
+#[case(Seed::from_entropy(), 16)]
+#[trace]
+#[case(Seed::from_entropy(), 32)]
+fn multi_proof_into_single_proofs_missing_node(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    let proven_leaf_count = rng.gen_range(1..leaf_count as usize);
+    let mut leaves_indices = (0..leaf_count).choose_multiple(&mut rng, proven_leaf_count);
+    leaves_indices.sort();
+
+    let multi_proof = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+        .unwrap()
+        .into_values();
+
+    for removed_index in multi_proof.nodes().keys() {
+        let mut nodes = multi_proof.nodes().clone();
+        nodes.remove(removed_index);
+        let incomplete = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+            multi_proof.tree_leaf_count(),
+            nodes,
+        );
+
+        // The missing node may be the first one hit, or one of its ancestors that can't be computed anymore
+        let verify_error = incomplete
+            .verify(indices_to_map(&leaves_indices, &leaves), t.root())
+            .unwrap_err();
+        let split_error = incomplete
+            .into_single_proofs(indices_to_map(&leaves_indices, &leaves))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            split_error,
+            MerkleProofVerificationError::RequiredNodeMissing(_)
+        ));
+        assert_eq!(split_error, verify_error);
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1)]
+#[trace]
+#[case(Seed::from_entropy(), 2)]
+#[trace]
+#[case(Seed::from_entropy(), 4)]
+#[trace]
+#[case(Seed::from_entropy(), 8)]
+#[trace]
+#[case(Seed::from_entropy(), 16)]
+#[trace]
+#[case(Seed::from_entropy(), 32)]
+#[trace]
+#[case(Seed::from_entropy(), 64)]
+#[trace]
+#[case(Seed::from_entropy(), 128)]
+fn multi_proof_merge(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    for _ in 0..50 {
+        let indices_a = gen_random_leaves_indices(&mut rng, leaf_count);
+        let indices_b = gen_random_leaves_indices(&mut rng, leaf_count);
+
+        let proof_a = MultiProofNodes::from_tree_leaves(&t, &indices_a)
+            .unwrap()
+            .into_values();
+        let proof_b = MultiProofNodes::from_tree_leaves(&t, &indices_b)
+            .unwrap()
+            .into_values();
+
+        let union = indices_a
+            .iter()
+            .chain(indices_b.iter())
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let expected = MultiProofNodes::from_tree_leaves(&t, &union)
+            .unwrap()
+            .into_values();
+
+        for merged in [
+            proof_a.merge(&proof_b, &union).unwrap(),
+            proof_b.merge(&proof_a, &union).unwrap(),
+        ] {
+            assert_eq!(merged.tree_leaf_count(), expected.tree_leaf_count());
+            assert_eq!(
+                merged.nodes(),
+                expected.nodes(),
+                "Failed for indices: {:?} and {:?}",
+                indices_a,
+                indices_b
+            );
+            assert!(!merged
+                .verify(indices_to_map(&union, &leaves), t.root())
+                .unwrap()
+                .failed());
+        }
+    }
+}
+
+#[test]
+fn multi_proof_merge_errors() {
+    let leaves = gen_leaves(8);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+    let other_t = MerkleTree::<HashedData, HashAlgo>::from_leaves(gen_leaves(4)).unwrap();
+
+    let proof_a = MultiProofNodes::from_tree_leaves(&t, &[0])
+        .unwrap()
+        .into_values();
+    let proof_b = MultiProofNodes::from_tree_leaves(&t, &[2])
+        .unwrap()
+        .into_values();
+    let proof_other = MultiProofNodes::from_tree_leaves(&other_t, &[0])
+        .unwrap()
+        .into_values();
+
+    assert_eq!(
+        proof_a.merge(&proof_other, &[0]).err().unwrap(),
+        MerkleProofCombinationError::TreeLeafCountMismatch(8, 4)
+    );
+
+    // Both proofs provide the node at absolute index 13
+    let mut nodes = proof_b.nodes().clone();
+    nodes.insert(13, HashedData::zero());
+    let tampered = MultiProofHashes::from_leaf_count_and_nodes(8, nodes);
+    assert_eq!(
+        proof_a.merge(&tampered, &[0, 2]).err().unwrap(),
+        MerkleProofCombinationError::ConflictingNodes(13)
+    );
+
+    let invalid_a =
+        MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(6, BTreeMap::new());
+    let invalid_b = invalid_a.clone();
+    assert_eq!(
+        invalid_a.merge(&invalid_b, &[0]).err().unwrap(),
+        MerkleProofCombinationError::InvalidTreeLeafCount(6)
+    );
+    for leaf_count in [1 << 31, u32::MAX] {
+        let huge = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+            leaf_count,
+            BTreeMap::new(),
+        );
+        assert_eq!(
+            huge.merge(&huge, &[0]).err().unwrap(),
+            MerkleProofCombinationError::InvalidTreeLeafCount(leaf_count)
+        );
+    }
+
+    assert_eq!(
+        proof_a.merge(&proof_b, &[0, 8]).err().unwrap(),
+        MerkleProofCombinationError::LeafIndexOutOfRange(8, 8)
+    );
+}
+
+#[test]
+fn multi_proof_merge_in_huge_tree() {
+    // A proof without nodes proves all the leaves, which would be all the leaves of the tree to infer,
+    // but the given leaves are only two, so only their paths are visited
+    let leaf_count = 1 << 30;
+    let empty = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+        leaf_count,
+        BTreeMap::new(),
+    );
+    let merged = empty.merge(&empty, &[0, leaf_count - 1]).unwrap();
+    assert_eq!(merged.tree_leaf_count(), leaf_count);
+    assert!(merged.nodes().is_empty());
+
+    // The siblings of the paths of the first and the last leaves, which are all still needed together,
+    // except the children of the root, which are on the path of the other leaf
+    let tree_size = TreeSize::from_leaf_count(leaf_count).unwrap();
+    let first = NodePosition::from_position(tree_size, 0, 0).unwrap();
+    let last = NodePosition::from_position(tree_size, 0, leaf_count - 1).unwrap();
+    let siblings = |leaf: NodePosition| {
+        leaf.into_iter_parents()
+            .filter_map(|node| node.sibling())
+            .map(|node| (node.abs_index(), HashedData::zero()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let proof_first = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+        leaf_count,
+        siblings(first),
+    );
+    let proof_last = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+        leaf_count,
+        siblings(last),
+    );
+    let merged = proof_first
+        .merge(&proof_last, &[0, leaf_count - 1])
+        .unwrap();
+    let root_children = [tree_size.get() - 3, tree_size.get() - 2];
+    let expected = siblings(first)
+        .into_iter()
+        .chain(siblings(last))
+        .filter(|(index, _)| !root_children.contains(index))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(merged.nodes(), &expected);
+}
+
+#[test]
+fn multi_proof_verification_one_leaf_tampered() {
+    let leaves = gen_leaves(1);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+    let multi_proof = MultiProofNodes::from_tree_leaves(&t, &[0])
+        .unwrap()
+        .into_values();
+    let tampered_leaves = indices_to_map(&[0], &gen_leaves(2)[1..]);
+
+    assert!(multi_proof
+        .verify(tampered_leaves.clone(), t.root())
+        .unwrap()
+        .failed());
+    assert!(multi_proof
+        .verify_by_levels(tampered_leaves, t.root())
+        .unwrap()
+        .failed());
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1)]
+#[trace]
+#[case(Seed::from_entropy(), 2)]
+#[trace]
+#[case(Seed::from_entropy(), 4)]
+#[trace]
+#[case(Seed::from_entropy(), 8)]
+#[trace]
+#[case(Seed::from_entropy(), 16)]
+#[trace]
+#[case(Seed::from_entropy(), 32)]
+#[trace]
+#[case(Seed::from_entropy(), 64)]
+#[trace]
+#[case(Seed::from_entropy(), 128)]
+fn multi_proof_verifiers_agree(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+    let tree_size = t.total_node_count().get();
+
+    for _ in 0..200 {
+        let leaves_indices = gen_random_leaves_indices(&mut rng, leaf_count);
+        let multi_proof = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+            .unwrap()
+            .into_values();
+
+        let mut nodes = multi_proof.nodes().clone();
+        let mut leaves_hashes_map = indices_to_map(&leaves_indices, &leaves);
+        let mut tree_leaf_count = multi_proof.tree_leaf_count();
+
+        // Apply a random tampering, or none at all
+        match rng.gen_range(0..7) {
+            0 => {}
+            1 => {
+                if let Some(index) = nodes.keys().choose(&mut rng).copied() {
+                    nodes.insert(index, HashedData::random_using(&mut rng));
+                }
+            }
+            2 => {
+                let index = *leaves_hashes_map.keys().choose(&mut rng).unwrap();
+                leaves_hashes_map.insert(index, HashedData::random_using(&mut rng));
+            }
+            3 => {
+                for _ in 0..rng.gen_range(1..=3) {
+                    if let Some(index) = nodes.keys().choose(&mut rng).copied() {
+                        nodes.remove(&index);
+                    }
+                }
+            }
+            4 => {
+                let index = rng.gen_range(0..tree_size);
+                nodes.insert(index, HashedData::random_using(&mut rng));
+            }
+            5 => {
+                let index = rng.gen_range(0..tree_size + 2);
+                nodes.insert(index, HashedData::random_using(&mut rng));
+            }
+            6 => {
+                tree_leaf_count = *[0, 1, 2, 3, tree_leaf_count * 2, tree_leaf_count / 2]
+                    .choose(&mut rng)
+                    .unwrap();
+            }
+            _ => unreachable!(),
+        }
+
+        let proof = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+            tree_leaf_count,
+            nodes,
+        );
+
+        assert_eq!(
+            proof.verify(leaves_hashes_map.clone(), t.root()),
+            proof.verify_by_levels(leaves_hashes_map, t.root()),
+            "Failed for indices: {:?}",
+            leaves_indices
+        );
+    }
+}
+
+fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    if v.is_empty() {
+        return Vec::new();
+    }
+    let len = v[0].len();
+    let mut iters: Vec<_> = v.into_iter().map(|n| n.into_iter()).collect();
+    (0..len)
+        .map(|_| {
+            iters
+                .iter_mut()
+                .map(|n| n.next().expect("Iter is in tandem. Should never happen."))
+                .collect::<Vec<T>>()
+        })
+        .collect()
+}
+
+/// The original extraction algorithm, which builds the single proofs of all the leaves and goes through
+/// their branches level by level. It's kept as a reference for the frontier-based algorithm.
+fn reference_proof_nodes_indices(
+    tree: &MerkleTree<HashedData, HashAlgo>,
+    leaves_indices: &[u32],
+) -> Vec<u32> {
+    let single_proofs_branches = leaves_indices
+        .iter()
+        .map(|i| {
+            SingleProofNodes::from_tree_leaf_including_padding(tree, *i)
+                .unwrap()
+                .branch()
+                .iter()
+                .map(|node| node.abs_index())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut computed_from_prev_level = BTreeSet::new();
+    let mut proof = Vec::new();
+
+    for nodes_of_level in transpose(single_proofs_branches) {
+        let siblings = nodes_of_level
+            .iter()
+            .map(|index| index ^ 1)
+            .collect::<BTreeSet<u32>>();
+
+        let proofs_at_level = nodes_of_level
+            .into_iter()
+            .filter(|index| !siblings.contains(index))
+            .filter(|index| !computed_from_prev_level.contains(index))
+            .collect::<BTreeSet<_>>();
+
+        computed_from_prev_level = proofs_at_level
+            .iter()
+            .tuple_windows::<(&u32, &u32)>()
+            .filter(|(i, j)| *i % 2 == 0 && *i + 1 == **j)
+            .map(|(i, _j)| {
+                NodePosition::from_abs_index(tree.total_node_count(), *i)
+                    .unwrap()
+                    .parent()
+                    .unwrap()
+                    .abs_index()
+            })
+            .collect();
+
+        proof.extend(proofs_at_level);
+    }
+
+    proof
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1)]
+#[trace]
+#[case(Seed::from_entropy(), 2)]
+#[trace]
+#[case(Seed::from_entropy(), 3)]
+#[trace]
+#[case(Seed::from_entropy(), 8)]
+#[trace]
+#[case(Seed::from_entropy(), 13)]
+#[trace]
+#[case(Seed::from_entropy(), 64)]
+#[trace]
+#[case(Seed::from_entropy(), 100)]
+#[trace]
+#[case(Seed::from_entropy(), 1024)]
+fn multi_proof_extraction_matches_reference(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+    for _ in 0..100 {
+        let leaves_indices = gen_random_leaves_indices(&mut rng, t.leaf_count().get());
+        let multi_proof =
+            MultiProofNodes::from_tree_leaves_including_padding(&t, &leaves_indices).unwrap();
+
+        assert_eq!(
+            multi_proof
+                .nodes()
+                .iter()
+                .map(|node| node.abs_index())
+                .collect::<Vec<_>>(),
+            reference_proof_nodes_indices(&t, &leaves_indices),
+            "Failed for indices: {:?}",
+            leaves_indices
+        );
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1)]
+#[trace]
+#[case(Seed::from_entropy(), 2)]
+#[trace]
+#[case(Seed::from_entropy(), 7)]
+#[trace]
+#[case(Seed::from_entropy(), 16)]
+#[trace]
+#[case(Seed::from_entropy(), 100)]
+fn multi_proof_compute_root_and_nodes(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    for _ in 0..50 {
+        let leaves_indices = gen_random_leaves_indices(&mut rng, leaf_count);
+        let multi_proof = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+            .unwrap()
+            .into_values();
+        let leaves_hashes_map = indices_to_map(&leaves_indices, &leaves);
+
+        assert_eq!(
+            multi_proof.compute_root(leaves_hashes_map.clone()).unwrap(),
+            t.root()
+        );
+
+        // The reconstructed nodes are exactly the ancestors of the proven leaves
+        let expected_nodes = leaves_indices
+            .iter()
+            .flat_map(|i| {
+                t.node_from_bottom(0, *i)
+                    .unwrap()
+                    .into_iter_parents()
+                    .skip(1)
+            })
+            .map(|node| (node.abs_index(), *node.hash()))
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            multi_proof.compute_nodes(leaves_hashes_map).unwrap(),
+            expected_nodes
+        );
+    }
+}
+
+#[test]
+fn multi_proof_compute_root_missing_node() {
+    let leaves = gen_leaves(8);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    let multi_proof = MultiProofNodes::from_tree_leaves(&t, &[0, 5])
+        .unwrap()
+        .into_values();
+    let mut nodes = multi_proof.nodes().clone();
+    nodes.remove(&1);
+    let incomplete = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(8, nodes);
+
+    assert_eq!(
+        incomplete
+            .compute_root(indices_to_map(&[0, 5], &leaves))
+            .unwrap_err(),
+        MerkleProofVerificationError::RequiredNodeMissing(1)
+    );
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 1)]
+#[trace]
+#[case(Seed::from_entropy(), 8)]
+#[trace]
+#[case(Seed::from_entropy(), 13)]
+#[trace]
+#[case(Seed::from_entropy(), 64)]
+fn multi_proof_verify_with_diagnostics(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    for _ in 0..20 {
+        let leaves_indices = gen_random_leaves_indices(&mut rng, leaf_count);
+        let multi_proof = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+            .unwrap()
+            .into_values();
+        let leaves_hashes_map = indices_to_map(&leaves_indices, &leaves);
+
+        let diagnostics = multi_proof
+            .verify_with_diagnostics(leaves_hashes_map.clone(), t.root(), &t)
+            .unwrap();
+        assert_eq!(
+            diagnostics.result(),
+            multi_proof
+                .verify(leaves_hashes_map.clone(), t.root())
+                .unwrap()
+        );
+        assert!(!diagnostics.result().failed());
+        assert!(diagnostics.mismatches().is_empty());
+        assert_eq!(diagnostics.first_failed_leaf(), None);
+
+        // Tamper with one of the leaves
+        let tampered_leaf = *leaves_indices.choose(&mut rng).unwrap();
+        let mut tampered_leaves = leaves_hashes_map.clone();
+        tampered_leaves.insert(tampered_leaf, HashedData::random_using(&mut rng));
+
+        let diagnostics = multi_proof
+            .verify_with_diagnostics(tampered_leaves, t.root(), &t)
+            .unwrap();
+        assert!(diagnostics.result().failed());
+        let mismatch = &diagnostics.mismatches()[&tampered_leaf];
+        assert_eq!(mismatch.level(), 0);
+        assert_eq!(mismatch.source(), NodeSource::Leaf);
+        assert_eq!(*mismatch.expected(), leaves[tampered_leaf as usize]);
+        assert!(diagnostics.first_failed_leaf().unwrap() <= tampered_leaf);
+
+        // Tamper with one of the proof nodes
+        if let Some(tampered_index) = multi_proof.nodes().keys().choose(&mut rng).copied() {
+            let mut nodes = multi_proof.nodes().clone();
+            nodes.insert(tampered_index, HashedData::random_using(&mut rng));
+            let tampered = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+                multi_proof.tree_leaf_count(),
+                nodes,
+            );
+
+            let diagnostics = tampered
+                .verify_with_diagnostics(leaves_hashes_map, t.root(), &t)
+                .unwrap();
+            assert!(diagnostics.result().failed());
+            assert!(diagnostics.mismatches().values().any(|mismatch| {
+                mismatch.abs_index() == tampered_index && mismatch.source() == NodeSource::Proof
+            }));
+        }
+    }
+}
+
+#[test]
+fn multi_proof_padding_leaves() {
+    let leaves = gen_leaves(5);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+    let padded_leaves = (0..8)
+        .map(|i| t.node_value_from_bottom(0, i).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        MultiProofNodes::from_tree_leaves(&t, &[1, 4, 6]).unwrap_err(),
+        MerkleTreeProofExtractionError::PaddingLeaf(6, 5)
+    );
+
+    // A proof of a padding leaf verifies, unless the real leaf count is required
+    let with_padding = MultiProofNodes::from_tree_leaves_including_padding(&t, &[1, 4, 6])
+        .unwrap()
+        .into_values();
+    let leaves_hashes_map = indices_to_map(&[1, 4, 6], &padded_leaves);
+    assert!(with_padding
+        .verify(leaves_hashes_map.clone(), t.root())
+        .unwrap()
+        .passed_decisively());
+    assert!(with_padding
+        .verify_within_real_leaf_count(leaves_hashes_map.clone(), t.root(), 8)
+        .unwrap()
+        .passed_decisively());
+    assert_eq!(
+        with_padding
+            .verify_within_real_leaf_count(leaves_hashes_map.clone(), t.root(), 5)
+            .unwrap_err(),
+        MerkleProofVerificationError::LeavesIndicesOutOfRange(vec![1, 4, 6], 5)
+    );
+
+    let real = MultiProofNodes::from_tree_leaves(&t, &[1, 4])
+        .unwrap()
+        .into_values();
+    assert!(real
+        .verify_within_real_leaf_count(indices_to_map(&[1, 4], &leaves), t.root(), 5)
+        .unwrap()
+        .passed_decisively());
+
+    for inconsistent in [0, 4, 9] {
+        assert_eq!(
+            real.verify_within_real_leaf_count(
+                indices_to_map(&[1, 4], &leaves),
+                t.root(),
+                inconsistent
+            )
+            .unwrap_err(),
+            MerkleProofVerificationError::InvalidTreeLeavesCount(inconsistent)
+        );
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn multi_proof_verify_count_committed(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for leaf_count in 1..40 {
+        let leaves = gen_leaves(leaf_count);
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+        let committed_root = t.count_committed_root();
+
+        let leaves_indices = gen_random_leaves_indices(&mut rng, leaf_count);
+        let proof = MultiProofNodes::from_tree_leaves(&t, &leaves_indices)
+            .unwrap()
+            .into_values();
+        let leaves_hashes_map = indices_to_map(&leaves_indices, &leaves);
+
+        assert!(proof
+            .verify_count_committed(leaves_hashes_map.clone(), committed_root, leaf_count)
+            .unwrap()
+            .passed_decisively());
+        assert!(proof
+            .verify_count_committed(leaves_hashes_map.clone(), t.root(), leaf_count)
+            .unwrap()
+            .failed());
+
+        // Another real leaf count that is consistent with the tree leaf count and the leaves
+        let min_leaf_count = core::cmp::max(
+            *leaves_indices.last().unwrap() + 1,
+            t.leaf_count().get() / 2 + 1,
+        );
+        let other_leaf_count = (min_leaf_count..=t.leaf_count().get())
+            .filter(|count| *count != leaf_count)
+            .choose(&mut rng);
+        if let Some(other_leaf_count) = other_leaf_count {
+            assert!(proof
+                .verify_count_committed(leaves_hashes_map.clone(), committed_root, other_leaf_count)
+                .unwrap()
+                .failed());
+        }
+
+        assert_eq!(
+            proof
+                .verify_count_committed(
+                    leaves_hashes_map.clone(),
+                    committed_root,
+                    t.leaf_count().get() * 2 + 1
+                )
+                .unwrap_err(),
+            MerkleProofVerificationError::InvalidTreeLeavesCount(t.leaf_count().get() * 2 + 1)
+        );
+    }
+}
+
+#[test]
+fn multi_proof_with_huge_tree_leaf_count() {
+    // Proofs may come from untrusted sources, so leaf counts that are too large for a tree must be errors, not panics
+    let leaves = BTreeMap::from([(0, HashedData::zero())]);
+    for tree_leaf_count in [1 << 31, u32::MAX] {
+        let proof = MultiProofHashes::<HashedData, HashAlgo>::from_leaf_count_and_nodes(
+            tree_leaf_count,
+            BTreeMap::new(),
+        );
+        let expected_err = MerkleProofVerificationError::InvalidTreeLeavesCount(tree_leaf_count);
+
+        assert_eq!(
+            proof.verify(leaves.clone(), HashedData::zero()),
+            Err(expected_err.clone())
+        );
+        assert_eq!(
+            proof.verify_by_levels(leaves.clone(), HashedData::zero()),
+            Err(expected_err.clone())
+        );
+        assert_eq!(
+            proof.compute_root(leaves.clone()),
+            Err(expected_err.clone())
+        );
+        assert_eq!(
+            proof.into_single_proofs(leaves.clone()).err(),
+            Some(expected_err)
+        );
+    }
+}
+
+#[test]
+fn multi_proof_verify_by_levels_in_huge_tree() {
+    // Only the nodes of the proof are visited, so a proof of a huge tree is verified as fast as a proof of a small one
+    let branch = (0..30)
+        .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+        .collect::<Vec<_>>();
+    let leaf = hash_data(HashedData::zero());
+    let leaf_index = (1 << 29) + 5;
+    let single_proof =
+        SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(leaf_index, branch);
+    let root = single_proof.compute_root(leaf);
+
+    let proof = MultiProofHashes::from_single_proofs(&[single_proof]).unwrap();
+    assert_eq!(proof.tree_leaf_count(), 1 << 30);
+    assert_eq!(
+        proof.verify_by_levels(BTreeMap::from([(leaf_index, leaf)]), root),
+        Ok(ProofVerifyResult::PassedDecisively)
+    );
+    assert_eq!(
+        proof.verify_by_levels(BTreeMap::from([(leaf_index, HashedData::zero())]), root),
+        Ok(ProofVerifyResult::Failed)
+    );
+}
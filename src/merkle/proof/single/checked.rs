@@ -0,0 +1,441 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::merkle::{
+    hasher::{LeafCountCommitter, PairHasher},
+    proof::verify_result::ProofVerifyResult,
+    tree::{store::NodeStore, tree_size::TreeSize, MerkleTree},
+    MerkleProofVerificationError, MerkleTreeProofExtractionError,
+};
+
+use super::{
+    compute_branch_root, proof_ref::SingleProofRef, verify_branch, SingleProofHashes,
+    SingleProofNodes,
+};
+
+/// Same as `SingleProofHashes`, but also carries the number of leaves of the tree, and checks that the
+/// leaf index is within it before verifying. Without this, the high bits of the leaf index that are
+/// beyond the length of the branch are ignored, and different indices verify identically.
+/// The leaf count can be that of the (padded) tree, or the real number of leaves before padding,
+/// in which case proving a padding leaf is rejected.
+#[must_use]
+#[cfg_attr(
+    feature = "scale-codec",
+    derive(parity_scale_codec::Encode, parity_scale_codec::Decode)
+)]
+pub struct CheckedSingleProofHashes<T, H> {
+    leaf_index_in_level: u32,
+    branch: Vec<T>,
+    leaf_count: u32,
+    _hasher: core::marker::PhantomData<H>,
+}
+
+impl<T: Debug, H> Debug for CheckedSingleProofHashes<T, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CheckedSingleProofHashes")
+            .field("leaf_index_in_level", &self.leaf_index_in_level)
+            .field("branch", &self.branch)
+            .field("leaf_count", &self.leaf_count)
+            .finish()
+    }
+}
+
+impl<T: Clone, H> Clone for CheckedSingleProofHashes<T, H> {
+    fn clone(&self) -> Self {
+        Self {
+            leaf_index_in_level: self.leaf_index_in_level,
+            branch: self.branch.clone(),
+            leaf_count: self.leaf_count,
+            _hasher: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: PartialEq, H> PartialEq for CheckedSingleProofHashes<T, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf_index_in_level == other.leaf_index_in_level
+            && self.branch == other.branch
+            && self.leaf_count == other.leaf_count
+    }
+}
+
+impl<T: Eq, H> Eq for CheckedSingleProofHashes<T, H> {}
+
+impl<T, H> CheckedSingleProofHashes<T, H> {
+    pub fn branch(&self) -> &[T] {
+        &self.branch
+    }
+
+    pub fn leaf_index_in_level(&self) -> u32 {
+        self.leaf_index_in_level
+    }
+
+    /// The number of leaves that the leaf index is bound to
+    pub fn leaf_count(&self) -> u32 {
+        self.leaf_count
+    }
+
+    /// Whether the proven leaf is the last one within the leaf count. This is meaningful only
+    /// after verifying with `verify_count_committed`, which proves the leaf count.
+    pub fn is_last_leaf(&self) -> bool {
+        self.leaf_index_in_level.checked_add(1) == Some(self.leaf_count)
+    }
+
+    /// Borrow this proof as an unchecked `SingleProofRef`.
+    pub fn as_proof_ref(&self) -> SingleProofRef<'_, T, H> {
+        SingleProofRef::from_leaf_index_and_branch(self.leaf_index_in_level, &self.branch)
+    }
+
+    /// Checks that the leaf count is consistent with the length of the branch,
+    /// and that the leaf index is within the leaf count.
+    pub fn check(&self) -> Result<(), MerkleProofVerificationError> {
+        let tree_leaf_count = Some(self.leaf_count)
+            .filter(|leaf_count| *leaf_count > 0)
+            .and_then(|leaf_count| leaf_count.checked_next_power_of_two())
+            .filter(|tree_leaf_count| TreeSize::from_leaf_count(*tree_leaf_count).is_ok())
+            .ok_or(MerkleProofVerificationError::InvalidTreeLeavesCount(
+                self.leaf_count,
+            ))?;
+
+        let expected_branch_length = tree_leaf_count.trailing_zeros() as usize;
+        if self.branch.len() != expected_branch_length {
+            return Err(MerkleProofVerificationError::BranchLengthMismatch(
+                expected_branch_length,
+                self.branch.len(),
+            ));
+        }
+
+        if self.leaf_index_in_level >= self.leaf_count {
+            return Err(MerkleProofVerificationError::LeafIndexOutOfRange(
+                self.leaf_index_in_level,
+                self.leaf_count,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Binds the proof to the given leaf count, after checking it. Only proofs of leaves can be bound.
+    pub fn from_proof(
+        proof: SingleProofHashes<T, H>,
+        leaf_count: u32,
+    ) -> Result<Self, MerkleProofVerificationError> {
+        if proof.level_from_bottom != 0 {
+            return Err(MerkleProofVerificationError::NotLeafLevel(
+                proof.level_from_bottom,
+            ));
+        }
+
+        let result = Self {
+            leaf_index_in_level: proof.leaf_index_in_level,
+            branch: proof.branch,
+            leaf_count,
+            _hasher: core::marker::PhantomData,
+        };
+        result.check()?;
+        Ok(result)
+    }
+
+    /// Drops the leaf count.
+    pub fn into_unchecked(self) -> SingleProofHashes<T, H> {
+        SingleProofHashes {
+            level_from_bottom: 0,
+            leaf_index_in_level: self.leaf_index_in_level,
+            branch: self.branch,
+            _hasher: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Clone, H: PairHasher<NodeType = T>> CheckedSingleProofHashes<T, H> {
+    /// Creates a proof for a leaf by its index in the lowest level, bound to the real leaf count of the tree,
+    /// so that padding leaves can't be proven with it.
+    pub fn from_tree_leaf<S: NodeStore<T>>(
+        tree: &MerkleTree<T, H, S>,
+        leaf_index: u32,
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        let proof = SingleProofNodes::from_tree_leaf(tree, leaf_index)?.into_values();
+        Ok(Self {
+            leaf_index_in_level: proof.leaf_index_in_level,
+            branch: proof.branch,
+            leaf_count: tree.real_leaf_count().get(),
+            _hasher: core::marker::PhantomData,
+        })
+    }
+}
+
+impl<T: Eq, H: PairHasher<NodeType = T>> CheckedSingleProofHashes<T, H> {
+    /// Checks the proof, then verifies that the given leaf can produce the root's hash.
+    pub fn verify(
+        &self,
+        leaf: T,
+        root: T,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        self.check()?;
+        Ok(verify_branch::<T, H>(
+            self.leaf_index_in_level.into(),
+            &self.branch,
+            leaf,
+            root,
+        ))
+    }
+
+    /// Checks the proof, then computes the root that the given leaf produces with it.
+    pub fn compute_root(&self, leaf: T) -> Result<T, MerkleProofVerificationError> {
+        self.check()?;
+        Ok(compute_branch_root::<T, H>(
+            self.leaf_index_in_level.into(),
+            &self.branch,
+            leaf,
+        ))
+    }
+}
+
+impl<T: Eq, H: LeafCountCommitter<NodeType = T>> CheckedSingleProofHashes<T, H> {
+    /// Checks the proof, then verifies that the given leaf, with the leaf count of this proof,
+    /// produce the count-committed root (see `MerkleTree::count_committed_root`).
+    /// The proof must be bound to the real leaf count of the tree for this to pass.
+    /// Since the commitment is always hashed, passing is always decisive.
+    pub fn verify_count_committed(
+        &self,
+        leaf: T,
+        committed_root: T,
+    ) -> Result<ProofVerifyResult, MerkleProofVerificationError> {
+        let computed = self.compute_count_committed_root(leaf)?;
+        Ok(ProofVerifyResult::PassedTrivially.or(computed == committed_root))
+    }
+
+    /// Checks the proof, then computes the count-committed root that the given leaf produces with it.
+    pub fn compute_count_committed_root(&self, leaf: T) -> Result<T, MerkleProofVerificationError> {
+        let root = self.compute_root(leaf)?;
+        Ok(H::commit_leaf_count(&root, self.leaf_count))
+    }
+}
+
+impl<T, H> From<CheckedSingleProofHashes<T, H>> for SingleProofHashes<T, H> {
+    fn from(proof: CheckedSingleProofHashes<T, H>) -> Self {
+        proof.into_unchecked()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internal::{hash_data, HashAlgo, HashedData};
+
+    use super::*;
+
+    fn gen_leaves(leaf_count: u32) -> Vec<HashedData> {
+        (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i as u64)))
+            .collect()
+    }
+
+    #[test]
+    fn verify_within_leaf_count() {
+        for leaf_count in 1..=17 {
+            let leaves = gen_leaves(leaf_count);
+            let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+            for (leaf_index, leaf) in leaves.iter().enumerate() {
+                let proof =
+                    CheckedSingleProofHashes::from_tree_leaf(&t, leaf_index as u32).unwrap();
+                assert_eq!(proof.leaf_count(), leaf_count);
+                assert_eq!(
+                    proof.verify(*leaf, t.root()).unwrap(),
+                    proof.as_proof_ref().verify(*leaf, t.root())
+                );
+                assert_eq!(proof.compute_root(*leaf).unwrap(), t.root());
+
+                // Bound to the padded leaf count
+                let bound = CheckedSingleProofHashes::from_proof(
+                    proof.clone().into_unchecked(),
+                    t.leaf_count().get(),
+                )
+                .unwrap();
+                assert_eq!(
+                    bound.verify(*leaf, t.root()).unwrap(),
+                    proof.verify(*leaf, t.root()).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn high_bits_of_index_are_rejected() {
+        let leaves = gen_leaves(8);
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+        let proof = SingleProofNodes::from_tree_leaf(&t, 3)
+            .unwrap()
+            .into_values();
+
+        // The unchecked proof ignores the high bits of the index
+        let aliased = SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+            3 + 8,
+            proof.branch().to_vec(),
+        );
+        assert!(aliased.verify(leaves[3], t.root()).passed_decisively());
+
+        let checked = CheckedSingleProofHashes {
+            leaf_index_in_level: 3 + 8,
+            branch: proof.branch().to_vec(),
+            leaf_count: 8,
+            _hasher: core::marker::PhantomData::<HashAlgo>,
+        };
+        assert_eq!(
+            checked.verify(leaves[3], t.root()).unwrap_err(),
+            MerkleProofVerificationError::LeafIndexOutOfRange(11, 8)
+        );
+        assert_eq!(
+            CheckedSingleProofHashes::from_proof(aliased, 8).unwrap_err(),
+            MerkleProofVerificationError::LeafIndexOutOfRange(11, 8)
+        );
+    }
+
+    #[test]
+    fn padding_leaves_are_rejected_with_real_leaf_count() {
+        let leaves = gen_leaves(5);
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+        for leaf_index in 5..8 {
+            assert_eq!(
+                CheckedSingleProofHashes::from_tree_leaf(&t, leaf_index).unwrap_err(),
+                MerkleTreeProofExtractionError::PaddingLeaf(leaf_index, 5)
+            );
+
+            let proof = SingleProofNodes::from_tree_leaf_including_padding(&t, leaf_index)
+                .unwrap()
+                .into_values();
+            assert!(CheckedSingleProofHashes::from_proof(proof.clone(), 8).is_ok());
+            assert_eq!(
+                CheckedSingleProofHashes::from_proof(proof, 5).unwrap_err(),
+                MerkleProofVerificationError::LeafIndexOutOfRange(leaf_index, 5)
+            );
+        }
+    }
+
+    #[test]
+    fn verify_count_committed() {
+        for leaf_count in 1..=17 {
+            let leaves = gen_leaves(leaf_count);
+            let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+            let committed_root = t.count_committed_root();
+
+            for (leaf_index, leaf) in leaves.iter().enumerate() {
+                let proof =
+                    CheckedSingleProofHashes::from_tree_leaf(&t, leaf_index as u32).unwrap();
+                assert_eq!(proof.is_last_leaf(), leaf_index as u32 == leaf_count - 1);
+                assert_eq!(
+                    proof.compute_count_committed_root(*leaf).unwrap(),
+                    committed_root
+                );
+                assert!(proof
+                    .verify_count_committed(*leaf, committed_root)
+                    .unwrap()
+                    .passed_decisively());
+                assert!(proof
+                    .verify_count_committed(*leaf, t.root())
+                    .unwrap()
+                    .failed());
+
+                // Claiming a different leaf count fails, as long as the proof is consistent with it
+                let other_leaf_count = if leaf_count.is_power_of_two() {
+                    leaf_count
+                } else {
+                    leaf_count + 1
+                };
+                let other = CheckedSingleProofHashes::from_proof(
+                    proof.clone().into_unchecked(),
+                    other_leaf_count,
+                )
+                .unwrap();
+                assert_eq!(
+                    other
+                        .verify_count_committed(*leaf, committed_root)
+                        .unwrap()
+                        .passed_decisively(),
+                    other_leaf_count == leaf_count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn last_leaf_of_padded_tree() {
+        let leaves = gen_leaves(5);
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+        let committed_root = t.count_committed_root();
+
+        let last = CheckedSingleProofHashes::from_tree_leaf(&t, 4).unwrap();
+        assert!(last.is_last_leaf());
+        assert!(last
+            .verify_count_committed(leaves[4], committed_root)
+            .unwrap()
+            .passed_decisively());
+
+        // The same proof bound to the padded leaf count verifies against the plain root, but
+        // not against the count-committed root, so the leaf can't be claimed to be the last one
+        let padded = CheckedSingleProofHashes::from_proof(last.into_unchecked(), 8).unwrap();
+        assert!(!padded.is_last_leaf());
+        assert!(padded
+            .verify(leaves[4], t.root())
+            .unwrap()
+            .passed_decisively());
+        assert!(padded
+            .verify_count_committed(leaves[4], committed_root)
+            .unwrap()
+            .failed());
+
+        // A padding leaf can't be the last leaf of a count-committed tree
+        let padding_leaf = t.node_value_from_bottom(0, 7).unwrap();
+        let padding_proof = SingleProofNodes::from_tree_leaf_including_padding(&t, 7)
+            .unwrap()
+            .into_values();
+        let padding_proof = CheckedSingleProofHashes::from_proof(padding_proof, 8).unwrap();
+        assert!(padding_proof.is_last_leaf());
+        assert!(padding_proof
+            .verify_count_committed(padding_leaf, committed_root)
+            .unwrap()
+            .failed());
+    }
+
+    #[test]
+    fn inconsistent_leaf_count() {
+        let leaves = gen_leaves(8);
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+        let proof = SingleProofNodes::from_tree_leaf(&t, 0)
+            .unwrap()
+            .into_values();
+
+        assert_eq!(
+            CheckedSingleProofHashes::from_proof(proof.clone(), 0).unwrap_err(),
+            MerkleProofVerificationError::InvalidTreeLeavesCount(0)
+        );
+        assert_eq!(
+            CheckedSingleProofHashes::from_proof(proof.clone(), 4).unwrap_err(),
+            MerkleProofVerificationError::BranchLengthMismatch(2, 3)
+        );
+        assert_eq!(
+            CheckedSingleProofHashes::from_proof(proof.clone(), 9).unwrap_err(),
+            MerkleProofVerificationError::BranchLengthMismatch(4, 3)
+        );
+        assert_eq!(
+            CheckedSingleProofHashes::from_proof(proof, u32::MAX).unwrap_err(),
+            MerkleProofVerificationError::InvalidTreeLeavesCount(u32::MAX)
+        );
+    }
+}
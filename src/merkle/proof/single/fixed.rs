@@ -0,0 +1,197 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::merkle::{
+    hasher::PairHasher, proof::verify_result::ProofVerifyResult, MerkleProofConversionError,
+};
+
+use super::{compute_branch_root, proof_ref::SingleProofRef, verify_branch, SingleProofHashes};
+
+/// Same as `SingleProofHashes`, but the branch is stored in an array, whose length is the depth of the tree
+/// (i.e., the number of levels minus one). Given that the depth is known at compile time, this struct can live
+/// on the stack and be verified without any allocations, which is useful for embedded or on-chain verification.
+#[must_use]
+#[cfg_attr(
+    feature = "scale-codec",
+    derive(parity_scale_codec::Encode, parity_scale_codec::Decode)
+)]
+pub struct FixedSingleProof<T, H, const DEPTH: usize> {
+    leaf_index_in_level: u32,
+    branch: [T; DEPTH],
+    _hasher: core::marker::PhantomData<H>,
+}
+
+impl<T: Debug, H, const DEPTH: usize> Debug for FixedSingleProof<T, H, DEPTH> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FixedSingleProof")
+            .field("leaf_index_in_level", &self.leaf_index_in_level)
+            .field("branch", &self.branch)
+            .finish()
+    }
+}
+
+impl<T: Clone, H, const DEPTH: usize> Clone for FixedSingleProof<T, H, DEPTH> {
+    fn clone(&self) -> Self {
+        Self::from_leaf_index_and_branch(self.leaf_index_in_level, self.branch.clone())
+    }
+}
+
+impl<T: PartialEq, H, const DEPTH: usize> PartialEq for FixedSingleProof<T, H, DEPTH> {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf_index_in_level == other.leaf_index_in_level && self.branch == other.branch
+    }
+}
+
+impl<T: Eq, H, const DEPTH: usize> Eq for FixedSingleProof<T, H, DEPTH> {}
+
+impl<T, H, const DEPTH: usize> FixedSingleProof<T, H, DEPTH> {
+    pub fn from_leaf_index_and_branch(leaf_index: u32, branch_nodes: [T; DEPTH]) -> Self {
+        Self {
+            leaf_index_in_level: leaf_index,
+            branch: branch_nodes,
+            _hasher: core::marker::PhantomData,
+        }
+    }
+
+    pub fn into_hashes(self) -> [T; DEPTH] {
+        self.branch
+    }
+
+    pub fn branch(&self) -> &[T; DEPTH] {
+        &self.branch
+    }
+
+    pub fn leaf_index_in_level(&self) -> u32 {
+        self.leaf_index_in_level
+    }
+
+    /// Borrow this proof as a `SingleProofRef`.
+    pub fn as_proof_ref(&self) -> SingleProofRef<'_, T, H> {
+        SingleProofRef::from_leaf_index_and_branch(self.leaf_index_in_level, &self.branch)
+    }
+}
+
+impl<T: Eq, H: PairHasher<NodeType = T>, const DEPTH: usize> FixedSingleProof<T, H, DEPTH> {
+    /// Verifies that the given leaf can produce the root's hash. This doesn't allocate.
+    pub fn verify(&self, leaf: T, root: T) -> ProofVerifyResult {
+        verify_branch::<T, H>(self.leaf_index_in_level.into(), &self.branch, leaf, root)
+    }
+
+    /// Computes the root that the given leaf produces with this proof. This doesn't allocate.
+    pub fn compute_root(&self, leaf: T) -> T {
+        compute_branch_root::<T, H>(self.leaf_index_in_level.into(), &self.branch, leaf)
+    }
+}
+
+impl<T: Eq, H: PairHasher<NodeType = T>, const DEPTH: usize> TryFrom<SingleProofHashes<T, H>>
+    for FixedSingleProof<T, H, DEPTH>
+{
+    type Error = MerkleProofConversionError;
+
+    fn try_from(proof: SingleProofHashes<T, H>) -> Result<Self, Self::Error> {
+        if proof.level_from_bottom() != 0 {
+            return Err(MerkleProofConversionError::NotLeafLevel(
+                proof.level_from_bottom(),
+            ));
+        }
+
+        let leaf_index = proof.leaf_index_in_level();
+        let branch: [T; DEPTH] = proof.into_hashes().try_into().map_err(|v: Vec<T>| {
+            MerkleProofConversionError::BranchLengthMismatch(DEPTH, v.len())
+        })?;
+
+        Ok(Self::from_leaf_index_and_branch(leaf_index, branch))
+    }
+}
+
+impl<T: Eq, H: PairHasher<NodeType = T>, const DEPTH: usize> From<FixedSingleProof<T, H, DEPTH>>
+    for SingleProofHashes<T, H>
+{
+    fn from(proof: FixedSingleProof<T, H, DEPTH>) -> Self {
+        let leaf_index = proof.leaf_index_in_level();
+        SingleProofHashes::from_leaf_index_and_branch(leaf_index, proof.into_hashes().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        internal::{hash_data, HashAlgo, HashedData},
+        proof::single::SingleProofNodes,
+        tree::MerkleTree,
+    };
+
+    use super::*;
+
+    fn gen_tree(leaf_count: u32) -> MerkleTree<HashedData, HashAlgo> {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i as u64)))
+            .collect::<Vec<_>>();
+        MerkleTree::from_leaves(leaves).unwrap()
+    }
+
+    fn check_conversion_and_verification<const DEPTH: usize>(leaf_count: u32) {
+        let t = gen_tree(leaf_count);
+
+        for leaf_index in 0..leaf_count {
+            let leaf = t.node_value_from_bottom(0, leaf_index).unwrap();
+            let proof = SingleProofNodes::from_tree_leaf(&t, leaf_index)
+                .unwrap()
+                .into_values();
+
+            let fixed = FixedSingleProof::<_, _, DEPTH>::try_from(proof.clone()).unwrap();
+            assert_eq!(fixed.leaf_index_in_level(), leaf_index);
+            assert_eq!(fixed.branch().as_slice(), proof.branch());
+            assert_eq!(fixed.verify(leaf, t.root()), proof.verify(leaf, t.root()));
+            assert_eq!(fixed.as_proof_ref(), proof.as_proof_ref());
+            assert!(fixed.verify(hash_data(leaf), t.root()).failed());
+
+            // Back to the owned form
+            let owned = SingleProofHashes::from(fixed);
+            assert_eq!(owned.leaf_index_in_level(), proof.leaf_index_in_level());
+            assert_eq!(owned.branch(), proof.branch());
+        }
+    }
+
+    #[test]
+    fn conversion_and_verification() {
+        check_conversion_and_verification::<0>(1);
+        check_conversion_and_verification::<1>(2);
+        check_conversion_and_verification::<2>(4);
+        check_conversion_and_verification::<3>(8);
+        check_conversion_and_verification::<4>(16);
+        check_conversion_and_verification::<5>(32);
+    }
+
+    #[test]
+    fn conversion_with_wrong_depth() {
+        let t = gen_tree(8);
+        let proof = SingleProofNodes::from_tree_leaf(&t, 3)
+            .unwrap()
+            .into_values();
+
+        assert_eq!(
+            FixedSingleProof::<_, _, 2>::try_from(proof.clone()).unwrap_err(),
+            MerkleProofConversionError::BranchLengthMismatch(2, 3)
+        );
+        assert_eq!(
+            FixedSingleProof::<_, _, 4>::try_from(proof).unwrap_err(),
+            MerkleProofConversionError::BranchLengthMismatch(4, 3)
+        );
+    }
+}
@@ -13,13 +13,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::merkle::hasher::PairHasher;
+pub mod checked;
+pub mod fixed;
+pub mod proof64;
+pub mod proof_ref;
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::merkle::{
+    hasher::PairHasher,
+    pos::{node_kind::NodeKind, NodePosition},
+    tree::{store::NodeStore, tree_size::TreeSize},
+    MerkleProofVerificationError,
+};
+
+use self::proof_ref::SingleProofRef;
 
 use super::{
     super::{
         tree::{MerkleTree, Node},
         MerkleTreeProofExtractionError,
     },
+    diagnostics::{diagnose_path, ExpectedNodes, NodeSource, SingleProofDiagnostics},
     verify_result::ProofVerifyResult,
 };
 
@@ -27,13 +43,29 @@ use super::{
 /// This is considered an intermediary object. For storage, use `SingleProofHashes` through
 /// `SingleProofNodes::into_values()`.
 #[must_use]
-#[derive(Debug, PartialEq, Eq)]
-pub struct SingleProofNodes<'a, T, H> {
-    leaf: Node<'a, T, H>,
-    branch: Vec<Node<'a, T, H>>,
+pub struct SingleProofNodes<'a, T, H, S = Vec<T>> {
+    leaf: Node<'a, T, H, S>,
+    branch: Vec<Node<'a, T, H, S>>,
+}
+
+impl<T: Debug, H, S: NodeStore<T>> Debug for SingleProofNodes<'_, T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SingleProofNodes")
+            .field("leaf", &self.leaf)
+            .field("branch", &self.branch)
+            .finish()
+    }
+}
+
+impl<T: PartialEq, H, S: NodeStore<T>> PartialEq for SingleProofNodes<'_, T, H, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf == other.leaf && self.branch == other.branch
+    }
 }
 
-impl<T, H> Clone for SingleProofNodes<'_, T, H> {
+impl<T: Eq, H, S: NodeStore<T>> Eq for SingleProofNodes<'_, T, H, S> {}
+
+impl<T, H, S> Clone for SingleProofNodes<'_, T, H, S> {
     fn clone(&self) -> Self {
         Self {
             leaf: self.leaf,
@@ -42,25 +74,41 @@ impl<T, H> Clone for SingleProofNodes<'_, T, H> {
     }
 }
 
-impl<'a, T: Clone, H: PairHasher<NodeType = T>> SingleProofNodes<'a, T, H> {
-    pub fn into_nodes(self) -> Vec<Node<'a, T, H>> {
+impl<'a, T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> SingleProofNodes<'a, T, H, S> {
+    pub fn into_nodes(self) -> Vec<Node<'a, T, H, S>> {
         self.branch
     }
 
-    pub fn branch(&self) -> &[Node<'a, T, H>] {
+    pub fn branch(&self) -> &[Node<'a, T, H, S>] {
         &self.branch
     }
 
-    pub fn leaf(&self) -> Node<'a, T, H> {
+    pub fn leaf(&self) -> Node<'a, T, H, S> {
         self.leaf
     }
 }
 
-impl<'a, T: Clone, H: PairHasher<NodeType = T>> SingleProofNodes<'a, T, H> {
+impl<'a, T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> SingleProofNodes<'a, T, H, S> {
     /// Creates a proof for a leaf by its index in the lowest level (the tip).
     /// A proof doesn't contain the root.
+    /// Padding leaves are refused, since a proof for them could be mistaken for a proof of a real leaf.
     pub fn from_tree_leaf(
-        tree: &'a MerkleTree<T, H>,
+        tree: &'a MerkleTree<T, H, S>,
+        leaf_index: u32,
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        if tree.is_padding_leaf(leaf_index) {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf(
+                leaf_index,
+                tree.real_leaf_count().get(),
+            ));
+        }
+
+        Self::from_tree_leaf_including_padding(tree, leaf_index)
+    }
+
+    /// Same as `from_tree_leaf`, but allows creating proofs for padding leaves.
+    pub fn from_tree_leaf_including_padding(
+        tree: &'a MerkleTree<T, H, S>,
         leaf_index: u32,
     ) -> Result<Self, MerkleTreeProofExtractionError> {
         let leaf_count = tree.leaf_count().get();
@@ -79,23 +127,60 @@ impl<'a, T: Clone, H: PairHasher<NodeType = T>> SingleProofNodes<'a, T, H> {
             ),
         )?;
 
-        let proof: Vec<_> = leaf
+        Ok(Self::from_node(leaf, 0, tree.level_count().get()))
+    }
+
+    /// Same as `from_tree_leaf`, but for a node at any level, i.e., the root of a whole subtree, such as the root
+    /// of a chunk of leaves. The position must be in a tree of the same size. Nodes whose leaves are all padding
+    /// are refused, like padding leaves. A proof of a node at level 0 is the same as a proof of the leaf.
+    pub fn from_tree_node(
+        tree: &'a MerkleTree<T, H, S>,
+        position: NodePosition,
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        let tree_size = tree.total_node_count();
+        if position.tree_size() != tree_size {
+            return Err(MerkleTreeProofExtractionError::TreeSizeMismatch(
+                position.tree_size().get(),
+                tree_size.get(),
+            ));
+        }
+
+        let first_leaf_index = position.leaf_range().start;
+        if tree.is_padding_leaf(first_leaf_index) {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf(
+                first_leaf_index,
+                tree.real_leaf_count().get(),
+            ));
+        }
+
+        let (level_from_bottom, index_in_level) = position.position();
+        let node = tree
+            .node_from_bottom(level_from_bottom, index_in_level)
+            .expect("The position is in the tree");
+
+        Ok(Self::from_node(
+            node,
+            level_from_bottom,
+            tree.level_count().get(),
+        ))
+    }
+
+    fn from_node(node: Node<'a, T, H, S>, level_from_bottom: u32, level_count: u32) -> Self {
+        let proof: Vec<_> = node
             .into_iter_parents()
             .map_while(|n| n.sibling())
             .collect();
 
         assert_eq!(
             proof.len() as u32,
-            tree.level_count().get()  - 1,
+            level_count - 1 - level_from_bottom,
             "This happens only if the we fail to find a sibling, which is only for root. In the loop, this cannot happen, so siblings must exist"
         );
 
-        let result = Self {
-            leaf,
+        Self {
+            leaf: node,
             branch: proof,
-        };
-
-        Ok(result)
+        }
     }
 
     pub fn into_values(self) -> SingleProofHashes<T, H> {
@@ -104,16 +189,18 @@ impl<'a, T: Clone, H: PairHasher<NodeType = T>> SingleProofNodes<'a, T, H> {
             .into_iter()
             .map(|node| node.hash().clone())
             .collect::<Vec<_>>();
-        let leaf_abs_index = self.leaf.into_position().position().1;
+        let (level_from_bottom, index_in_level) = self.leaf.into_position().position();
         SingleProofHashes {
-            leaf_index_in_level: leaf_abs_index,
+            level_from_bottom,
+            leaf_index_in_level: index_in_level,
             branch: proof,
-            _hasher: std::marker::PhantomData,
+            _hasher: core::marker::PhantomData,
         }
     }
 }
 
-/// Same as `SingleProofNodes`, but has only hashes and leaf index in the lowest level.
+/// Same as `SingleProofNodes`, but has only hashes, and the level and index of the proven node in its level,
+/// which is a leaf, unless the proof was created with `SingleProofNodes::from_tree_node` for a higher level.
 /// This is the minimum information required to prove that the given leaf can produce the root's hash.
 /// This struct is supposed to be serialized, unlike `SingleProofNodes`.
 #[must_use]
@@ -123,9 +210,12 @@ impl<'a, T: Clone, H: PairHasher<NodeType = T>> SingleProofNodes<'a, T, H> {
     derive(parity_scale_codec::Encode, parity_scale_codec::Decode)
 )]
 pub struct SingleProofHashes<T, H> {
+    /// The level of the proven node, so that the branch starts at the right height; 0 for leaves
+    level_from_bottom: u32,
+    /// The index of the proven node in its level
     leaf_index_in_level: u32,
     branch: Vec<T>,
-    _hasher: std::marker::PhantomData<H>,
+    _hasher: core::marker::PhantomData<H>,
 }
 
 impl<T: Eq, H: PairHasher<NodeType = T>> SingleProofHashes<T, H> {
@@ -141,43 +231,182 @@ impl<T: Eq, H: PairHasher<NodeType = T>> SingleProofHashes<T, H> {
         self.leaf_index_in_level
     }
 
+    pub fn level_from_bottom(&self) -> u32 {
+        self.level_from_bottom
+    }
+
     pub fn from_leaf_index_and_branch(leaf_index: u32, branch_nodes: Vec<T>) -> Self {
+        Self::from_position_and_branch(0, leaf_index, branch_nodes)
+    }
+
+    /// Same as `from_leaf_index_and_branch`, for a proof of a node at any level.
+    pub fn from_position_and_branch(
+        level_from_bottom: u32,
+        index_in_level: u32,
+        branch_nodes: Vec<T>,
+    ) -> Self {
         Self {
-            leaf_index_in_level: leaf_index,
+            level_from_bottom,
+            leaf_index_in_level: index_in_level,
             branch: branch_nodes,
-            _hasher: std::marker::PhantomData,
+            _hasher: core::marker::PhantomData,
         }
     }
+
+    /// Borrow this proof as a `SingleProofRef`, which doesn't own the branch.
+    /// The level isn't kept, as it doesn't change the verification.
+    pub fn as_proof_ref(&self) -> SingleProofRef<'_, T, H> {
+        SingleProofRef::from_leaf_index_and_branch(self.leaf_index_in_level, &self.branch)
+    }
 }
 
 impl<T: Eq, H: PairHasher<NodeType = T>> SingleProofHashes<T, H> {
-    /// Verifies that the given leaf can produce the root's hash.
+    /// Verifies that the given leaf, or the node at the level of the proof, can produce the root's hash.
     pub fn verify(&self, leaf: T, root: T) -> ProofVerifyResult {
-        // in case it's a single-node tree, we don't need to verify or hash anything
-        if self.branch.is_empty() {
-            return match leaf == root {
-                true => ProofVerifyResult::PassedTrivially,
-                false => ProofVerifyResult::Failed,
-            };
-        }
+        verify_branch::<T, H>(self.leaf_index_in_level.into(), &self.branch, leaf, root)
+    }
+
+    /// Computes the root that the given leaf produces with this proof.
+    pub fn compute_root(&self, leaf: T) -> T {
+        compute_branch_root::<T, H>(self.leaf_index_in_level.into(), &self.branch, leaf)
+    }
+}
+
+impl<T: Clone, H: PairHasher<NodeType = T>> SingleProofHashes<T, H> {
+    /// Computes all the nodes on the path from the given leaf to the root (excluding the leaf),
+    /// as pairs of absolute index and hash, in ascending order of index. The last node is the root.
+    pub fn compute_nodes(&self, leaf: T) -> Result<Vec<(u32, T)>, MerkleProofVerificationError> {
+        let leaf_position = self.node_position()?;
 
-        let hash = self
+        let mut hash = leaf;
+        let nodes = self
             .branch
             .iter()
-            .enumerate()
-            .fold(leaf, |prev_hash, (index, sibling)| {
-                let node_in_level_index = self.leaf_index_in_level >> index;
-                if node_in_level_index % 2 == 0 {
-                    H::hash_pair(&prev_hash, sibling)
-                } else {
-                    H::hash_pair(sibling, &prev_hash)
-                }
-            });
-
-        match hash == root {
-            true => ProofVerifyResult::PassedDecisively,
+            .zip(leaf_position.into_iter_parents())
+            .map(|(sibling, node)| {
+                hash = match node.node_kind() {
+                    NodeKind::LeftChild => H::hash_pair(&hash, sibling),
+                    NodeKind::RightChild => H::hash_pair(sibling, &hash),
+                    NodeKind::Root => unreachable!("The branch never reaches the root"),
+                };
+                let parent = node.parent().expect("Not root, as per the branch length");
+                (parent.abs_index(), hash.clone())
+            })
+            .collect();
+
+        Ok(nodes)
+    }
+
+    /// Same as `verify`, with the same result, but every node on the path from the leaf to the root is compared
+    /// with the expected nodes (e.g., a reference tree), to find the first one that diverged. Like in `verify`,
+    /// the bits of the leaf index beyond the length of the branch are ignored. Branches that are too long
+    /// for a supported tree are an error, as the path can't be compared with a tree.
+    pub fn verify_with_diagnostics<E: ExpectedNodes<T>>(
+        &self,
+        leaf: T,
+        root: T,
+        expected: &E,
+    ) -> Result<SingleProofDiagnostics<T>, MerkleProofVerificationError>
+    where
+        T: Eq,
+    {
+        let tree_size = self.checked_tree_size()?;
+        let level_size = tree_size.leaf_count().get() >> self.level_from_bottom;
+        let leaf_index = self.leaf_index_in_level & (level_size - 1);
+        let leaf_position =
+            NodePosition::from_position(tree_size, self.level_from_bottom, leaf_index)
+                .expect("The index is masked to the level size");
+
+        let mut branch = self.branch.iter();
+        let (_, mismatch) =
+            diagnose_path::<T, H, E>(leaf_position, leaf.clone(), expected, |_| {
+                let sibling = branch
+                    .next()
+                    .expect("The branch length matches the tree size");
+                Ok((sibling.clone(), NodeSource::Proof))
+            })?;
+
+        let result =
+            verify_branch::<T, H>(self.leaf_index_in_level.into(), &self.branch, leaf, root);
+
+        Ok(SingleProofDiagnostics::new(result, mismatch))
+    }
+
+    /// The size of the tree, which is implied by the level and the length of the branch.
+    fn checked_tree_size(&self) -> Result<TreeSize, MerkleProofVerificationError> {
+        u32::try_from(self.branch.len())
+            .ok()
+            .and_then(|length| length.checked_add(self.level_from_bottom))
+            .and_then(|level_count| 1u32.checked_shl(level_count))
+            .and_then(|leaf_count| TreeSize::from_leaf_count(leaf_count).ok())
+            .ok_or(MerkleProofVerificationError::BranchTooLong(
+                self.branch.len(),
+            ))
+    }
+
+    /// The position of the proven node in the tree, whose size is implied by the level and the length of the branch.
+    pub fn node_position(&self) -> Result<NodePosition, MerkleProofVerificationError> {
+        let tree_size = self.checked_tree_size()?;
+
+        NodePosition::from_position(tree_size, self.level_from_bottom, self.leaf_index_in_level)
+            .ok_or_else(|| match self.level_from_bottom {
+                0 => MerkleProofVerificationError::LeavesIndicesOutOfRange(
+                    [self.leaf_index_in_level].into(),
+                    tree_size.leaf_count().get(),
+                ),
+                level => MerkleProofVerificationError::NodeIndexOutOfRange(
+                    self.leaf_index_in_level,
+                    tree_size.leaf_count().get() >> level,
+                ),
+            })
+    }
+}
+
+/// Hashes the leaf with the nodes of the branch, from the bottom to the top, and returns the result.
+/// The index is 64-bit, to be shared by all index widths. The bits of the index beyond the length
+/// of the branch are ignored, and a branch longer than the index bits is treated as having zero bits there.
+fn compute_branch_root<T, H: PairHasher<NodeType = T>>(
+    leaf_index_in_level: u64,
+    branch: &[T],
+    leaf: T,
+) -> T {
+    branch
+        .iter()
+        .enumerate()
+        .fold(leaf, |prev_hash, (index, sibling)| {
+            let node_in_level_index = u32::try_from(index)
+                .ok()
+                .and_then(|index| leaf_index_in_level.checked_shr(index))
+                .unwrap_or(0);
+            if node_in_level_index % 2 == 0 {
+                H::hash_pair(&prev_hash, sibling)
+            } else {
+                H::hash_pair(sibling, &prev_hash)
+            }
+        })
+}
+
+/// Hashes the leaf with the nodes of the branch, from the bottom to the top, and compares the result with the root.
+/// This is the common verification logic for all single proof representations, and it doesn't allocate.
+fn verify_branch<T: Eq, H: PairHasher<NodeType = T>>(
+    leaf_index_in_level: u64,
+    branch: &[T],
+    leaf: T,
+    root: T,
+) -> ProofVerifyResult {
+    // in case it's a single-node tree, we don't need to verify or hash anything
+    if branch.is_empty() {
+        return match leaf == root {
+            true => ProofVerifyResult::PassedTrivially,
             false => ProofVerifyResult::Failed,
-        }
+        };
+    }
+
+    let hash = compute_branch_root::<T, H>(leaf_index_in_level, branch, leaf);
+
+    match hash == root {
+        true => ProofVerifyResult::PassedDecisively,
+        false => ProofVerifyResult::Failed,
     }
 }
 
@@ -0,0 +1,237 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::merkle::{
+    hasher::PairHasher,
+    pos::position64::NodePosition64,
+    proof::verify_result::ProofVerifyResult,
+    tree::{store::NodeStore, tree64::MerkleTree64},
+    MerkleTreeProofExtractionError,
+};
+
+use super::{compute_branch_root, verify_branch, SingleProofHashes};
+
+/// Same as `SingleProofHashes`, but with a 64-bit leaf index, for trees that have more leaves than
+/// `SingleProofHashes` supports. Proofs of trees that fit in both verify the same.
+#[must_use]
+#[cfg_attr(
+    feature = "scale-codec",
+    derive(parity_scale_codec::Encode, parity_scale_codec::Decode)
+)]
+pub struct SingleProofHashes64<T, H> {
+    leaf_index_in_level: u64,
+    branch: Vec<T>,
+    _hasher: core::marker::PhantomData<H>,
+}
+
+impl<T: Debug, H> Debug for SingleProofHashes64<T, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SingleProofHashes64")
+            .field("leaf_index_in_level", &self.leaf_index_in_level)
+            .field("branch", &self.branch)
+            .finish()
+    }
+}
+
+impl<T: Clone, H> Clone for SingleProofHashes64<T, H> {
+    fn clone(&self) -> Self {
+        Self::from_leaf_index_and_branch(self.leaf_index_in_level, self.branch.clone())
+    }
+}
+
+impl<T: PartialEq, H> PartialEq for SingleProofHashes64<T, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf_index_in_level == other.leaf_index_in_level && self.branch == other.branch
+    }
+}
+
+impl<T: Eq, H> Eq for SingleProofHashes64<T, H> {}
+
+impl<T, H> SingleProofHashes64<T, H> {
+    pub fn into_hashes(self) -> Vec<T> {
+        self.branch
+    }
+
+    pub fn branch(&self) -> &[T] {
+        &self.branch
+    }
+
+    pub fn leaf_index_in_level(&self) -> u64 {
+        self.leaf_index_in_level
+    }
+
+    pub fn from_leaf_index_and_branch(leaf_index: u64, branch_nodes: Vec<T>) -> Self {
+        Self {
+            leaf_index_in_level: leaf_index,
+            branch: branch_nodes,
+            _hasher: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Clone, H> SingleProofHashes64<T, H> {
+    /// Creates a proof for a leaf of a `MerkleTree64` by its index in the lowest level (the tip).
+    /// Padding leaves are refused, like in `SingleProofNodes::from_tree_leaf`.
+    pub fn from_tree_leaf<S: NodeStore<T>>(
+        tree: &MerkleTree64<T, H, S>,
+        leaf_index: u64,
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        if tree.is_padding_leaf(leaf_index) {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf64(
+                leaf_index,
+                tree.real_leaf_count().get(),
+            ));
+        }
+
+        Self::from_tree_leaf_including_padding(tree, leaf_index)
+    }
+
+    /// Same as `from_tree_leaf`, but allows creating proofs for padding leaves.
+    pub fn from_tree_leaf_including_padding<S: NodeStore<T>>(
+        tree: &MerkleTree64<T, H, S>,
+        leaf_index: u64,
+    ) -> Result<Self, MerkleTreeProofExtractionError> {
+        let leaf_count = tree.leaf_count().get();
+        let leaf = NodePosition64::from_position(tree.total_node_count(), 0, leaf_index).ok_or(
+            MerkleTreeProofExtractionError::LeafIndexOutOfRange64(leaf_index, leaf_count),
+        )?;
+
+        let branch = leaf
+            .into_iter_parents()
+            .map_while(|n| n.sibling())
+            .map(|sibling| {
+                tree.node(sibling.abs_index())
+                    .cloned()
+                    .expect("Sibling of a node in the tree is in the tree")
+            })
+            .collect();
+
+        Ok(Self::from_leaf_index_and_branch(leaf_index, branch))
+    }
+}
+
+impl<T: Eq, H: PairHasher<NodeType = T>> SingleProofHashes64<T, H> {
+    /// Verifies that the given leaf can produce the root's hash.
+    pub fn verify(&self, leaf: T, root: T) -> ProofVerifyResult {
+        verify_branch::<T, H>(self.leaf_index_in_level, &self.branch, leaf, root)
+    }
+
+    /// Computes the root that the given leaf produces with this proof.
+    pub fn compute_root(&self, leaf: T) -> T {
+        compute_branch_root::<T, H>(self.leaf_index_in_level, &self.branch, leaf)
+    }
+}
+
+impl<T, H> TryFrom<SingleProofHashes<T, H>> for SingleProofHashes64<T, H> {
+    type Error = SingleProofHashes<T, H>;
+
+    /// Fails, returning the proof back, if it's not a proof of a leaf.
+    fn try_from(proof: SingleProofHashes<T, H>) -> Result<Self, Self::Error> {
+        if proof.level_from_bottom != 0 {
+            return Err(proof);
+        }
+
+        Ok(Self::from_leaf_index_and_branch(
+            proof.leaf_index_in_level.into(),
+            proof.branch,
+        ))
+    }
+}
+
+impl<T, H> TryFrom<SingleProofHashes64<T, H>> for SingleProofHashes<T, H> {
+    type Error = SingleProofHashes64<T, H>;
+
+    /// Fails, returning the proof back, if the leaf index doesn't fit in 32 bits.
+    fn try_from(proof: SingleProofHashes64<T, H>) -> Result<Self, Self::Error> {
+        match u32::try_from(proof.leaf_index_in_level) {
+            Ok(leaf_index_in_level) => Ok(SingleProofHashes {
+                level_from_bottom: 0,
+                leaf_index_in_level,
+                branch: proof.branch,
+                _hasher: core::marker::PhantomData,
+            }),
+            Err(_) => Err(proof),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        internal::{hash_data, HashAlgo, HashedData},
+        proof::single::SingleProofNodes,
+        tree::MerkleTree,
+    };
+
+    use super::*;
+
+    #[test]
+    fn same_as_32_bit_proof() {
+        for leaf_count in 1..=17u64 {
+            let leaves = (0..leaf_count)
+                .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+                .collect::<Vec<_>>();
+            let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+            for (leaf_index, leaf) in leaves.iter().enumerate() {
+                let proof = SingleProofNodes::from_tree_leaf(&t, leaf_index as u32)
+                    .unwrap()
+                    .into_values();
+                let proof64 = SingleProofHashes64::try_from(proof.clone()).ok().unwrap();
+                assert_eq!(proof64.leaf_index_in_level(), leaf_index as u64);
+                assert_eq!(proof64.branch(), proof.branch());
+                assert_eq!(
+                    proof64.verify(*leaf, t.root()),
+                    proof.verify(*leaf, t.root())
+                );
+                assert_eq!(proof64.compute_root(*leaf), t.root());
+                let narrowed = SingleProofHashes::try_from(proof64).ok().unwrap();
+                assert_eq!(narrowed.leaf_index_in_level(), proof.leaf_index_in_level());
+                assert_eq!(narrowed.branch(), proof.branch());
+            }
+        }
+    }
+
+    #[test]
+    fn beyond_32_bit_index() {
+        // A proof of the last leaf in a tree of 2^40 leaves, where all leaves are the same,
+        // so each level of the tree has a single distinct node
+        let levels = 40;
+        let leaf = hash_data(HashedData::zero());
+        let branch = (0..levels)
+            .scan(leaf, |node, _| {
+                let sibling = *node;
+                *node = HashAlgo::hash_pair(node, node);
+                Some(sibling)
+            })
+            .collect::<Vec<_>>();
+        let root = (0..levels).fold(leaf, |node, _| HashAlgo::hash_pair(&node, &node));
+
+        let leaf_index = (1u64 << levels) - 1;
+        let proof = SingleProofHashes64::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+            leaf_index, branch,
+        );
+        assert!(proof.verify(leaf, root).passed_decisively());
+        assert!(proof
+            .verify(hash_data(HashedData::from_low_u64_be(1)), root)
+            .failed());
+
+        let narrowed = SingleProofHashes::try_from(proof.clone()).err().unwrap();
+        assert_eq!(narrowed, proof);
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt::Debug;
+
+use crate::merkle::{hasher::PairHasher, proof::verify_result::ProofVerifyResult};
+
+use super::{compute_branch_root, fixed::FixedSingleProof, verify_branch, SingleProofHashes};
+
+/// Same as `SingleProofHashes`, but borrows the branch instead of owning it.
+/// This is useful for verifying proofs that live in a buffer (e.g., a decoded transaction),
+/// without having to allocate a vector for the branch.
+#[must_use]
+pub struct SingleProofRef<'a, T, H> {
+    leaf_index_in_level: u32,
+    branch: &'a [T],
+    _hasher: core::marker::PhantomData<H>,
+}
+
+impl<T: Debug, H> Debug for SingleProofRef<'_, T, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SingleProofRef")
+            .field("leaf_index_in_level", &self.leaf_index_in_level)
+            .field("branch", &self.branch)
+            .finish()
+    }
+}
+
+impl<T: PartialEq, H> PartialEq for SingleProofRef<'_, T, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf_index_in_level == other.leaf_index_in_level && self.branch == other.branch
+    }
+}
+
+impl<T: Eq, H> Eq for SingleProofRef<'_, T, H> {}
+
+impl<T, H> Clone for SingleProofRef<'_, T, H> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, H> Copy for SingleProofRef<'_, T, H> {}
+
+impl<'a, T, H> SingleProofRef<'a, T, H> {
+    pub fn from_leaf_index_and_branch(leaf_index: u32, branch_nodes: &'a [T]) -> Self {
+        Self {
+            leaf_index_in_level: leaf_index,
+            branch: branch_nodes,
+            _hasher: core::marker::PhantomData,
+        }
+    }
+
+    pub fn branch(&self) -> &'a [T] {
+        self.branch
+    }
+
+    pub fn leaf_index_in_level(&self) -> u32 {
+        self.leaf_index_in_level
+    }
+}
+
+impl<T: Eq, H: PairHasher<NodeType = T>> SingleProofRef<'_, T, H> {
+    /// Verifies that the given leaf can produce the root's hash. This doesn't allocate.
+    pub fn verify(&self, leaf: T, root: T) -> ProofVerifyResult {
+        verify_branch::<T, H>(self.leaf_index_in_level.into(), self.branch, leaf, root)
+    }
+
+    /// Computes the root that the given leaf produces with this proof. This doesn't allocate.
+    pub fn compute_root(&self, leaf: T) -> T {
+        compute_branch_root::<T, H>(self.leaf_index_in_level.into(), self.branch, leaf)
+    }
+}
+
+impl<'a, T: Eq, H: PairHasher<NodeType = T>> From<&'a SingleProofHashes<T, H>>
+    for SingleProofRef<'a, T, H>
+{
+    fn from(proof: &'a SingleProofHashes<T, H>) -> Self {
+        proof.as_proof_ref()
+    }
+}
+
+impl<'a, T, H, const DEPTH: usize> From<&'a FixedSingleProof<T, H, DEPTH>>
+    for SingleProofRef<'a, T, H>
+{
+    fn from(proof: &'a FixedSingleProof<T, H, DEPTH>) -> Self {
+        proof.as_proof_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        internal::{hash_data, HashAlgo, HashedData},
+        proof::single::SingleProofNodes,
+        tree::MerkleTree,
+    };
+
+    use super::*;
+
+    #[test]
+    fn verify_same_as_owned_proof() {
+        for leaf_count in 1..40u32 {
+            let leaves = (0..leaf_count)
+                .map(|i| hash_data(HashedData::from_low_u64_be(i as u64)))
+                .collect::<Vec<_>>();
+            let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+            for leaf_index in 0..t.leaf_count().get() {
+                let leaf = t.node_value_from_bottom(0, leaf_index).unwrap();
+                let proof = SingleProofNodes::from_tree_leaf_including_padding(&t, leaf_index)
+                    .unwrap()
+                    .into_values();
+                let proof_ref = SingleProofRef::from(&proof);
+
+                assert_eq!(proof_ref.leaf_index_in_level(), leaf_index);
+                assert_eq!(proof_ref.branch(), proof.branch());
+                assert_eq!(
+                    proof_ref.verify(leaf, t.root()),
+                    proof.verify(leaf, t.root())
+                );
+
+                // A wrong leaf must fail in both cases
+                let wrong_leaf = hash_data(leaf);
+                assert!(proof_ref.verify(wrong_leaf, t.root()).failed());
+                assert!(proof.verify(wrong_leaf, t.root()).failed());
+            }
+        }
+    }
+}
@@ -14,12 +14,27 @@
 // limitations under the License.
 
 use crate::rand_tools::{make_seedable_rng, Seed};
+use rand::Rng;
 use rstest::rstest;
 
+use std::collections::BTreeMap;
+
 use crate::{
+    hasher::PairHasher,
     internal::{hash_data, HashAlgo, HashedData},
-    proof::single::SingleProofNodes,
-    tree::MerkleTree,
+    pos::NodePosition,
+    proof::{
+        diagnostics::NodeSource,
+        multi::MultiProofHashes,
+        single::{
+            checked::CheckedSingleProofHashes, fixed::FixedSingleProof,
+            proof64::SingleProofHashes64, SingleProofHashes, SingleProofNodes,
+        },
+        verify_result::ProofVerifyResult,
+    },
+    tree::{tree_size::TreeSize, MerkleTree},
+    MerkleProofCombinationError, MerkleProofConversionError, MerkleProofVerificationError,
+    MerkleTreeProofExtractionError,
 };
 
 fn gen_leaves(n: u32) -> Vec<HashedData> {
@@ -161,3 +176,377 @@ fn single_proof_eight_leaves_tamper_with_leaf(#[case] seed: Seed, #[case] leaf_c
             .failed());
     }
 }
+
+#[rstest]
+#[case(1)]
+#[case(2)]
+#[case(5)]
+#[case(8)]
+#[case(16)]
+#[case(33)]
+fn single_proof_compute_root_and_nodes(#[case] leaf_count: u32) {
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    for (leaf_index, leaf) in leaves.iter().enumerate() {
+        let leaf_node = t.node_from_bottom(0, leaf_index as u32).unwrap();
+        let proof = SingleProofNodes::from_tree_leaf(&t, leaf_index as u32)
+            .unwrap()
+            .into_values();
+
+        assert_eq!(proof.compute_root(*leaf), t.root());
+        assert_eq!(proof.as_proof_ref().compute_root(*leaf), t.root());
+
+        let expected_nodes = leaf_node
+            .into_iter_parents()
+            .skip(1)
+            .map(|node| (node.abs_index(), *node.hash()))
+            .collect::<Vec<_>>();
+        assert_eq!(proof.compute_nodes(*leaf).unwrap(), expected_nodes);
+    }
+}
+
+#[test]
+fn single_proof_compute_nodes_errors() {
+    let leaves = gen_leaves(4);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+    let branch = SingleProofNodes::from_tree_leaf(&t, 0)
+        .unwrap()
+        .into_values()
+        .into_hashes();
+
+    let out_of_range =
+        SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(4, branch);
+    assert_eq!(
+        out_of_range.compute_nodes(leaves[0]).unwrap_err(),
+        MerkleProofVerificationError::LeavesIndicesOutOfRange(vec![4], 4)
+    );
+
+    let too_long = SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+        0,
+        vec![HashedData::zero(); 31],
+    );
+    assert_eq!(
+        too_long.compute_nodes(leaves[0]).unwrap_err(),
+        MerkleProofVerificationError::BranchTooLong(31)
+    );
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy(), 2)]
+#[trace]
+#[case(Seed::from_entropy(), 8)]
+#[trace]
+#[case(Seed::from_entropy(), 13)]
+#[trace]
+#[case(Seed::from_entropy(), 64)]
+fn single_proof_verify_with_diagnostics(#[case] seed: Seed, #[case] leaf_count: u32) {
+    let mut rng = make_seedable_rng(seed);
+
+    let leaves = gen_leaves(leaf_count);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+    let other_t = MerkleTree::<HashedData, HashAlgo>::from_leaves(gen_leaves(1)).unwrap();
+
+    for (leaf_index, leaf) in leaves.iter().enumerate() {
+        let leaf_node = t.node_from_bottom(0, leaf_index as u32).unwrap();
+        let proof = SingleProofNodes::from_tree_leaf(&t, leaf_index as u32)
+            .unwrap()
+            .into_values();
+
+        let diagnostics = proof.verify_with_diagnostics(*leaf, t.root(), &t).unwrap();
+        assert!(diagnostics.result().passed_decisively());
+        assert_eq!(diagnostics.mismatch(), None);
+
+        // A wrong root doesn't make any node of the path diverge
+        let diagnostics = proof
+            .verify_with_diagnostics(*leaf, HashedData::random_using(&mut rng), &t)
+            .unwrap();
+        assert!(diagnostics.result().failed());
+        assert_eq!(diagnostics.mismatch(), None);
+
+        // A wrong leaf diverges right away
+        let bad_leaf = HashedData::random_using(&mut rng);
+        let diagnostics = proof
+            .verify_with_diagnostics(bad_leaf, t.root(), &t)
+            .unwrap();
+        assert!(diagnostics.result().failed());
+        let mismatch = diagnostics.mismatch().unwrap();
+        assert_eq!(mismatch.level(), 0);
+        assert_eq!(mismatch.abs_index(), leaf_node.abs_index());
+        assert_eq!(mismatch.source(), NodeSource::Leaf);
+        assert_eq!(*mismatch.found(), bad_leaf);
+        assert_eq!(mismatch.expected(), leaf);
+
+        // A tree of a different size doesn't provide expected nodes
+        let diagnostics = proof
+            .verify_with_diagnostics(bad_leaf, t.root(), &other_t)
+            .unwrap();
+        assert_eq!(diagnostics.mismatch(), None);
+
+        let expected_nodes = proof
+            .compute_nodes(*leaf)
+            .unwrap()
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let siblings = leaf_node
+            .into_iter_parents()
+            .map_while(|node| node.sibling())
+            .collect::<Vec<_>>();
+
+        for level in 0..proof.branch().len() {
+            let mut branch = proof.branch().to_vec();
+            branch[level] = HashedData::random_using(&mut rng);
+            let tampered = SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+                leaf_index as u32,
+                branch,
+            );
+
+            // The reference tree has the siblings, so the tampered sibling is found
+            let diagnostics = tampered
+                .verify_with_diagnostics(*leaf, t.root(), &t)
+                .unwrap();
+            assert!(diagnostics.result().failed());
+            let mismatch = diagnostics.mismatch().unwrap();
+            assert_eq!(mismatch.level(), level as u32);
+            assert_eq!(mismatch.abs_index(), siblings[level].abs_index());
+            assert_eq!(mismatch.source(), NodeSource::Proof);
+
+            // Only the intermediate nodes are expected, so the divergence is found in the parent
+            let diagnostics = tampered
+                .verify_with_diagnostics(*leaf, t.root(), &expected_nodes)
+                .unwrap();
+            let mismatch = diagnostics.mismatch().unwrap();
+            assert_eq!(mismatch.level(), level as u32 + 1);
+            assert_eq!(mismatch.source(), NodeSource::Computed);
+        }
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn single_proof_diagnostics_result_same_as_verify(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for _ in 0..200 {
+        let leaf_count = rng.gen_range(1..=40);
+        let leaves = gen_leaves(leaf_count);
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+        let leaf_index = rng.gen_range(0..leaf_count);
+        let mut branch = SingleProofNodes::from_tree_leaf(&t, leaf_index)
+            .unwrap()
+            .into_values()
+            .into_hashes();
+        if !branch.is_empty() && rng.gen_bool(0.2) {
+            let level = rng.gen_range(0..branch.len());
+            branch[level] = HashedData::random_using(&mut rng);
+        }
+
+        // Indices beyond the leaves of the tree are tolerated by `verify`
+        let proof_leaf_index = match rng.gen_range(0..3) {
+            0 => leaf_index,
+            1 => leaf_index + (rng.gen_range(1..=4) << branch.len()),
+            _ => rng.gen(),
+        };
+        let proof = SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(
+            proof_leaf_index,
+            branch,
+        );
+        let leaf = match rng.gen_bool(0.8) {
+            true => leaves[leaf_index as usize],
+            false => HashedData::random_using(&mut rng),
+        };
+        let root = match rng.gen_bool(0.8) {
+            true => t.root(),
+            false => HashedData::random_using(&mut rng),
+        };
+
+        let diagnostics = proof.verify_with_diagnostics(leaf, root, &t).unwrap();
+        assert_eq!(diagnostics.result(), proof.verify(leaf, root));
+    }
+}
+
+#[test]
+fn single_proof_padding_leaves() {
+    let leaves = gen_leaves(5);
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+    for leaf_index in 5..8 {
+        assert_eq!(
+            SingleProofNodes::from_tree_leaf(&t, leaf_index)
+                .err()
+                .unwrap(),
+            MerkleTreeProofExtractionError::PaddingLeaf(leaf_index, 5)
+        );
+
+        let leaf = t.node_value_from_bottom(0, leaf_index).unwrap();
+        let proof = SingleProofNodes::from_tree_leaf_including_padding(&t, leaf_index).unwrap();
+        assert_eq!(proof.leaf().hash(), &leaf);
+        assert!(proof
+            .into_values()
+            .verify(leaf, t.root())
+            .passed_decisively());
+    }
+}
+
+#[test]
+fn single_proof_branch_longer_than_index_bits() {
+    // Branches longer than the bits of the index are treated as having zero bits beyond them
+    let leaf = hash_data(HashedData::zero());
+    let branch = (0..40u64)
+        .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+        .collect::<Vec<_>>();
+    let proof =
+        SingleProofHashes::<HashedData, HashAlgo>::from_leaf_index_and_branch(3, branch.clone());
+
+    let root = branch
+        .iter()
+        .enumerate()
+        .fold(leaf, |node, (level, sibling)| match level < 2 {
+            true => HashAlgo::hash_pair(sibling, &node),
+            false => HashAlgo::hash_pair(&node, sibling),
+        });
+    assert_eq!(proof.compute_root(leaf), root);
+    assert!(proof.verify(leaf, root).passed_decisively());
+}
+
+#[test]
+fn single_proof_of_every_node() {
+    for leaf_count in 1..=33 {
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(gen_leaves(leaf_count)).unwrap();
+        let tree_size = t.total_node_count();
+
+        for abs_index in 0..tree_size.get() {
+            let position = NodePosition::from_abs_index(tree_size, abs_index).unwrap();
+            let (level, index) = position.position();
+            let node = t.node_value_from_bottom(level, index).unwrap();
+
+            let proof = match SingleProofNodes::from_tree_node(&t, position) {
+                Ok(proof) => proof.into_values(),
+                Err(err) => {
+                    assert!(t.is_padding_leaf(position.leaf_range().start));
+                    assert_eq!(
+                        err,
+                        MerkleTreeProofExtractionError::PaddingLeaf(
+                            position.leaf_range().start,
+                            leaf_count
+                        )
+                    );
+                    continue;
+                }
+            };
+
+            assert_eq!(proof.level_from_bottom(), level);
+            assert_eq!(proof.leaf_index_in_level(), index);
+            assert_eq!(
+                proof.branch().len() as u32,
+                t.level_count().get() - 1 - level
+            );
+            assert_eq!(proof.node_position(), Ok(position));
+            assert_eq!(proof.compute_root(node), t.root());
+            let computed_nodes = proof.compute_nodes(node).unwrap();
+            assert_eq!(computed_nodes.len(), proof.branch().len());
+            if abs_index != tree_size.get() - 1 {
+                assert_eq!(
+                    computed_nodes.last(),
+                    Some(&(tree_size.get() - 1, t.root()))
+                );
+            }
+
+            let result = proof.verify(node, t.root());
+            assert!(
+                result.passed_decisively()
+                    || (result.passed_trivially() && proof.branch().is_empty())
+            );
+            assert_eq!(
+                proof.verify(HashedData::zero(), t.root()),
+                ProofVerifyResult::Failed
+            );
+            let diagnostics = proof.verify_with_diagnostics(node, t.root(), &t).unwrap();
+            assert_eq!(diagnostics.result(), result);
+            assert!(diagnostics.mismatch().is_none());
+
+            if level == 0 {
+                let leaf_proof = SingleProofNodes::from_tree_leaf(&t, index)
+                    .unwrap()
+                    .into_values();
+                assert_eq!(
+                    proof.leaf_index_in_level(),
+                    leaf_proof.leaf_index_in_level()
+                );
+                assert_eq!(proof.branch(), leaf_proof.branch());
+            } else {
+                // Proof types that are only for leaves refuse proofs of higher nodes
+                assert_eq!(
+                    CheckedSingleProofHashes::from_proof(proof.clone(), leaf_count).err(),
+                    Some(MerkleProofVerificationError::NotLeafLevel(level))
+                );
+                assert_eq!(
+                    FixedSingleProof::<_, _, 0>::try_from(proof.clone()).err(),
+                    Some(MerkleProofConversionError::NotLeafLevel(level))
+                );
+                assert_eq!(
+                    MultiProofHashes::from_single_proofs(core::slice::from_ref(&proof)).err(),
+                    Some(MerkleProofCombinationError::NotLeafLevel(level))
+                );
+                let refused = SingleProofHashes64::try_from(proof.clone()).err().unwrap();
+                assert_eq!(refused.level_from_bottom(), level);
+                assert_eq!(refused.branch(), proof.branch());
+            }
+        }
+    }
+}
+
+#[test]
+fn single_proof_of_chunk_root() {
+    // A chunk of 4 leaves, at level 2, in a tree of 16 leaves
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(gen_leaves(16)).unwrap();
+    let chunk_leaves = t.leaves()[8..12].to_vec();
+    let chunk = MerkleTree::<_, HashAlgo>::from_leaves(chunk_leaves).unwrap();
+
+    let position = NodePosition::from_position(t.total_node_count(), 2, 2).unwrap();
+    let proof = SingleProofNodes::from_tree_node(&t, position)
+        .unwrap()
+        .into_values();
+    assert!(proof.verify(chunk.root(), t.root()).passed_decisively());
+}
+
+#[test]
+fn single_proof_invalid_node_positions() {
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(gen_leaves(5)).unwrap();
+
+    let other_tree_position =
+        NodePosition::from_position(TreeSize::from_leaf_count(4).unwrap(), 1, 0).unwrap();
+    assert_eq!(
+        SingleProofNodes::from_tree_node(&t, other_tree_position).unwrap_err(),
+        MerkleTreeProofExtractionError::TreeSizeMismatch(7, 15)
+    );
+
+    // The right half of the second level is all padding, but the node above it has a real leaf
+    let padding_position = NodePosition::from_position(t.total_node_count(), 1, 3).unwrap();
+    assert_eq!(
+        SingleProofNodes::from_tree_node(&t, padding_position).unwrap_err(),
+        MerkleTreeProofExtractionError::PaddingLeaf(6, 5)
+    );
+    let position = NodePosition::from_position(t.total_node_count(), 2, 1).unwrap();
+    assert!(SingleProofNodes::from_tree_node(&t, position).is_ok());
+
+    let proof =
+        SingleProofHashes::<_, HashAlgo>::from_position_and_branch(2, 2, vec![HashedData::zero()]);
+    assert_eq!(
+        proof.node_position(),
+        Err(MerkleProofVerificationError::NodeIndexOutOfRange(2, 2))
+    );
+    assert_eq!(
+        proof.compute_nodes(HashedData::zero()),
+        Err(MerkleProofVerificationError::NodeIndexOutOfRange(2, 2))
+    );
+
+    let proof =
+        SingleProofHashes::<_, HashAlgo>::from_position_and_branch(31, 0, vec![HashedData::zero()]);
+    assert_eq!(
+        proof.node_position(),
+        Err(MerkleProofVerificationError::BranchTooLong(1))
+    );
+}
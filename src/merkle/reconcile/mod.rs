@@ -0,0 +1,404 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A request/response protocol to find the leaves that differ between the trees of two peers, like `MerkleTree::diff`,
+//! without sending the whole tree. The requesting peer asks for the root of the responding peer's tree, then,
+//! level by level, for the children of the nodes that differ from its own tree, until it reaches the differing leaves.
+//! All the differing nodes of a level are requested at once, so reconciling takes at most one round trip per level,
+//! and `O(d * log(n))` nodes are transferred in total for `d` differing leaves.
+//!
+//! The children sent by the responding peer are checked to hash to their parent, so the differing leaves are leaves of
+//! the tree with the root that the responding peer sent. The root doesn't commit to the number of real leaves, though,
+//! which is also sent by the responding peer, and decides which leaves are left out as padding. A requesting peer that
+//! knows the count-committed root of the remote tree (see `MerkleTree::count_committed_root`) should pass it to
+//! `ReconcileRequester::with_count_committed_root`, which verifies both. Otherwise, the real leaf count is unverified,
+//! and a dishonest responding peer can report another count with the same root, which hides real leaves as padding,
+//! or reports padding leaves as real ones.
+//!
+//! The protocol is transport agnostic: `ReconcileRequester` and `ReconcileResponder` are state machines that consume
+//! and produce messages, which can be sent over any channel.
+
+use alloc::vec::Vec;
+use core::{fmt::Debug, num::NonZeroU32};
+
+use crate::merkle::{
+    hasher::{LeafCountCommitter, PairHasher},
+    pos::NodePosition,
+    tree::{store::NodeStore, MerkleTree},
+    ReconcileError, ReconcileRejection,
+};
+
+/// A message from the requesting peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconcileRequest {
+    /// Asks for the size of the tree and its root, which starts a reconciliation.
+    Root,
+    /// Asks for the nodes at the given absolute indices.
+    Nodes(Vec<u32>),
+}
+
+/// A message from the responding peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconcileResponse<T> {
+    /// The size of the tree, including padding, the number of real leaves, and the root.
+    Root {
+        leaf_count: u32,
+        real_leaf_count: u32,
+        root: T,
+    },
+    /// The requested nodes, in the requested order.
+    Nodes(Vec<T>),
+    /// The request is invalid, and nothing more will be sent for it.
+    Rejected(ReconcileRejection),
+}
+
+/// The result of a reconciliation, as seen by the requesting peer.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconcileOutcome<T> {
+    /// The leaves of the responding peer that differ from the leaves of the requesting peer, by leaf index, in ascending order.
+    /// Like in `MerkleTree::diff`, leaves that are padding in both trees are not included.
+    pub differing_leaves: Vec<(u32, T)>,
+    /// The number of real leaves in the tree of the responding peer. It's always checked to fit the tree size,
+    /// but it's only verified if the requester was created with `ReconcileRequester::with_count_committed_root`.
+    pub remote_real_leaf_count: u32,
+}
+
+/// What the requesting peer should do after handling a response.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequesterStep<T> {
+    /// Send the request to the responding peer, and handle its response.
+    Send(ReconcileRequest),
+    /// The reconciliation is finished.
+    Done(ReconcileOutcome<T>),
+}
+
+enum RequesterState<T> {
+    Idle,
+    AwaitingRoot,
+    /// Awaiting the children of the given differing nodes, with their hashes in the remote tree
+    AwaitingNodes {
+        parents: Vec<(NodePosition, T)>,
+        remote_real_leaf_count: u32,
+        differing_leaves: Vec<(u32, T)>,
+    },
+    Done,
+}
+
+/// See `LeafCountCommitter::commit_leaf_count`.
+type CommitLeafCount<T> = fn(&T, u32) -> T;
+
+/// The state machine of the peer that finds which of its leaves differ from the responding peer's leaves.
+/// Start with `start`, send the returned requests, and pass the responses to `handle_response`, until it's done.
+#[must_use]
+pub struct ReconcileRequester<'a, T, H, S = Vec<T>> {
+    tree: &'a MerkleTree<T, H, S>,
+    /// The trusted count-committed root of the remote tree, with the function that commits to the leaf count
+    count_committed_root: Option<(T, CommitLeafCount<T>)>,
+    state: RequesterState<T>,
+}
+
+impl<T, H, S> Debug for ReconcileRequester<'_, T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = match self.state {
+            RequesterState::Idle => "Idle",
+            RequesterState::AwaitingRoot => "AwaitingRoot",
+            RequesterState::AwaitingNodes { .. } => "AwaitingNodes",
+            RequesterState::Done => "Done",
+        };
+        f.debug_struct("ReconcileRequester")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+impl<'a, T: Clone + Eq, H: PairHasher<NodeType = T>, S: NodeStore<T>>
+    ReconcileRequester<'a, T, H, S>
+{
+    pub fn new(tree: &'a MerkleTree<T, H, S>) -> Self {
+        Self {
+            tree,
+            count_committed_root: None,
+            state: RequesterState::Idle,
+        }
+    }
+
+    /// Starts (or restarts) the reconciliation, and returns the first request to send.
+    pub fn start(&mut self) -> ReconcileRequest {
+        self.state = RequesterState::AwaitingRoot;
+        ReconcileRequest::Root
+    }
+
+    /// Returns true if the reconciliation finished, successfully or not.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, RequesterState::Done)
+    }
+
+    /// Handles the response to the last sent request. On error, the reconciliation is finished and has to be restarted.
+    pub fn handle_response(
+        &mut self,
+        response: ReconcileResponse<T>,
+    ) -> Result<RequesterStep<T>, ReconcileError> {
+        let state = core::mem::replace(&mut self.state, RequesterState::Done);
+
+        match (state, response) {
+            (_, ReconcileResponse::Rejected(rejection)) => Err(ReconcileError::Rejected(rejection)),
+            (
+                RequesterState::AwaitingRoot,
+                ReconcileResponse::Root {
+                    leaf_count,
+                    real_leaf_count,
+                    root,
+                },
+            ) => self.handle_root(leaf_count, real_leaf_count, root),
+            (
+                RequesterState::AwaitingNodes {
+                    parents,
+                    remote_real_leaf_count,
+                    differing_leaves,
+                },
+                ReconcileResponse::Nodes(nodes),
+            ) => self.handle_nodes(parents, remote_real_leaf_count, differing_leaves, nodes),
+            (_, _) => Err(ReconcileError::UnexpectedResponse),
+        }
+    }
+
+    fn handle_root(
+        &mut self,
+        leaf_count: u32,
+        real_leaf_count: u32,
+        root: T,
+    ) -> Result<RequesterStep<T>, ReconcileError> {
+        let tree_size = self.tree.total_node_count();
+        if leaf_count != tree_size.leaf_count().get() {
+            return Err(ReconcileError::LeafCountMismatch(
+                tree_size.leaf_count().get(),
+                leaf_count,
+            ));
+        }
+
+        // The real leaf count must be one that pads to the leaf count, as in `MerkleTree::from_store`,
+        // otherwise real leaves could be passed off as padding
+        let valid_real_leaf_count = NonZeroU32::new(real_leaf_count)
+            .is_some_and(|count| count.get().checked_next_power_of_two() == Some(leaf_count));
+        if !valid_real_leaf_count {
+            return Err(ReconcileError::LeafCountMismatch(
+                leaf_count,
+                real_leaf_count,
+            ));
+        }
+
+        if let Some((committed_root, commit_leaf_count)) = &self.count_committed_root {
+            if commit_leaf_count(&root, real_leaf_count) != *committed_root {
+                return Err(ReconcileError::CountCommittedRootMismatch);
+            }
+        }
+
+        let root_position = NodePosition::from_abs_index(tree_size, tree_size.get() - 1)
+            .expect("The root is always in the tree");
+        self.compare_level([(root_position, root)], real_leaf_count, Vec::new())
+    }
+
+    fn handle_nodes(
+        &mut self,
+        parents: Vec<(NodePosition, T)>,
+        remote_real_leaf_count: u32,
+        differing_leaves: Vec<(u32, T)>,
+        nodes: Vec<T>,
+    ) -> Result<RequesterStep<T>, ReconcileError> {
+        if nodes.len() != parents.len() * 2 {
+            return Err(ReconcileError::NodeCountMismatch(
+                parents.len() * 2,
+                nodes.len(),
+            ));
+        }
+
+        let mut children = Vec::with_capacity(nodes.len());
+        let mut nodes = nodes.into_iter();
+        for (parent, parent_hash) in parents {
+            let (left, right) = parent.children().expect("Only non-leaves are requested");
+            let left_hash = nodes.next().expect("The length is checked");
+            let right_hash = nodes.next().expect("The length is checked");
+
+            if H::hash_pair(&left_hash, &right_hash) != parent_hash {
+                return Err(ReconcileError::InvalidChildren(parent.abs_index()));
+            }
+            children.push((left, left_hash));
+            children.push((right, right_hash));
+        }
+
+        self.compare_level(children, remote_real_leaf_count, differing_leaves)
+    }
+
+    /// Compares the given remote nodes, which are in the same level, with the local ones. Differing leaves are collected,
+    /// and the children of differing non-leaves are requested.
+    fn compare_level(
+        &mut self,
+        remote_nodes: impl IntoIterator<Item = (NodePosition, T)>,
+        remote_real_leaf_count: u32,
+        mut differing_leaves: Vec<(u32, T)>,
+    ) -> Result<RequesterStep<T>, ReconcileError> {
+        let mut parents = Vec::new();
+        for (position, remote_hash) in remote_nodes {
+            let (level, index_in_level) = position.position();
+            let local_hash = self
+                .tree
+                .node_value_from_bottom(level, index_in_level)
+                .expect("The position is in the tree");
+            if local_hash == remote_hash {
+                continue;
+            }
+
+            if level == 0 {
+                let remote_is_padding = index_in_level >= remote_real_leaf_count;
+                if !(remote_is_padding && self.tree.is_padding_leaf(index_in_level)) {
+                    differing_leaves.push((index_in_level, remote_hash));
+                }
+            } else {
+                parents.push((position, remote_hash));
+            }
+        }
+
+        if parents.is_empty() {
+            return Ok(RequesterStep::Done(ReconcileOutcome {
+                differing_leaves,
+                remote_real_leaf_count,
+            }));
+        }
+
+        let request = ReconcileRequest::Nodes(
+            parents
+                .iter()
+                .flat_map(|(parent, _)| {
+                    let (left, right) = parent.children().expect("Only non-leaves are requested");
+                    [left.abs_index(), right.abs_index()]
+                })
+                .collect(),
+        );
+        self.state = RequesterState::AwaitingNodes {
+            parents,
+            remote_real_leaf_count,
+            differing_leaves,
+        };
+        Ok(RequesterStep::Send(request))
+    }
+}
+
+impl<'a, T: Clone + Eq, H: LeafCountCommitter<NodeType = T>, S: NodeStore<T>>
+    ReconcileRequester<'a, T, H, S>
+{
+    /// Same as `new`, but the root and the real leaf count sent by the responding peer must produce the given trusted
+    /// count-committed root of the remote tree (see `MerkleTree::count_committed_root`), so that both are verified.
+    pub fn with_count_committed_root(tree: &'a MerkleTree<T, H, S>, committed_root: T) -> Self {
+        Self {
+            tree,
+            count_committed_root: Some((committed_root, H::commit_leaf_count)),
+            state: RequesterState::Idle,
+        }
+    }
+}
+
+enum ResponderState {
+    AwaitingRoot,
+    Serving { rounds: u32 },
+}
+
+/// The state machine of the peer that provides the nodes of its tree to a requesting peer. It rejects requests that
+/// don't follow the protocol, so that a requesting peer can't make it do more work than a reconciliation needs.
+#[must_use]
+pub struct ReconcileResponder<'a, T, H, S = Vec<T>> {
+    tree: &'a MerkleTree<T, H, S>,
+    state: ResponderState,
+}
+
+impl<T, H, S> Debug for ReconcileResponder<'_, T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let rounds = match self.state {
+            ResponderState::AwaitingRoot => None,
+            ResponderState::Serving { rounds } => Some(rounds),
+        };
+        f.debug_struct("ReconcileResponder")
+            .field("rounds", &rounds)
+            .finish()
+    }
+}
+
+impl<'a, T: Clone, H, S: NodeStore<T>> ReconcileResponder<'a, T, H, S> {
+    pub fn new(tree: &'a MerkleTree<T, H, S>) -> Self {
+        Self {
+            tree,
+            state: ResponderState::AwaitingRoot,
+        }
+    }
+
+    pub fn handle_request(&mut self, request: ReconcileRequest) -> ReconcileResponse<T> {
+        match self.try_handle_request(request) {
+            Ok(response) => response,
+            Err(rejection) => ReconcileResponse::Rejected(rejection),
+        }
+    }
+
+    fn try_handle_request(
+        &mut self,
+        request: ReconcileRequest,
+    ) -> Result<ReconcileResponse<T>, ReconcileRejection> {
+        let tree_size = self.tree.total_node_count();
+
+        match request {
+            ReconcileRequest::Root => {
+                self.state = ResponderState::Serving { rounds: 0 };
+                Ok(ReconcileResponse::Root {
+                    leaf_count: tree_size.leaf_count().get(),
+                    real_leaf_count: self.tree.real_leaf_count().get(),
+                    root: self.tree.root(),
+                })
+            }
+            ReconcileRequest::Nodes(indices) => {
+                let rounds = match &mut self.state {
+                    ResponderState::AwaitingRoot => {
+                        return Err(ReconcileRejection::RootNotRequested)
+                    }
+                    ResponderState::Serving { rounds } => rounds,
+                };
+                // Every round goes down one level, below the root
+                if *rounds + 1 >= tree_size.level_count().get() {
+                    return Err(ReconcileRejection::TooManyRounds);
+                }
+                if indices.len() > tree_size.get() as usize {
+                    return Err(ReconcileRejection::TooManyNodes(indices.len()));
+                }
+
+                let nodes = indices
+                    .into_iter()
+                    .map(|index| {
+                        let position = NodePosition::from_abs_index(tree_size, index)
+                            .ok_or(ReconcileRejection::IndexOutOfRange(index, tree_size.get()))?;
+                        let (level, index_in_level) = position.position();
+                        Ok(self
+                            .tree
+                            .node_value_from_bottom(level, index_in_level)
+                            .expect("The position is in the tree"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                *rounds += 1;
+                Ok(ReconcileResponse::Nodes(nodes))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
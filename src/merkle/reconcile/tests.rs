@@ -0,0 +1,353 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::mpsc;
+
+use rand::Rng;
+use rstest::rstest;
+
+use crate::{
+    internal::{hash_data, HashAlgo, HashedData},
+    rand_tools::{make_seedable_rng, Seed},
+};
+
+use super::*;
+
+type Tree = MerkleTree<HashedData, HashAlgo>;
+
+fn gen_leaves(n: u32) -> Vec<HashedData> {
+    (0..n)
+        .map(|i| hash_data(HashedData::from_low_u64_be(i as u64)))
+        .collect()
+}
+
+/// Reconciles the local tree with the remote tree, where each peer runs in its own thread, and the messages are sent
+/// through in-memory channels. Returns the outcome and the number of round trips.
+fn reconcile_over_channel(
+    local: &Tree,
+    remote: &Tree,
+) -> (Result<ReconcileOutcome<HashedData>, ReconcileError>, usize) {
+    let (request_sender, request_receiver) = mpsc::channel::<ReconcileRequest>();
+    let (response_sender, response_receiver) = mpsc::channel::<ReconcileResponse<HashedData>>();
+
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut responder = ReconcileResponder::new(remote);
+            // The requesting peer hangs up when it's done
+            while let Ok(request) = request_receiver.recv() {
+                response_sender
+                    .send(responder.handle_request(request))
+                    .unwrap();
+            }
+        });
+
+        // The sender is moved here, so that it's dropped, and the responding peer stops, when the requesting peer is done
+        let request_sender = request_sender;
+        let mut requester = ReconcileRequester::new(local);
+        let mut request = requester.start();
+        let mut round_trips = 0;
+        loop {
+            request_sender.send(request).unwrap();
+            round_trips += 1;
+            let response = response_receiver.recv().unwrap();
+            match requester.handle_response(response) {
+                Ok(RequesterStep::Send(next_request)) => request = next_request,
+                Ok(RequesterStep::Done(outcome)) => return (Ok(outcome), round_trips),
+                Err(err) => return (Err(err), round_trips),
+            }
+        }
+    })
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn reconcile_random_changes(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for leaf_count in 1..=40 {
+        let local_leaves = gen_leaves(leaf_count);
+        let local = Tree::from_leaves(local_leaves.clone()).unwrap();
+
+        for _ in 0..5 {
+            let mut remote_leaves = local_leaves.clone();
+            for leaf in remote_leaves.iter_mut() {
+                if rng.gen_bool(0.1) {
+                    *leaf = HashedData::repeat_byte(rng.gen_range(1..=255));
+                }
+            }
+            let remote = Tree::from_leaves(remote_leaves.clone()).unwrap();
+
+            let (outcome, round_trips) = reconcile_over_channel(&local, &remote);
+            let outcome = outcome.unwrap();
+            assert!(round_trips <= local.level_count().get() as usize);
+            assert_eq!(outcome.remote_real_leaf_count, leaf_count);
+
+            let expected = local
+                .diff(&remote)
+                .unwrap()
+                .into_iter()
+                .map(|index| (index, remote_leaves[index as usize]))
+                .collect::<Vec<_>>();
+            assert_eq!(outcome.differing_leaves, expected);
+
+            // Applying the differing leaves syncs the local leaves with the remote ones
+            let mut synced_leaves = local_leaves.clone();
+            for (index, leaf) in outcome.differing_leaves {
+                synced_leaves[index as usize] = leaf;
+            }
+            assert_eq!(Tree::from_leaves(synced_leaves).unwrap(), remote);
+        }
+    }
+}
+
+#[test]
+fn reconcile_same_trees() {
+    let t = Tree::from_leaves(gen_leaves(13)).unwrap();
+    let (outcome, round_trips) = reconcile_over_channel(&t, &t.clone());
+    assert_eq!(
+        outcome,
+        Ok(ReconcileOutcome {
+            differing_leaves: vec![],
+            remote_real_leaf_count: 13,
+        })
+    );
+    assert_eq!(round_trips, 1);
+}
+
+#[test]
+fn reconcile_different_real_leaf_counts() {
+    let leaves = gen_leaves(7);
+    let local = Tree::from_leaves(leaves[..5].to_vec()).unwrap();
+    let remote = Tree::from_leaves(leaves.clone()).unwrap();
+
+    let (outcome, _) = reconcile_over_channel(&local, &remote);
+    assert_eq!(
+        outcome,
+        Ok(ReconcileOutcome {
+            differing_leaves: vec![(5, leaves[5]), (6, leaves[6])],
+            remote_real_leaf_count: 7,
+        })
+    );
+}
+
+/// Reconciles with a responding peer that reports the given real leaf count instead of its own.
+fn reconcile_with_forged_real_leaf_count(
+    mut requester: ReconcileRequester<HashedData, HashAlgo>,
+    remote: &Tree,
+    forged_real_leaf_count: u32,
+) -> Result<ReconcileOutcome<HashedData>, ReconcileError> {
+    let mut responder = ReconcileResponder::new(remote);
+    let mut request = requester.start();
+    loop {
+        let response = match responder.handle_request(request) {
+            ReconcileResponse::Root {
+                leaf_count, root, ..
+            } => ReconcileResponse::Root {
+                leaf_count,
+                real_leaf_count: forged_real_leaf_count,
+                root,
+            },
+            response => response,
+        };
+        match requester.handle_response(response)? {
+            RequesterStep::Send(next_request) => request = next_request,
+            RequesterStep::Done(outcome) => return Ok(outcome),
+        }
+    }
+}
+
+#[test]
+fn reconcile_with_count_committed_root() {
+    let leaves = gen_leaves(7);
+    let local = Tree::from_leaves(leaves[..5].to_vec()).unwrap();
+    let remote = Tree::from_leaves(leaves.clone()).unwrap();
+    let committed_root = remote.count_committed_root();
+
+    // Reporting the new leaves as padding keeps the same root, so without the count-committed root, they're hidden
+    assert_eq!(
+        reconcile_with_forged_real_leaf_count(ReconcileRequester::new(&local), &remote, 5),
+        Ok(ReconcileOutcome {
+            differing_leaves: vec![],
+            remote_real_leaf_count: 5,
+        })
+    );
+    assert_eq!(
+        reconcile_with_forged_real_leaf_count(
+            ReconcileRequester::with_count_committed_root(&local, committed_root),
+            &remote,
+            5
+        ),
+        Err(ReconcileError::CountCommittedRootMismatch)
+    );
+
+    assert_eq!(
+        reconcile_with_forged_real_leaf_count(
+            ReconcileRequester::with_count_committed_root(&local, committed_root),
+            &remote,
+            7
+        ),
+        Ok(ReconcileOutcome {
+            differing_leaves: vec![(5, leaves[5]), (6, leaves[6])],
+            remote_real_leaf_count: 7,
+        })
+    );
+    assert_eq!(
+        reconcile_with_forged_real_leaf_count(
+            ReconcileRequester::with_count_committed_root(&local, remote.root()),
+            &remote,
+            7
+        ),
+        Err(ReconcileError::CountCommittedRootMismatch)
+    );
+}
+
+#[test]
+fn reconcile_with_invalid_real_leaf_count() {
+    let leaves = gen_leaves(7);
+    let local = Tree::from_leaves(leaves[..5].to_vec()).unwrap();
+    let remote = Tree::from_leaves(leaves).unwrap();
+
+    // No real leaves at all
+    assert_eq!(
+        reconcile_with_forged_real_leaf_count(ReconcileRequester::new(&local), &remote, 0),
+        Err(ReconcileError::LeafCountMismatch(8, 0))
+    );
+
+    // Too few real leaves for the leaf count, which would make real leaves look like padding
+    for forged_real_leaf_count in [1, 2, 4] {
+        assert_eq!(
+            reconcile_with_forged_real_leaf_count(
+                ReconcileRequester::new(&local),
+                &remote,
+                forged_real_leaf_count
+            ),
+            Err(ReconcileError::LeafCountMismatch(8, forged_real_leaf_count))
+        );
+    }
+
+    // More real leaves than the leaf count
+    assert_eq!(
+        reconcile_with_forged_real_leaf_count(ReconcileRequester::new(&local), &remote, 9),
+        Err(ReconcileError::LeafCountMismatch(8, 9))
+    );
+}
+
+#[test]
+fn reconcile_different_sizes() {
+    let local = Tree::from_leaves(gen_leaves(5)).unwrap();
+    let remote = Tree::from_leaves(gen_leaves(9)).unwrap();
+
+    let (outcome, round_trips) = reconcile_over_channel(&local, &remote);
+    assert_eq!(outcome, Err(ReconcileError::LeafCountMismatch(8, 16)));
+    assert_eq!(round_trips, 1);
+}
+
+#[test]
+fn requester_rejects_invalid_responses() {
+    let local = Tree::from_leaves(gen_leaves(8)).unwrap();
+    let mut remote_leaves = gen_leaves(8);
+    remote_leaves[2] = HashedData::zero();
+    let remote = Tree::from_leaves(remote_leaves).unwrap();
+
+    let mut requester = ReconcileRequester::new(&local);
+    assert!(!requester.is_done());
+    assert_eq!(
+        requester.handle_response(ReconcileResponse::Nodes(vec![])),
+        Err(ReconcileError::UnexpectedResponse)
+    );
+    assert!(requester.is_done());
+
+    let mut responder = ReconcileResponder::new(&remote);
+    let root_response = responder.handle_request(requester.start());
+    let request = match requester.handle_response(root_response.clone()).unwrap() {
+        RequesterStep::Send(request) => request,
+        RequesterStep::Done(_) => panic!("The trees differ"),
+    };
+    assert_eq!(request, ReconcileRequest::Nodes(vec![12, 13]));
+
+    // The children of the root don't hash to the root that was sent before
+    assert_eq!(
+        requester.handle_response(ReconcileResponse::Nodes(vec![
+            HashedData::zero(),
+            HashedData::zero()
+        ])),
+        Err(ReconcileError::InvalidChildren(14))
+    );
+
+    // Restarting after an error
+    requester.start();
+    let _ = requester.handle_response(root_response).unwrap();
+    assert_eq!(
+        requester.handle_response(ReconcileResponse::Nodes(vec![HashedData::zero()])),
+        Err(ReconcileError::NodeCountMismatch(2, 1))
+    );
+
+    requester.start();
+    assert_eq!(
+        requester.handle_response(ReconcileResponse::Rejected(
+            ReconcileRejection::RootNotRequested
+        )),
+        Err(ReconcileError::Rejected(
+            ReconcileRejection::RootNotRequested
+        ))
+    );
+}
+
+#[test]
+fn responder_rejects_invalid_requests() {
+    let t = Tree::from_leaves(gen_leaves(8)).unwrap();
+    let mut responder = ReconcileResponder::new(&t);
+
+    assert_eq!(
+        responder.handle_request(ReconcileRequest::Nodes(vec![0])),
+        ReconcileResponse::Rejected(ReconcileRejection::RootNotRequested)
+    );
+    assert_eq!(
+        responder.handle_request(ReconcileRequest::Root),
+        ReconcileResponse::Root {
+            leaf_count: 8,
+            real_leaf_count: 8,
+            root: t.root(),
+        }
+    );
+    assert_eq!(
+        responder.handle_request(ReconcileRequest::Nodes(vec![15])),
+        ReconcileResponse::Rejected(ReconcileRejection::IndexOutOfRange(15, 15))
+    );
+    assert_eq!(
+        responder.handle_request(ReconcileRequest::Nodes(vec![0; 16])),
+        ReconcileResponse::Rejected(ReconcileRejection::TooManyNodes(16))
+    );
+
+    // One round per level below the root
+    for _ in 0..3 {
+        assert_eq!(
+            responder.handle_request(ReconcileRequest::Nodes(vec![0, 14])),
+            ReconcileResponse::Nodes(vec![t.leaves()[0], t.root()])
+        );
+    }
+    assert_eq!(
+        responder.handle_request(ReconcileRequest::Nodes(vec![0])),
+        ReconcileResponse::Rejected(ReconcileRejection::TooManyRounds)
+    );
+
+    // Requesting the root again starts over
+    responder.handle_request(ReconcileRequest::Root);
+    assert_eq!(
+        responder.handle_request(ReconcileRequest::Nodes(vec![0])),
+        ReconcileResponse::Nodes(vec![t.leaves()[0]])
+    );
+}
@@ -0,0 +1,341 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering of trees, as Graphviz DOT or as an ASCII diagram, with shortened hashes.
+//! The nodes of a proof can be highlighted, to see which nodes a proof provides and which
+//! ones are computed from the leaves when verifying it.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::merkle::{
+    hasher::PairHasher,
+    pos::NodePosition,
+    proof::{diagnostics::NodeSource, multi::MultiProofNodes, single::SingleProofNodes},
+    tree::{store::NodeStore, MerkleTree, Node},
+};
+
+/// The number of bytes of a hash that are shown, in hex, when rendering a node.
+const SHORT_HASH_BYTES: usize = 4;
+
+/// Renders a tree, with optional highlighting of the nodes of proofs.
+/// The proofs must be extracted from the same tree. Padding leaves are always marked.
+#[must_use]
+pub struct TreeRenderer<'a, T, H, S = Vec<T>> {
+    tree: &'a MerkleTree<T, H, S>,
+    highlights: BTreeMap<u32, NodeSource>,
+}
+
+impl<'a, T: AsRef<[u8]> + Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>>
+    TreeRenderer<'a, T, H, S>
+{
+    pub fn new(tree: &'a MerkleTree<T, H, S>) -> Self {
+        Self {
+            tree,
+            highlights: BTreeMap::new(),
+        }
+    }
+
+    /// Highlights the leaf of the proof, the nodes of its branch, and the nodes computed from them.
+    pub fn highlight_single_proof(self, proof: &SingleProofNodes<'_, T, H, S>) -> Self {
+        self.highlight(core::iter::once(proof.leaf()), proof.branch())
+    }
+
+    /// Highlights the leaves of the proof, its nodes, and the nodes computed from them.
+    pub fn highlight_multi_proof(self, proof: &MultiProofNodes<'_, T, H, S>) -> Self {
+        self.highlight(proof.proof_leaves().iter().copied(), proof.nodes())
+    }
+
+    fn highlight<'b>(
+        mut self,
+        leaves: impl IntoIterator<Item = Node<'b, T, H, S>>,
+        proof_nodes: &[Node<'b, T, H, S>],
+    ) -> Self {
+        let leaves = leaves.into_iter().collect::<Vec<_>>();
+
+        for node in proof_nodes {
+            self.highlights.insert(node.abs_index(), NodeSource::Proof);
+        }
+        for leaf in &leaves {
+            self.highlights.insert(leaf.abs_index(), NodeSource::Leaf);
+        }
+        for leaf in leaves {
+            for parent in leaf.into_iter_parents().skip(1) {
+                self.highlights
+                    .entry(parent.abs_index())
+                    .or_insert(NodeSource::Computed);
+            }
+        }
+
+        self
+    }
+
+    fn source(&self, abs_index: u32) -> Option<NodeSource> {
+        self.highlights.get(&abs_index).copied()
+    }
+
+    fn is_padding(&self, position: NodePosition) -> bool {
+        let (level, index) = position.position();
+        level == 0 && self.tree.is_padding_leaf(index)
+    }
+
+    /// Renders the tree as a Graphviz DOT digraph, where every node points to its children.
+    /// Proven leaves are green, proof nodes are blue, computed nodes are yellow, and padding leaves are dashed.
+    pub fn render_dot(&self) -> String {
+        let mut out = String::new();
+        self.write_dot(&mut out)
+            .expect("Writing to a String never fails");
+        out
+    }
+
+    fn write_dot(&self, out: &mut String) -> core::fmt::Result {
+        writeln!(out, "digraph MerkleTree {{")?;
+        writeln!(out, "  node [shape=box, fontname=\"monospace\"];")?;
+
+        for (node, position) in self.tree.iter_breadth_first() {
+            let index = node.abs_index();
+            let (level, index_in_level) = position.position();
+
+            let mut styles = Vec::new();
+            let fill_color = self.source(index).map(|source| match source {
+                NodeSource::Leaf => "palegreen",
+                NodeSource::Proof => "lightblue",
+                NodeSource::Computed => "lightyellow",
+            });
+            if fill_color.is_some() {
+                styles.push("filled");
+            }
+            if self.is_padding(position) {
+                styles.push("dashed");
+            }
+
+            write!(
+                out,
+                "  n{index} [label=\"{level}/{index_in_level}: {}\"",
+                short_hash(node.hash())
+            )?;
+            if !styles.is_empty() {
+                write!(out, ", style=\"{}\"", styles.join(","))?;
+            }
+            if let Some(fill_color) = fill_color {
+                write!(out, ", fillcolor={fill_color}")?;
+            }
+            writeln!(out, "];")?;
+
+            if let Some((left, right)) = node.children() {
+                writeln!(out, "  n{index} -> n{};", left.abs_index())?;
+                writeln!(out, "  n{index} -> n{};", right.abs_index())?;
+            }
+        }
+
+        writeln!(out, "}}")
+    }
+
+    /// Renders the tree as an ASCII diagram, one line per level, starting from the root, where every node
+    /// is centered above its children. Proven leaves are in `[]`, proof nodes in `()`, computed nodes in `{}`,
+    /// and padding leaves are followed by `~`.
+    pub fn render_ascii(&self) -> String {
+        let label_width = SHORT_HASH_BYTES * 2 + 3;
+        let column_width = label_width + 1;
+
+        let mut out = String::new();
+        for (level, nodes) in self.tree.levels().enumerate().rev() {
+            let span_width = column_width << level;
+            let mut line = String::new();
+            for (index_in_level, hash) in nodes.iter().enumerate() {
+                let position = NodePosition::from_position(
+                    self.tree.total_node_count(),
+                    level as u32,
+                    index_in_level as u32,
+                )
+                .expect("The node is in the level");
+
+                let (open, close) = match self.source(position.abs_index()) {
+                    Some(NodeSource::Leaf) => ('[', ']'),
+                    Some(NodeSource::Proof) => ('(', ')'),
+                    Some(NodeSource::Computed) => ('{', '}'),
+                    None => (' ', ' '),
+                };
+                let padding = if self.is_padding(position) { '~' } else { ' ' };
+
+                let start = index_in_level * span_width + (span_width - label_width) / 2;
+                while line.len() < start {
+                    line.push(' ');
+                }
+                line.push(open);
+                line.push_str(&short_hash(hash));
+                line.push(close);
+                line.push(padding);
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+/// The first bytes of the hash in hex.
+fn short_hash<T: AsRef<[u8]>>(hash: &T) -> String {
+    hash.as_ref()
+        .iter()
+        .take(SHORT_HASH_BYTES)
+        .fold(String::new(), |mut s, byte| {
+            write!(s, "{byte:02x}").expect("Writing to a String never fails");
+            s
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internal::{hash_data, HashAlgo, HashedData};
+
+    use super::*;
+
+    fn gen_tree(leaf_count: u64) -> MerkleTree<HashedData, HashAlgo> {
+        let leaves = (0..leaf_count).map(|i| hash_data(HashedData::from_low_u64_be(i)));
+        MerkleTree::from_leaves(leaves).unwrap()
+    }
+
+    fn short(tree: &MerkleTree<HashedData, HashAlgo>, level: u32, index: u32) -> String {
+        hex::encode(&tree.node_value_from_bottom(level, index).unwrap()[..SHORT_HASH_BYTES])
+    }
+
+    #[test]
+    fn ascii_without_highlights() {
+        let t = gen_tree(3);
+        let expected = [
+            format!("{:18} {}", "", short(&t, 2, 0)),
+            format!("{:6} {}{:15} {}", "", short(&t, 1, 0), "", short(&t, 1, 1)),
+            format!(
+                " {}    {}    {}    {} ~",
+                short(&t, 0, 0),
+                short(&t, 0, 1),
+                short(&t, 0, 2),
+                short(&t, 0, 3)
+            ),
+        ]
+        .map(|line| line + "\n")
+        .concat();
+
+        assert_eq!(TreeRenderer::new(&t).render_ascii(), expected);
+    }
+
+    #[test]
+    fn ascii_with_single_proof() {
+        let t = gen_tree(3);
+        let proof = SingleProofNodes::from_tree_leaf(&t, 2).unwrap();
+        let expected = [
+            format!("{:18}{{{}}}", "", short(&t, 2, 0)),
+            format!(
+                "{:6}({}){:14}{{{}}}",
+                "",
+                short(&t, 1, 0),
+                "",
+                short(&t, 1, 1)
+            ),
+            format!(
+                " {}    {}   [{}]  ({})~",
+                short(&t, 0, 0),
+                short(&t, 0, 1),
+                short(&t, 0, 2),
+                short(&t, 0, 3)
+            ),
+        ]
+        .map(|line| line + "\n")
+        .concat();
+
+        assert_eq!(
+            TreeRenderer::new(&t)
+                .highlight_single_proof(&proof)
+                .render_ascii(),
+            expected
+        );
+    }
+
+    #[test]
+    fn dot_with_multi_proof() {
+        let t = gen_tree(5);
+        let proof = MultiProofNodes::from_tree_leaves(&t, &[1, 4]).unwrap();
+        let dot = TreeRenderer::new(&t)
+            .highlight_multi_proof(&proof)
+            .render_dot();
+
+        assert!(dot.starts_with("digraph MerkleTree {\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let lines = dot.lines().collect::<Vec<_>>();
+        let node_line = |abs_index: u32| {
+            let prefix = format!("  n{abs_index} [");
+            lines
+                .iter()
+                .find(|line| line.starts_with(&prefix))
+                .copied()
+                .unwrap()
+        };
+
+        // Every node appears once, and every internal node points to its children
+        assert_eq!(
+            lines.iter().filter(|line| line.contains("[label=")).count(),
+            15
+        );
+        assert_eq!(
+            lines.iter().filter(|line| line.contains(" -> ")).count(),
+            14
+        );
+        assert!(lines.contains(&"  n14 -> n12;"));
+        assert!(lines.contains(&"  n14 -> n13;"));
+        assert!(lines.contains(&"  n8 -> n0;"));
+
+        assert_eq!(
+            node_line(1),
+            format!(
+                "  n1 [label=\"0/1: {}\", style=\"filled\", fillcolor=palegreen];",
+                short(&t, 0, 1)
+            )
+        );
+        assert_eq!(
+            node_line(4),
+            format!(
+                "  n4 [label=\"0/4: {}\", style=\"filled\", fillcolor=palegreen];",
+                short(&t, 0, 4)
+            )
+        );
+        assert_eq!(
+            node_line(5),
+            format!(
+                "  n5 [label=\"0/5: {}\", style=\"filled,dashed\", fillcolor=lightblue];",
+                short(&t, 0, 5)
+            )
+        );
+        assert_eq!(
+            node_line(6),
+            format!(
+                "  n6 [label=\"0/6: {}\", style=\"dashed\"];",
+                short(&t, 0, 6)
+            )
+        );
+        assert_eq!(
+            node_line(2),
+            format!("  n2 [label=\"0/2: {}\"];", short(&t, 0, 2))
+        );
+        assert!(node_line(0).ends_with("fillcolor=lightblue];"));
+        assert!(node_line(9).ends_with("fillcolor=lightblue];"));
+        assert!(node_line(11).ends_with("fillcolor=lightblue];"));
+        for computed in [8, 10, 12, 13, 14] {
+            assert!(node_line(computed).ends_with("fillcolor=lightyellow];"));
+        }
+    }
+}
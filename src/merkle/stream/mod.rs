@@ -0,0 +1,442 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verified streaming of large data, such as files. The data is split into chunks of a fixed size, and a tree is
+//! built from the Blake2b hashes of the chunks. The encoding of the data interleaves the chunks with the nodes
+//! that are needed to verify them, so that each chunk can be verified against the root as soon as it arrives,
+//! rather than after the whole data is received.
+//!
+//! The encoding starts with a header of the content length (u64, little endian), the chunk size (u32, little endian)
+//! and the root of the tree, then the tree is written in pre-order: a parent is written as the hashes of its two
+//! children, followed by the encoding of the left subtree, then of the right subtree, and a leaf is written as its
+//! chunk. Subtrees that have only padding leaves are not written.
+//!
+//! The root that the encoding is verified against commits to the layout of the stream, too: it's the hash of the
+//! count-committed root of the tree (see `MerkleTree::count_committed_root`), the content length and the chunk size.
+//! So a header that doesn't match the root is rejected before any chunk is read, and a forged chunk size can't make
+//! the decoder buffer more than the chunks of the encoded stream.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use crate::merkle::{
+    hasher::{
+        blake2b::{Blake2bHasher, Blake2bNode},
+        LeafCountCommitter, PairHasher,
+    },
+    pos::NodePosition,
+    tree::{tree_size::TreeSize, MerkleTree},
+    StreamError,
+};
+
+/// The size of the header of an encoding: the content length, the chunk size and the root of the tree.
+pub const HEADER_SIZE: usize = 8 + 4 + NODE_SIZE;
+
+const NODE_SIZE: usize = core::mem::size_of::<Blake2bNode>();
+
+/// The tree of the chunks of a stream, which is used to create its encoding.
+pub type ChunkTree = MerkleTree<Blake2bNode, Blake2bHasher>;
+
+/// How a stream is split into chunks. All the chunks have the chunk size, except the last one,
+/// which may be shorter. An empty stream has a single empty chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    content_length: u64,
+    chunk_size: u32,
+    chunk_count: u32,
+}
+
+impl Layout {
+    fn new(content_length: u64, chunk_size: u32) -> Result<Self, StreamError> {
+        if chunk_size == 0 {
+            return Err(StreamError::ZeroChunkSize);
+        }
+
+        let chunk_count = content_length.div_ceil(chunk_size.into()).max(1);
+        let chunk_count = u32::try_from(chunk_count)
+            .ok()
+            .filter(|count| {
+                count
+                    .checked_next_power_of_two()
+                    .is_some_and(|leaf_count| TreeSize::from_leaf_count(leaf_count).is_ok())
+            })
+            .ok_or(StreamError::TooManyChunks(content_length, chunk_size))?;
+
+        Ok(Self {
+            content_length,
+            chunk_size,
+            chunk_count,
+        })
+    }
+
+    /// The number of bytes in the given range of chunks, which must be real chunks.
+    fn data_length(&self, chunk_range: Range<u32>) -> u64 {
+        let chunk_size = u64::from(self.chunk_size);
+        let end = (u64::from(chunk_range.end) * chunk_size).min(self.content_length);
+        end.saturating_sub(u64::from(chunk_range.start) * chunk_size)
+    }
+
+    fn chunk_length(&self, chunk_index: u32) -> u64 {
+        self.data_length(chunk_index..chunk_index + 1)
+    }
+
+    /// The number of bytes of the encoding of the subtree under the given node, without the header.
+    fn encoded_length(&self, position: NodePosition) -> u64 {
+        let (level, _) = position.position();
+        let leaf_range = position.leaf_range();
+        let real_end = leaf_range.end.min(self.chunk_count);
+        if leaf_range.start >= real_end {
+            return 0;
+        }
+
+        // Every parent with at least one real leaf under it is written as a pair of nodes
+        let real_leaf_count = u64::from(real_end - leaf_range.start);
+        let parent_count = (1..=level)
+            .map(|l| real_leaf_count.div_ceil(1 << l))
+            .sum::<u64>();
+
+        parent_count * 2 * NODE_SIZE as u64 + self.data_length(leaf_range.start..real_end)
+    }
+}
+
+/// The tree of the chunks of a stream, with how the stream is split into chunks.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamTree {
+    tree: ChunkTree,
+    layout: Layout,
+}
+
+impl StreamTree {
+    /// The root that the encoding of the stream is verified against, see `commit_layout`.
+    pub fn root(&self) -> Blake2bNode {
+        commit_layout(&self.tree.root(), &self.layout)
+    }
+
+    pub fn tree(&self) -> &ChunkTree {
+        &self.tree
+    }
+
+    pub fn content_length(&self) -> u64 {
+        self.layout.content_length
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.layout.chunk_size
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        self.layout.chunk_count
+    }
+
+    /// The number of bytes of the encoding of the stream, including the header.
+    pub fn encoded_length(&self) -> u64 {
+        HEADER_SIZE as u64 + self.layout.encoded_length(root_position(&self.layout))
+    }
+}
+
+/// Combines the root of the tree with the layout of the stream. The count-committed root of the tree is concatenated
+/// with the content length and the chunk size in little endian, which makes the input 44 bytes.
+fn commit_layout(tree_root: &Blake2bNode, layout: &Layout) -> Blake2bNode {
+    let count_committed_root = Blake2bHasher::commit_leaf_count(tree_root, layout.chunk_count);
+    let mut data = [0u8; NODE_SIZE + 8 + 4];
+    data[..NODE_SIZE].copy_from_slice(&count_committed_root);
+    data[NODE_SIZE..NODE_SIZE + 8].copy_from_slice(&layout.content_length.to_le_bytes());
+    data[NODE_SIZE + 8..].copy_from_slice(&layout.chunk_size.to_le_bytes());
+    Blake2bHasher::hash_data(data)
+}
+
+fn root_position(layout: &Layout) -> NodePosition {
+    let leaf_count = layout
+        .chunk_count
+        .checked_next_power_of_two()
+        .expect("Checked when creating the layout");
+    let tree_size =
+        TreeSize::from_leaf_count(leaf_count).expect("Checked when creating the layout");
+    NodePosition::from_position(tree_size, tree_size.level_count().get() - 1, 0)
+        .expect("The root is always in the tree")
+}
+
+/// Reads at most `length` bytes, fewer only if the end of the reader is reached.
+fn read_up_to<R: Read>(reader: &mut R, length: u64) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(length).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Splits the data of the reader, until its end, into chunks of the given size, and builds the tree of their hashes.
+pub fn hash_chunks<R: Read>(reader: &mut R, chunk_size: u32) -> Result<StreamTree, StreamError> {
+    if chunk_size == 0 {
+        return Err(StreamError::ZeroChunkSize);
+    }
+
+    let mut leaves = Vec::new();
+    let mut content_length = 0u64;
+    loop {
+        let chunk = read_up_to(reader, chunk_size.into())?;
+        // An empty stream still has an empty chunk
+        if chunk.is_empty() && !leaves.is_empty() {
+            break;
+        }
+
+        content_length += chunk.len() as u64;
+        leaves.push(Blake2bHasher::hash_data(&chunk));
+        if chunk.len() < chunk_size as usize {
+            break;
+        }
+    }
+
+    let layout = Layout::new(content_length, chunk_size)?;
+    let tree = ChunkTree::from_leaves(leaves)?;
+    Ok(StreamTree { tree, layout })
+}
+
+/// Encodes the data of the reader, from its current position until its end, into the writer. The data is read twice:
+/// once to build the tree, and once to write the chunks, which are checked to not have changed in between.
+/// Returns the tree, whose `StreamTree::root` is what the encoding is verified against.
+pub fn encode<R: Read + Seek, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    chunk_size: u32,
+) -> Result<StreamTree, StreamError> {
+    let start = reader.stream_position()?;
+    let stream_tree = hash_chunks(reader, chunk_size)?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let layout = stream_tree.layout;
+    let tree = &stream_tree.tree;
+    writer.write_all(&layout.content_length.to_le_bytes())?;
+    writer.write_all(&layout.chunk_size.to_le_bytes())?;
+    writer.write_all(&tree.root())?;
+
+    let mut pending = vec![root_position(&layout)];
+    while let Some(position) = pending.pop() {
+        match position.children() {
+            None => {
+                let chunk_index = position.leaf_range().start;
+                let chunk = read_up_to(reader, layout.chunk_length(chunk_index))?;
+                let leaf = tree.leaves()[chunk_index as usize];
+                if chunk.len() as u64 != layout.chunk_length(chunk_index)
+                    || Blake2bHasher::hash_data(&chunk) != leaf
+                {
+                    return Err(StreamError::SourceChanged(chunk_index));
+                }
+                writer.write_all(&chunk)?;
+            }
+            Some((left, right)) => {
+                for child in [left, right] {
+                    let (level, index) = child.position();
+                    let hash = tree
+                        .node_value_from_bottom(level, index)
+                        .expect("The child is in the tree");
+                    writer.write_all(&hash)?;
+                }
+                if right.leaf_range().start < layout.chunk_count {
+                    pending.push(right);
+                }
+                pending.push(left);
+            }
+        }
+    }
+
+    Ok(stream_tree)
+}
+
+/// Decodes an encoding from a reader, and yields its chunks, with their indices, each after it's verified against
+/// the root. After an error, nothing more is yielded, and the chunks that were yielded before the error are valid.
+#[must_use]
+pub struct StreamDecoder<R> {
+    reader: R,
+    /// Skips the given number of bytes of the reader, by seeking if possible
+    skip: fn(&mut R, u64) -> io::Result<()>,
+    layout: Layout,
+    chunk_range: Range<u32>,
+    /// The nodes that are still to be read, each with its verified hash, the next one last
+    pending: Vec<(NodePosition, Blake2bNode)>,
+    failed: bool,
+}
+
+impl<R> core::fmt::Debug for StreamDecoder<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StreamDecoder")
+            .field("layout", &self.layout)
+            .field("chunk_range", &self.chunk_range)
+            .field("pending", &self.pending)
+            .field("failed", &self.failed)
+            .finish()
+    }
+}
+
+impl<R: Read> StreamDecoder<R> {
+    /// Reads the header of the encoding, and checks it against the given root, which must be
+    /// the `StreamTree::root` of the encoded stream. All the chunks are yielded.
+    pub fn new(reader: R, root: Blake2bNode) -> Result<Self, StreamError> {
+        Self::from_header(reader, root, skip_by_reading, None)
+    }
+
+    fn from_header(
+        mut reader: R,
+        root: Blake2bNode,
+        skip: fn(&mut R, u64) -> io::Result<()>,
+        chunk_range: Option<Range<u32>>,
+    ) -> Result<Self, StreamError> {
+        let mut header = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let content_length = u64::from_le_bytes(header[..8].try_into().expect("8 bytes"));
+        let chunk_size = u32::from_le_bytes(header[8..12].try_into().expect("4 bytes"));
+        let tree_root: Blake2bNode = header[12..].try_into().expect("Node size");
+
+        let layout = Layout::new(content_length, chunk_size).map_err(|err| match err {
+            // The root commits to the layout, so a header that's too large for a tree is not for this root
+            StreamError::TooManyChunks(_, _) => StreamError::RootMismatch,
+            err => err,
+        })?;
+        if commit_layout(&tree_root, &layout) != root {
+            return Err(StreamError::RootMismatch);
+        }
+
+        let chunk_range = chunk_range.unwrap_or(0..layout.chunk_count);
+        if chunk_range.start >= chunk_range.end || chunk_range.end > layout.chunk_count {
+            return Err(StreamError::InvalidChunkRange(
+                chunk_range.start,
+                chunk_range.end,
+                layout.chunk_count,
+            ));
+        }
+
+        Ok(Self {
+            reader,
+            skip,
+            layout,
+            chunk_range,
+            pending: vec![(root_position(&layout), tree_root)],
+            failed: false,
+        })
+    }
+
+    pub fn content_length(&self) -> u64 {
+        self.layout.content_length
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.layout.chunk_size
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        self.layout.chunk_count
+    }
+
+    /// The range of the indices of the chunks that are yielded.
+    pub fn chunk_range(&self) -> Range<u32> {
+        self.chunk_range.clone()
+    }
+
+    fn read_node(&mut self) -> Result<Blake2bNode, StreamError> {
+        let mut node = Blake2bNode::default();
+        self.reader.read_exact(&mut node)?;
+        Ok(node)
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<(u32, Vec<u8>)>, StreamError> {
+        while let Some((position, hash)) = self.pending.pop() {
+            let leaf_range = position.leaf_range();
+            if leaf_range.start >= self.chunk_range.end {
+                // The remaining nodes are all after the range
+                self.pending.clear();
+                return Ok(None);
+            }
+            if leaf_range.end <= self.chunk_range.start {
+                (self.skip)(&mut self.reader, self.layout.encoded_length(position))?;
+                continue;
+            }
+
+            match position.children() {
+                None => {
+                    let chunk_index = leaf_range.start;
+                    let chunk_length = self.layout.chunk_length(chunk_index);
+                    let chunk = read_up_to(&mut self.reader, chunk_length)?;
+                    if chunk.len() as u64 != chunk_length {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                    if Blake2bHasher::hash_data(&chunk) != hash {
+                        return Err(StreamError::ChunkHashMismatch(chunk_index));
+                    }
+                    return Ok(Some((chunk_index, chunk)));
+                }
+                Some((left, right)) => {
+                    let left_hash = self.read_node()?;
+                    let right_hash = self.read_node()?;
+                    if Blake2bHasher::hash_pair(&left_hash, &right_hash) != hash {
+                        return Err(StreamError::NodeHashMismatch(position.abs_index()));
+                    }
+                    if right.leaf_range().start < self.layout.chunk_count {
+                        self.pending.push((right, right_hash));
+                    }
+                    self.pending.push((left, left_hash));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<R: Read + Seek> StreamDecoder<R> {
+    /// Like `new`, but only the chunks in the given range are yielded. The subtrees before the range are
+    /// skipped by seeking over them, so only the nodes on the way to the range, and its chunks, are read.
+    /// The reader must be at the start of the encoding.
+    pub fn with_chunk_range(
+        reader: R,
+        root: Blake2bNode,
+        chunk_range: Range<u32>,
+    ) -> Result<Self, StreamError> {
+        Self::from_header(reader, root, skip_by_seeking, Some(chunk_range))
+    }
+}
+
+impl<R: Read> Iterator for StreamDecoder<R> {
+    type Item = Result<(u32, Vec<u8>), StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.next_chunk() {
+            Ok(chunk) => chunk.map(Ok),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn skip_by_reading<R: Read>(reader: &mut R, length: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(length), &mut io::sink())?;
+    if skipped != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn skip_by_seeking<R: Seek>(reader: &mut R, length: u64) -> io::Result<()> {
+    let offset = i64::try_from(length).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    reader.seek(SeekFrom::Current(offset))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
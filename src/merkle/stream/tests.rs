@@ -0,0 +1,263 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Cursor;
+
+use rand::{Rng, RngCore};
+use rstest::rstest;
+
+use crate::rand_tools::{make_seedable_rng, Seed};
+
+use super::*;
+
+fn encode_data(data: &[u8], chunk_size: u32) -> (StreamTree, Vec<u8>) {
+    let mut encoding = Vec::new();
+    let stream_tree = encode(&mut Cursor::new(data), &mut encoding, chunk_size).unwrap();
+    (stream_tree, encoding)
+}
+
+fn decode_all<R: Read>(decoder: StreamDecoder<R>) -> Result<Vec<(u32, Vec<u8>)>, StreamError> {
+    decoder.collect()
+}
+
+/// Counts the bytes that are read, to check that seeking skips the parts that aren't needed.
+struct CountingReader<R> {
+    inner: R,
+    read_count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.read_count += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn encode_and_decode(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for chunk_size in [1, 3, 4, 64] {
+        for content_length in 0..=(chunk_size as usize * 9) {
+            let mut data = vec![0u8; content_length];
+            rng.fill_bytes(&mut data);
+
+            let (stream_tree, encoding) = encode_data(&data, chunk_size);
+            assert_eq!(stream_tree.content_length(), content_length as u64);
+            assert_eq!(
+                stream_tree.chunk_count() as usize,
+                content_length.div_ceil(chunk_size as usize).max(1)
+            );
+            assert_eq!(stream_tree.encoded_length(), encoding.len() as u64);
+            assert_eq!(
+                stream_tree.tree().root(),
+                encoding[12..HEADER_SIZE],
+                "The header has the root of the tree"
+            );
+
+            let expected_chunks = if data.is_empty() {
+                vec![(0, vec![])]
+            } else {
+                data.chunks(chunk_size as usize)
+                    .enumerate()
+                    .map(|(i, chunk)| (i as u32, chunk.to_vec()))
+                    .collect::<Vec<_>>()
+            };
+            let decoder = StreamDecoder::new(encoding.as_slice(), stream_tree.root()).unwrap();
+            assert_eq!(decoder.content_length(), content_length as u64);
+            assert_eq!(decode_all(decoder).unwrap(), expected_chunks);
+        }
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn decode_chunk_ranges(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    let chunk_size = 8;
+    for chunk_count in 1..=17u32 {
+        let content_length =
+            chunk_size as usize * (chunk_count as usize - 1) + rng.gen_range(1..=8);
+        let mut data = vec![0u8; content_length];
+        rng.fill_bytes(&mut data);
+        let (stream_tree, encoding) = encode_data(&data, chunk_size);
+        let chunks = data.chunks(chunk_size as usize).collect::<Vec<_>>();
+
+        for start in 0..chunk_count {
+            for end in (start + 1)..=chunk_count {
+                let mut reader = CountingReader {
+                    inner: Cursor::new(encoding.as_slice()),
+                    read_count: 0,
+                };
+                let decoder =
+                    StreamDecoder::with_chunk_range(&mut reader, stream_tree.root(), start..end)
+                        .unwrap();
+                assert_eq!(decoder.chunk_range(), start..end);
+
+                let expected_chunks = (start..end)
+                    .map(|i| (i, chunks[i as usize].to_vec()))
+                    .collect::<Vec<_>>();
+                assert_eq!(decode_all(decoder).unwrap(), expected_chunks);
+
+                // Only the header, the chunks in the range, and the pairs of the parents that overlap the range are read
+                let range_length = expected_chunks
+                    .iter()
+                    .map(|(_, c)| c.len() as u64)
+                    .sum::<u64>();
+                let level_count = stream_tree.tree().level_count().get();
+                let max_parent_count = (1..level_count)
+                    .map(|l| (end - start).div_ceil(1 << l) as u64 + 1)
+                    .sum::<u64>();
+                let max_node_bytes = max_parent_count * 2 * NODE_SIZE as u64;
+                assert!(reader.read_count <= HEADER_SIZE as u64 + range_length + max_node_bytes);
+            }
+        }
+    }
+}
+
+#[test]
+fn tampered_encoding_is_rejected() {
+    let data = (0..=100u8).collect::<Vec<_>>();
+    let (stream_tree, encoding) = encode_data(&data, 16);
+    let chunks = data.chunks(16).collect::<Vec<_>>();
+
+    for byte_index in 0..encoding.len() {
+        let mut tampered = encoding.clone();
+        tampered[byte_index] ^= 1;
+
+        // The root commits to the whole header, so changes of the header are detected before any chunk is read
+        let mut decoder = match StreamDecoder::new(tampered.as_slice(), stream_tree.root()) {
+            Ok(decoder) => decoder,
+            Err(_) => continue,
+        };
+        assert!(
+            byte_index >= HEADER_SIZE,
+            "Tampering header byte {byte_index} wasn't detected"
+        );
+        let mut failed = false;
+        for result in decoder.by_ref() {
+            match result {
+                // Chunks that are yielded before the error are always valid
+                Ok((index, chunk)) => assert_eq!(chunk, chunks[index as usize]),
+                Err(_) => {
+                    failed = true;
+                    break;
+                }
+            }
+        }
+        assert!(failed, "Tampering byte {byte_index} wasn't detected");
+        assert!(decoder.next().is_none());
+    }
+}
+
+#[test]
+fn truncated_encoding_is_rejected() {
+    let data = vec![7u8; 100];
+    let (stream_tree, encoding) = encode_data(&data, 16);
+
+    let decoder = StreamDecoder::new(&encoding[..encoding.len() - 1], stream_tree.root()).unwrap();
+    assert_eq!(
+        decode_all(decoder),
+        Err(StreamError::Io(io::ErrorKind::UnexpectedEof))
+    );
+    assert_eq!(
+        StreamDecoder::new(&encoding[..HEADER_SIZE - 1], stream_tree.root()).err(),
+        Some(StreamError::Io(io::ErrorKind::UnexpectedEof))
+    );
+}
+
+#[test]
+fn invalid_arguments() {
+    let data = vec![7u8; 100];
+    let (stream_tree, encoding) = encode_data(&data, 16);
+
+    assert_eq!(
+        encode(&mut Cursor::new(&data), &mut Vec::new(), 0).err(),
+        Some(StreamError::ZeroChunkSize)
+    );
+    assert_eq!(
+        StreamDecoder::new(encoding.as_slice(), stream_tree.tree().root()).err(),
+        Some(StreamError::RootMismatch)
+    );
+    for (start, end) in [(0, 0), (3, 2), (0, 8)] {
+        assert_eq!(
+            StreamDecoder::with_chunk_range(Cursor::new(&encoding), stream_tree.root(), start..end)
+                .err(),
+            Some(StreamError::InvalidChunkRange(start, end, 7))
+        );
+    }
+}
+
+#[test]
+fn header_with_another_chunk_count_is_rejected() {
+    // The tree of 3 chunks has a padding leaf, which is the hash of the third leaf. A fourth chunk
+    // whose hash is that padding leaf gives a tree with the same root, so the chunk count must be committed to.
+    let data = (0..96u8).collect::<Vec<_>>();
+    let (stream_tree, _) = encode_data(&data, 32);
+    let third_leaf = stream_tree.tree().leaves()[2];
+    let extended_data = [data.as_slice(), third_leaf.as_slice()].concat();
+    let (extended_stream_tree, extended_encoding) = encode_data(&extended_data, 32);
+
+    assert_eq!(
+        stream_tree.tree().root(),
+        extended_stream_tree.tree().root()
+    );
+    assert_ne!(stream_tree.root(), extended_stream_tree.root());
+    assert_eq!(
+        StreamDecoder::new(extended_encoding.as_slice(), stream_tree.root()).err(),
+        Some(StreamError::RootMismatch)
+    );
+}
+
+#[test]
+fn header_with_forged_layout_is_rejected() {
+    // A single chunk, whose tree is the same for any chunk size and content length that give one chunk
+    let data = vec![7u8; 10];
+    let (stream_tree, encoding) = encode_data(&data, 16);
+
+    for (content_length, chunk_size) in [
+        (u64::from(u32::MAX), u32::MAX),
+        (10, u32::MAX),
+        (11, 16),
+        (9, 10),
+    ] {
+        let mut forged = encoding.clone();
+        forged[..8].copy_from_slice(&content_length.to_le_bytes());
+        forged[8..12].copy_from_slice(&chunk_size.to_le_bytes());
+        // Enough data after the header for the forged chunk, which must not be read
+        forged.resize(HEADER_SIZE + 1024, 0);
+
+        let mut reader = CountingReader {
+            inner: Cursor::new(forged.as_slice()),
+            read_count: 0,
+        };
+        assert_eq!(
+            StreamDecoder::new(&mut reader, stream_tree.root()).err(),
+            Some(StreamError::RootMismatch)
+        );
+        assert_eq!(reader.read_count, HEADER_SIZE as u64);
+    }
+}
@@ -14,14 +14,28 @@
 // limitations under the License.
 
 pub mod padding;
+pub mod persistent;
+pub mod store;
+pub mod traversal;
+pub mod tree64;
 pub mod tree_size;
 
-use self::{padding::IncrementalPaddingIterator, tree_size::TreeSize};
-use std::fmt::Debug;
+use self::{
+    padding::IncrementalPaddingIterator,
+    store::NodeStore,
+    traversal::{DepthFirstIterator, DepthFirstOrder},
+    tree_size::TreeSize,
+};
+use alloc::vec::Vec;
+use core::fmt::Debug;
 
-use std::num::NonZeroU32;
+use core::num::NonZeroU32;
 
-use super::{hasher::PairHasher, pos::NodePosition, MerkleTreeAccessError, MerkleTreeFormError};
+use super::{
+    hasher::{LeafCountCommitter, PairHasher},
+    pos::NodePosition,
+    MerkleTreeAccessError, MerkleTreeDiffError, MerkleTreeFormError, NodeStoreError,
+};
 
 /// Merkle tree in the form of a vector, where the bottom leaves first, from left to right, and the root is
 /// the last element.
@@ -38,38 +52,62 @@ use super::{hasher::PairHasher, pos::NodePosition, MerkleTreeAccessError, Merkle
 ///
 /// Given that this is strictly a filled-up binary tree, the number of leaves is always a power of 2, and the total number of
 /// nodes is always 2 * leaves - 1. These are invariants that are always held through type-level checks.
+/// The number of leaves before padding is kept too, to tell the real leaves apart from the padding leaves.
+///
+/// The nodes are kept in a `NodeStore`, by absolute index, which is a `Vec` by default.
 #[derive(Clone)]
-pub struct MerkleTree<T, H> {
-    tree: Vec<T>,
-    _hasher: std::marker::PhantomData<H>,
+pub struct MerkleTree<T, H, S = Vec<T>> {
+    store: S,
+    real_leaf_count: NonZeroU32,
+    _phantom: core::marker::PhantomData<(T, H)>,
 }
 
-impl<T: PartialEq, H> PartialEq for MerkleTree<T, H> {
+impl<T: PartialEq, H, S: NodeStore<T>> PartialEq for MerkleTree<T, H, S> {
     fn eq(&self, other: &Self) -> bool {
-        self.tree == other.tree
+        self.nodes() == other.nodes() && self.real_leaf_count == other.real_leaf_count
     }
 }
 
-impl<T, H> Eq for MerkleTree<T, H> where T: Eq {}
+impl<T: Eq, H, S: NodeStore<T>> Eq for MerkleTree<T, H, S> {}
 
-impl<T: Debug, H> Debug for MerkleTree<T, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<T: Debug, H, S: NodeStore<T>> Debug for MerkleTree<T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("MerkleTree")
-            .field("tree", &self.tree)
+            .field("tree", &self.nodes())
+            .field("real_leaf_count", &self.real_leaf_count)
             .finish()
     }
 }
 
-impl<T: Clone, H> MerkleTree<T, H> {
+impl<T, H, S: NodeStore<T>> MerkleTree<T, H, S> {
+    /// All the nodes of the tree, by absolute index.
+    fn nodes(&self) -> &[T] {
+        self.store
+            .get_range(0..self.store.len())
+            .expect("The whole store is in range")
+    }
+
+    /// The store that holds the nodes of the tree.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Consumes the tree and returns the store that holds its nodes.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+}
+
+impl<T: Clone, H, S: NodeStore<T>> MerkleTree<T, H, S> {
     pub fn root(&self) -> T {
-        self.tree
+        self.nodes()
             .last()
             .cloned()
             .expect("By design, at least one element must exist")
     }
 
     pub fn total_node_count(&self) -> TreeSize {
-        self.tree
+        self.store
             .len()
             .try_into()
             .expect("(total_node_count) By design, tree_size is always > 0")
@@ -80,6 +118,17 @@ impl<T: Clone, H> MerkleTree<T, H> {
         tree_size.leaf_count()
     }
 
+    /// The number of leaves that the tree was created from, before padding.
+    pub fn real_leaf_count(&self) -> NonZeroU32 {
+        self.real_leaf_count
+    }
+
+    /// Returns true if the leaf at the given index was created by padding, rather than provided
+    /// when creating the tree. Indices beyond the leaves of the tree are not padding leaves.
+    pub fn is_padding_leaf(&self, leaf_index: u32) -> bool {
+        leaf_index >= self.real_leaf_count.get() && leaf_index < self.leaf_count().get()
+    }
+
     pub fn level_count(&self) -> NonZeroU32 {
         let tree_size = self.total_node_count();
         tree_size.level_count()
@@ -87,7 +136,7 @@ impl<T: Clone, H> MerkleTree<T, H> {
 
     pub fn node_value_from_bottom(&self, level_from_bottom: u32, index_in_level: u32) -> Option<T> {
         let index_in_tree = NodePosition::from_position(
-            self.tree
+            self.store
                 .len()
                 .try_into()
                 .expect("Tree size is by design > 0"),
@@ -96,16 +145,16 @@ impl<T: Clone, H> MerkleTree<T, H> {
         )?
         .abs_index();
 
-        Some(self.tree[index_in_tree as usize].clone())
+        self.store.get(index_in_tree as usize).cloned()
     }
 
     pub fn node_from_bottom(
         &self,
         level_from_bottom: u32,
         index_in_level: u32,
-    ) -> Option<Node<T, H>> {
+    ) -> Option<Node<'_, T, H, S>> {
         let absolute_index = NodePosition::from_position(
-            self.tree
+            self.store
                 .len()
                 .try_into()
                 .expect("Tree size is by design > 0"),
@@ -119,49 +168,124 @@ impl<T: Clone, H> MerkleTree<T, H> {
             absolute_index,
         })
     }
-}
 
-impl<T: Clone, H: PairHasher<NodeType = T>> MerkleTree<T, H> {
-    fn create_tree_from_padded_leaves(
-        padded_leaves: impl IntoIterator<Item = T>,
-    ) -> Result<Vec<T>, MerkleTreeFormError> {
-        let mut tree = padded_leaves.into_iter().collect::<Vec<_>>();
-        if tree.is_empty() {
-            return Err(MerkleTreeFormError::TooSmall(tree.len()));
-        }
-        let steps = tree.len() - 1;
-        tree.reserve(steps); // reserve another tree.len() - 1 elements (the rest of the tree after the leaves)
-        for i in 0..steps {
-            let el = H::hash_pair(&tree[i * 2], &tree[i * 2 + 1]);
-            tree.push(el);
-        }
+    /// The nodes at the given level, from left to right, or None if the level is above the root.
+    pub fn level(&self, level_from_bottom: u32) -> Option<&[T]> {
+        let level_start = self.total_node_count().level_start(level_from_bottom)? as usize;
+        let level_size = (self.leaf_count().get() >> level_from_bottom) as usize;
+        self.store.get_range(level_start..level_start + level_size)
+    }
+
+    /// The levels of the tree, from the leaves to the root.
+    pub fn levels(&self) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator + '_ {
+        (0..self.level_count().get()).map(|level| self.level(level).expect("Level is in range"))
+    }
+
+    /// The leaves of the tree, including the padding leaves.
+    pub fn leaves(&self) -> &[T] {
+        &self.nodes()[..self.leaf_count().get() as usize]
+    }
+
+    /// Iterates over the nodes of the tree, breadth-first, i.e., from the root down, level by level,
+    /// and from left to right within a level.
+    pub fn iter_breadth_first(
+        &self,
+    ) -> impl Iterator<Item = (Node<'_, T, H, S>, NodePosition)> + '_ {
+        let tree_size = self.total_node_count();
+        (0..self.level_count().get()).rev().flat_map(move |level| {
+            let level_start = tree_size.level_start(level).expect("Level is in range");
+            let level_size = self.leaf_count().get() >> level;
+            (level_start..level_start + level_size).map(move |absolute_index| {
+                let node = Node {
+                    tree_ref: self,
+                    absolute_index,
+                };
+                let position = NodePosition::from_abs_index(tree_size, absolute_index)
+                    .expect("Index is in the level");
+                (node, position)
+            })
+        })
+    }
 
-        Ok(tree)
+    /// Iterates over the nodes of the tree, depth-first, in the given order.
+    pub fn iter_depth_first(&self, order: DepthFirstOrder) -> DepthFirstIterator<'_, T, H, S> {
+        DepthFirstIterator::new(self, order)
     }
+}
 
+impl<T: Clone, H: PairHasher<NodeType = T>> MerkleTree<T, H> {
     /// Create a new merkle tree from a list of leaves, and padding with incremental padding if needed.
     /// Incremental padding means that the padding is created by hashing the last element of the list,
     /// and then hashing the result with the next element of the list, and so on.
     pub fn from_leaves(leaves: impl IntoIterator<Item = T>) -> Result<Self, MerkleTreeFormError> {
-        let pad_f = |i: &T| H::hash_single(i);
+        Self::from_leaves_in_store(Vec::new(), leaves)
+    }
+}
 
-        let padded_leaves_iter = IncrementalPaddingIterator::new(leaves.into_iter().fuse(), pad_f);
+impl<T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> MerkleTree<T, H, S> {
+    /// Computes the internal nodes of the tree, from the padded leaves that are already in the store.
+    fn create_tree_from_padded_leaves(store: &mut S) -> Result<(), MerkleTreeFormError> {
+        checked_tree_size_for_leaf_count(store.len())?;
+        hash_internal_nodes::<T, H, S>(store)?;
+        Ok(())
+    }
+
+    /// Like `from_leaves`, but the nodes are written to the given store, which must be empty.
+    pub fn from_leaves_in_store(
+        mut store: S,
+        leaves: impl IntoIterator<Item = T>,
+    ) -> Result<Self, MerkleTreeFormError> {
+        if !store.is_empty() {
+            return Err(NodeStoreError::NotEmpty(store.len()).into());
+        }
 
-        let tree = Self::create_tree_from_padded_leaves(padded_leaves_iter)?;
+        let ((), real_leaf_count) =
+            with_padded_leaves::<T, H, _>(leaves, MAX_LEAF_COUNT, |padded| {
+                Ok(store.put_batch(0, padded)?)
+            })?;
 
-        TreeSize::try_from(tree.len()).expect("Invalid tree size. Invariant broken.");
+        Self::create_tree_from_padded_leaves(&mut store)?;
+
+        TreeSize::try_from(store.len()).expect("Invalid tree size. Invariant broken.");
         let res = Self {
-            tree,
-            _hasher: std::marker::PhantomData,
+            store,
+            real_leaf_count: u32::try_from(real_leaf_count)
+                .ok()
+                .and_then(NonZeroU32::new)
+                .expect("The real leaves are at most the checked padded leaves, and at least one"),
+            _phantom: core::marker::PhantomData,
         };
         Ok(res)
     }
 
+    /// Creates a tree from a store that already has all its nodes, such as a reopened `MmapNodeStore`,
+    /// with the number of real leaves, which the store doesn't record. The nodes aren't hashed again,
+    /// so they're trusted to be those of a tree that was built by `from_leaves_in_store`.
+    pub fn from_store(store: S, real_leaf_count: u32) -> Result<Self, MerkleTreeFormError> {
+        let tree_size = TreeSize::try_from(store.len())
+            .map_err(|_| MerkleTreeFormError::InvalidStoreSize(store.len()))?;
+        let leaf_count = tree_size.leaf_count().get();
+
+        // The padding makes the leaf count the smallest power of two that fits the real leaves
+        let real_leaf_count = NonZeroU32::new(real_leaf_count)
+            .filter(|count| count.get().checked_next_power_of_two() == Some(leaf_count))
+            .ok_or(MerkleTreeFormError::RealLeafCountMismatch(
+                real_leaf_count,
+                leaf_count,
+            ))?;
+
+        Ok(Self {
+            store,
+            real_leaf_count,
+            _phantom: core::marker::PhantomData,
+        })
+    }
+
     /// See MerkleTreeNodeParentIterator for more details.
     pub fn iter_from_leaf_to_root(
         &self,
         start_leaf_index: u32,
-    ) -> Result<MerkleTreeNodeParentIterator<T, H>, MerkleTreeAccessError> {
+    ) -> Result<MerkleTreeNodeParentIterator<'_, T, H, S>, MerkleTreeAccessError> {
         let leaf_count = self.leaf_count().get();
 
         if start_leaf_index >= leaf_count {
@@ -182,13 +306,151 @@ impl<T: Clone, H: PairHasher<NodeType = T>> MerkleTree<T, H> {
     }
 }
 
-pub struct Node<'a, T, H> {
-    tree_ref: &'a MerkleTree<T, H>,
+impl<T: Clone, H: LeafCountCommitter<NodeType = T>, S: NodeStore<T>> MerkleTree<T, H, S> {
+    /// The root of the tree combined with the real leaf count, which, unlike the root,
+    /// tells trees that differ only in padding apart. Proofs can be verified against it
+    /// with `CheckedSingleProofHashes::verify_count_committed` and
+    /// `MultiProofHashes::verify_count_committed`.
+    pub fn count_committed_root(&self) -> T {
+        H::commit_leaf_count(&self.root(), self.real_leaf_count.get())
+    }
+}
+
+impl<T: Clone + PartialEq, H, S: NodeStore<T>> MerkleTree<T, H, S> {
+    /// Finds the leaves that differ between this tree and another tree of the same size, in ascending order.
+    /// Starting from the root, only the subtrees whose roots differ are descended into, so the number of hash
+    /// comparisons is `O(d * log(n))` for `d` differing leaves, rather than `O(n)`.
+    /// Leaves that are padding in both trees are not reported, since they only differ as a consequence of
+    /// differing real leaves.
+    pub fn diff<S2: NodeStore<T>>(
+        &self,
+        other: &MerkleTree<T, H, S2>,
+    ) -> Result<Vec<u32>, MerkleTreeDiffError> {
+        self.diff_counting_comparisons(other)
+            .map(|(leaves, _)| leaves)
+    }
+
+    /// Same as `diff`, but also returns the number of hash comparisons that were made.
+    fn diff_counting_comparisons<S2: NodeStore<T>>(
+        &self,
+        other: &MerkleTree<T, H, S2>,
+    ) -> Result<(Vec<u32>, usize), MerkleTreeDiffError> {
+        if self.leaf_count() != other.leaf_count() {
+            return Err(MerkleTreeDiffError::LeafCountMismatch(
+                self.leaf_count().get(),
+                other.leaf_count().get(),
+            ));
+        }
+
+        let tree_size = self.total_node_count();
+        let root = NodePosition::from_abs_index(tree_size, tree_size.get() - 1)
+            .expect("The root is always in the tree");
+
+        let mut differing_leaves = Vec::new();
+        let mut comparison_count = 0;
+        let mut stack = Vec::from([root]);
+        while let Some(position) = stack.pop() {
+            comparison_count += 1;
+            let abs_index = position.abs_index() as usize;
+            if self.store.get(abs_index) == other.store.get(abs_index) {
+                continue;
+            }
+
+            match position.children() {
+                // The right child is pushed first, so that the leaves are found from left to right
+                Some((left, right)) => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+                None => {
+                    let (_, leaf_index) = position.position();
+                    if !(self.is_padding_leaf(leaf_index) && other.is_padding_leaf(leaf_index)) {
+                        differing_leaves.push(leaf_index);
+                    }
+                }
+            }
+        }
+
+        Ok((differing_leaves, comparison_count))
+    }
+}
+
+/// The largest number of leaves, including padding, that a supported tree can have.
+const MAX_LEAF_COUNT: usize = 1 << 30;
+
+/// Pads the leaves, and passes the padded leaves to `consume`, whose result is returned with the number of real leaves.
+/// Inputs that are known to be larger than `max_leaf_count` are rejected before anything is passed to `consume`.
+/// Otherwise, no more leaves than that are passed, and nothing is padded after them, before the input is rejected.
+fn with_padded_leaves<T: Clone, H: PairHasher<NodeType = T>, R>(
+    leaves: impl IntoIterator<Item = T>,
+    max_leaf_count: usize,
+    consume: impl FnOnce(&mut dyn Iterator<Item = T>) -> Result<R, MerkleTreeFormError>,
+) -> Result<(R, usize), MerkleTreeFormError> {
+    let leaves = leaves.into_iter();
+    let min_leaf_count = leaves.size_hint().0;
+    if min_leaf_count > max_leaf_count {
+        return Err(MerkleTreeFormError::TooLarge(
+            min_leaf_count
+                .checked_next_power_of_two()
+                .unwrap_or(usize::MAX),
+        ));
+    }
+
+    let pad_f = |i: &T| H::hash_single(i);
+
+    let mut real_leaf_count = 0usize;
+    let counted_leaves = leaves
+        .map_while(|leaf| {
+            real_leaf_count += 1;
+            (real_leaf_count <= max_leaf_count).then_some(leaf)
+        })
+        .fuse();
+    let result = consume(&mut IncrementalPaddingIterator::new(counted_leaves, pad_f))?;
+    if real_leaf_count > max_leaf_count {
+        return Err(MerkleTreeFormError::TooLarge(
+            max_leaf_count.saturating_mul(2),
+        ));
+    }
+
+    Ok((result, real_leaf_count))
+}
+
+/// Computes the internal nodes of a tree, from the padded leaves, which are all the nodes in the store.
+fn hash_internal_nodes<T, H: PairHasher<NodeType = T>, S: NodeStore<T>>(
+    store: &mut S,
+) -> Result<(), NodeStoreError> {
+    let steps = store.len() - 1;
+    for i in 0..steps {
+        let el = H::hash_pair(
+            store.get(i * 2).expect("Written before"),
+            store.get(i * 2 + 1).expect("Written before"),
+        );
+        store.put(steps + 1 + i, el)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the given number of (padded) leaves can form a supported tree, i.e., that the tree size
+/// fits in a `TreeSize`. Larger trees are rejected with an error, rather than a panic.
+fn checked_tree_size_for_leaf_count(leaf_count: usize) -> Result<TreeSize, MerkleTreeFormError> {
+    if leaf_count == 0 {
+        return Err(MerkleTreeFormError::TooSmall(leaf_count));
+    }
+
+    u32::try_from(leaf_count)
+        .ok()
+        .and_then(|count| TreeSize::from_leaf_count(count).ok())
+        .ok_or(MerkleTreeFormError::TooLarge(leaf_count))
+}
+
+pub struct Node<'a, T, H, S = Vec<T>> {
+    tree_ref: &'a MerkleTree<T, H, S>,
     absolute_index: u32,
 }
 
-impl<T: Debug, H> Debug for Node<'_, T, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<T: Debug, H, S: NodeStore<T>> Debug for Node<'_, T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Node")
             .field("tree_ref", &self.tree_ref)
             .field("absolute_index", &self.absolute_index)
@@ -196,28 +458,31 @@ impl<T: Debug, H> Debug for Node<'_, T, H> {
     }
 }
 
-impl<T: PartialEq, H> PartialEq for Node<'_, T, H> {
+impl<T: PartialEq, H, S: NodeStore<T>> PartialEq for Node<'_, T, H, S> {
     fn eq(&self, other: &Self) -> bool {
         self.tree_ref == other.tree_ref && self.absolute_index == other.absolute_index
     }
 }
 
-impl<T: Eq, H> Eq for Node<'_, T, H> {}
+impl<T: Eq, H, S: NodeStore<T>> Eq for Node<'_, T, H, S> {}
 
-impl<T, H> Clone for Node<'_, T, H> {
+impl<T, H, S> Clone for Node<'_, T, H, S> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<T, H> Copy for Node<'_, T, H> {}
+impl<T, H, S> Copy for Node<'_, T, H, S> {}
 
-impl<'a, T, H> Node<'a, T, H> {
-    pub fn hash(&self) -> &T {
-        &self.tree_ref.tree[self.absolute_index as usize]
+impl<'a, T, H, S: NodeStore<T>> Node<'a, T, H, S> {
+    pub fn hash(&self) -> &'a T {
+        self.tree_ref
+            .store
+            .get(self.absolute_index as usize)
+            .expect("Should never fail since the index is transitively valid")
     }
 
-    pub fn tree(&self) -> &'a MerkleTree<T, H> {
+    pub fn tree(&self) -> &'a MerkleTree<T, H, S> {
         self.tree_ref
     }
 
@@ -226,7 +491,7 @@ impl<'a, T, H> Node<'a, T, H> {
     }
 }
 
-impl<'a, T: Clone, H: PairHasher<NodeType = T>> Node<'a, T, H> {
+impl<'a, T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> Node<'a, T, H, S> {
     pub fn into_position(self) -> NodePosition {
         NodePosition::from_abs_index(self.tree().total_node_count(), self.absolute_index)
             .expect("Should never fail since the index is transitively valid")
@@ -254,34 +519,88 @@ impl<'a, T: Clone, H: PairHasher<NodeType = T>> Node<'a, T, H> {
     }
 
     pub fn is_root(&self) -> bool {
-        self.absolute_index == self.tree().tree.len() as u32 - 1
+        self.absolute_index == self.tree().store.len() as u32 - 1
     }
 
-    pub fn into_iter_parents(self) -> MerkleTreeNodeParentIterator<'a, T, H> {
+    pub fn into_iter_parents(self) -> MerkleTreeNodeParentIterator<'a, T, H, S> {
         MerkleTreeNodeParentIterator { node: Some(self) }
     }
+
+    fn with_position(&self, position: NodePosition) -> Self {
+        Node {
+            tree_ref: self.tree_ref,
+            absolute_index: position.abs_index(),
+        }
+    }
+
+    /// Returns the left child of the node, or None if it's a leaf.
+    pub fn left_child(&self) -> Option<Self> {
+        let pos = self.into_position().left_child()?;
+        Some(self.with_position(pos))
+    }
+
+    /// Returns the right child of the node, or None if it's a leaf.
+    pub fn right_child(&self) -> Option<Self> {
+        let pos = self.into_position().right_child()?;
+        Some(self.with_position(pos))
+    }
+
+    /// Returns the left and right children of the node, or None if it's a leaf.
+    pub fn children(&self) -> Option<(Self, Self)> {
+        let (left, right) = self.into_position().children()?;
+        Some((self.with_position(left), self.with_position(right)))
+    }
+
+    /// See `NodePosition::leaf_range`.
+    pub fn leaf_range(&self) -> core::ops::Range<u32> {
+        self.into_position().leaf_range()
+    }
+
+    /// See `NodePosition::descendants_at_level`.
+    pub fn descendants_at_level(
+        &self,
+        level_from_bottom: u32,
+    ) -> Option<impl Iterator<Item = Node<'a, T, H, S>>> {
+        let node = *self;
+        let descendants = self
+            .into_position()
+            .descendants_at_level(level_from_bottom)?;
+        Some(descendants.map(move |pos| node.with_position(pos)))
+    }
+
+    /// Returns the lowest node that has both nodes under it (or is one of them).
+    /// Returns None if the nodes belong to different trees.
+    pub fn lowest_common_ancestor(&self, other: &Self) -> Option<Self> {
+        if !core::ptr::eq(self.tree_ref, other.tree_ref) {
+            return None;
+        }
+
+        let pos =
+            NodePosition::lowest_common_ancestor(&self.into_position(), &other.into_position())?;
+        Some(self.with_position(pos))
+    }
 }
 
 /// An iterator that iterates from a leaf node to the root node, vertically.
 #[must_use]
-pub struct MerkleTreeNodeParentIterator<'a, T, H> {
-    node: Option<Node<'a, T, H>>,
+pub struct MerkleTreeNodeParentIterator<'a, T, H, S = Vec<T>> {
+    node: Option<Node<'a, T, H, S>>,
 }
 
-impl<T: Debug, H> Debug for MerkleTreeNodeParentIterator<'_, T, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<T: Debug, H, S: NodeStore<T>> Debug for MerkleTreeNodeParentIterator<'_, T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("MerkleTreeNodeParentIterator")
             .field("node", &self.node)
             .finish()
     }
 }
 
-impl<'a, T: Clone, H: PairHasher<NodeType = T>> Iterator
-    for MerkleTreeNodeParentIterator<'a, T, H>
+impl<'a, T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> Iterator
+    for MerkleTreeNodeParentIterator<'a, T, H, S>
 {
-    type Item = Node<'a, T, H>;
+    type Item = Node<'a, T, H, S>;
 
-    fn next(&mut self) -> Option<Node<'a, T, H>> {
+    fn next(&mut self) -> Option<Node<'a, T, H, S>> {
         match self.node {
             None => None,
             Some(_) => {
@@ -13,7 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::iter::FusedIterator;
+use core::iter::FusedIterator;
 
 /// An iterator that pads the leaves of a Merkle tree with incremental padding,
 /// i.e. the padding function is applied to the last value of the iterator,
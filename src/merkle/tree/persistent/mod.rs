@@ -0,0 +1,442 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent (immutable) trees, where updating leaves creates a new version of the tree, which shares
+//! the unchanged subtrees with the previous version instead of copying them. Updating `k` leaves of a tree
+//! creates `O(k * log(n))` new nodes, so keeping many versions of a tree whose leaves change a little
+//! between versions is cheap. See `PersistentTreeVersions` for keeping and dropping versions.
+
+pub mod versions;
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::{fmt::Debug, num::NonZeroU32};
+
+use itertools::Itertools;
+
+use crate::merkle::{
+    hasher::PairHasher,
+    proof::{multi::MultiProofHashes, single::SingleProofHashes},
+    MerkleTreeFormError, MerkleTreeProofExtractionError, MerkleTreeUpdateError,
+};
+
+use super::{
+    checked_tree_size_for_leaf_count, store::NodeStore, tree_size::TreeSize, with_padded_leaves,
+    MerkleTree, MAX_LEAF_COUNT,
+};
+
+/// A node of a persistent tree, which may be shared between many versions of the tree.
+enum PersistentNode<T> {
+    Leaf(T),
+    Branch {
+        hash: T,
+        left: Arc<PersistentNode<T>>,
+        right: Arc<PersistentNode<T>>,
+    },
+}
+
+impl<T> PersistentNode<T> {
+    fn hash(&self) -> &T {
+        match self {
+            PersistentNode::Leaf(hash) => hash,
+            PersistentNode::Branch { hash, .. } => hash,
+        }
+    }
+
+    fn children(&self) -> Option<(&Arc<Self>, &Arc<Self>)> {
+        match self {
+            PersistentNode::Leaf(_) => None,
+            PersistentNode::Branch { left, right, .. } => Some((left, right)),
+        }
+    }
+}
+
+/// A version of a merkle tree, stored as linked nodes, rather than in a `NodeStore`, so that versions can share nodes.
+/// It has the same padding, root and proofs as `MerkleTree` for the same leaves. Cloning it is cheap,
+/// as only the reference to the root is cloned.
+#[must_use]
+pub struct PersistentMerkleTree<T, H> {
+    root: Arc<PersistentNode<T>>,
+    tree_size: TreeSize,
+    real_leaf_count: NonZeroU32,
+    _hasher: core::marker::PhantomData<H>,
+}
+
+impl<T, H> Clone for PersistentMerkleTree<T, H> {
+    fn clone(&self) -> Self {
+        Self {
+            root: Arc::clone(&self.root),
+            tree_size: self.tree_size,
+            real_leaf_count: self.real_leaf_count,
+            _hasher: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Debug, H> Debug for PersistentMerkleTree<T, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PersistentMerkleTree")
+            .field("root", self.root.hash())
+            .field("tree_size", &self.tree_size)
+            .field("real_leaf_count", &self.real_leaf_count)
+            .finish()
+    }
+}
+
+impl<T: Clone, H> PersistentMerkleTree<T, H> {
+    pub fn root(&self) -> T {
+        self.root.hash().clone()
+    }
+
+    pub fn total_node_count(&self) -> TreeSize {
+        self.tree_size
+    }
+
+    pub fn leaf_count(&self) -> NonZeroU32 {
+        self.tree_size.leaf_count()
+    }
+
+    /// The number of leaves that the tree was created from, before padding.
+    pub fn real_leaf_count(&self) -> NonZeroU32 {
+        self.real_leaf_count
+    }
+
+    /// See `MerkleTree::is_padding_leaf`.
+    pub fn is_padding_leaf(&self, leaf_index: u32) -> bool {
+        leaf_index >= self.real_leaf_count.get() && leaf_index < self.leaf_count().get()
+    }
+
+    pub fn level_count(&self) -> NonZeroU32 {
+        self.tree_size.level_count()
+    }
+
+    /// Finds the node at the given position by descending from the root, following the bits of the index.
+    fn node_at(&self, level_from_bottom: u32, index_in_level: u32) -> Option<&PersistentNode<T>> {
+        let level_count = self.level_count().get();
+        if level_from_bottom >= level_count
+            || index_in_level >= self.leaf_count().get() >> level_from_bottom
+        {
+            return None;
+        }
+
+        let depth = level_count - 1 - level_from_bottom;
+        let node = (0..depth).rev().fold(&*self.root, |node, bit| {
+            let (left, right) = node.children().expect("Only leaves have no children");
+            match (index_in_level >> bit) & 1 {
+                0 => left,
+                _ => right,
+            }
+        });
+        Some(node)
+    }
+
+    pub fn node_value_from_bottom(&self, level_from_bottom: u32, index_in_level: u32) -> Option<T> {
+        self.node_at(level_from_bottom, index_in_level)
+            .map(|node| node.hash().clone())
+    }
+
+    /// The leaves of the tree, including the padding leaves, from left to right.
+    pub fn leaves(&self) -> Vec<T> {
+        let mut leaves = Vec::with_capacity(self.leaf_count().get() as usize);
+        let mut stack = Vec::from([&*self.root]);
+        while let Some(node) = stack.pop() {
+            match node.children() {
+                Some((left, right)) => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+                None => leaves.push(node.hash().clone()),
+            }
+        }
+        leaves
+    }
+
+    fn check_real_leaf_index(&self, leaf_index: u32) -> Result<(), MerkleTreeProofExtractionError> {
+        if leaf_index >= self.leaf_count().get() {
+            return Err(MerkleTreeProofExtractionError::LeafIndexOutOfRange(
+                leaf_index,
+                self.leaf_count().get(),
+            ));
+        }
+        if self.is_padding_leaf(leaf_index) {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf(
+                leaf_index,
+                self.real_leaf_count.get(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Eq + Clone, H: PairHasher<NodeType = T>> PersistentMerkleTree<T, H> {
+    /// Creates a proof for a leaf by its index in the lowest level, the same proof that `SingleProofNodes::from_tree_leaf`
+    /// creates for a `MerkleTree` with the same leaves. Padding leaves are refused.
+    pub fn single_proof(
+        &self,
+        leaf_index: u32,
+    ) -> Result<SingleProofHashes<T, H>, MerkleTreeProofExtractionError> {
+        self.check_real_leaf_index(leaf_index)?;
+
+        // The siblings are collected from the top down, while the branch goes from the bottom up
+        let depth = self.level_count().get() - 1;
+        let mut branch = Vec::with_capacity(depth as usize);
+        let mut node = &*self.root;
+        for bit in (0..depth).rev() {
+            let (left, right) = node.children().expect("Only leaves have no children");
+            let (next, sibling) = match (leaf_index >> bit) & 1 {
+                0 => (left, right),
+                _ => (right, left),
+            };
+            branch.push(sibling.hash().clone());
+            node = next;
+        }
+        branch.reverse();
+
+        Ok(SingleProofHashes::from_leaf_index_and_branch(
+            leaf_index, branch,
+        ))
+    }
+
+    /// Creates a proof for the leaves with the given indices, which must be sorted and unique, the same proof that
+    /// `MultiProofNodes::from_tree_leaves` creates for a `MerkleTree` with the same leaves. Padding leaves are refused.
+    pub fn multi_proof(
+        &self,
+        leaves_indices: &[u32],
+    ) -> Result<MultiProofHashes<T, H>, MerkleTreeProofExtractionError> {
+        if leaves_indices.is_empty() {
+            return Err(MerkleTreeProofExtractionError::NoLeavesToCreateProof);
+        }
+        if !leaves_indices.iter().tuple_windows().all(|(i, j)| i < j) {
+            return Err(
+                MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices(
+                    leaves_indices.to_vec(),
+                ),
+            );
+        }
+        if leaves_indices
+            .iter()
+            .any(|index| *index >= self.leaf_count().get())
+        {
+            return Err(MerkleTreeProofExtractionError::IndexOutOfRange(
+                leaves_indices.to_vec(),
+                self.leaf_count().get(),
+            ));
+        }
+
+        if let Some(padding_leaf_index) = leaves_indices
+            .iter()
+            .find(|index| self.is_padding_leaf(**index))
+        {
+            return Err(MerkleTreeProofExtractionError::PaddingLeaf(
+                *padding_leaf_index,
+                self.real_leaf_count.get(),
+            ));
+        }
+
+        // The frontier is the list of nodes, in the current level, that have proven leaves under them, with their
+        // index in the level and those leaves. Going down level by level, a child without proven leaves under it
+        // is a proof node, so every proof node is visited once, as in `MultiProofNodes::from_tree_leaves`.
+        let mut frontier = Vec::from([(&*self.root, 0u32, leaves_indices)]);
+        let mut nodes = BTreeMap::new();
+
+        for level in (1..self.level_count().get()).rev() {
+            let child_level_start = self
+                .tree_size
+                .level_start(level - 1)
+                .expect("Level is in range");
+            let mut next_frontier = Vec::with_capacity(frontier.len() * 2);
+
+            for (node, index, indices) in frontier {
+                let (left, right) = node.children().expect("Only leaves have no children");
+                let middle_leaf_index = (2 * index + 1) << (level - 1);
+                let (left_indices, right_indices) =
+                    indices.split_at(indices.partition_point(|i| *i < middle_leaf_index));
+
+                for (child, child_index, child_indices) in [
+                    (left, 2 * index, left_indices),
+                    (right, 2 * index + 1, right_indices),
+                ] {
+                    if child_indices.is_empty() {
+                        nodes.insert(child_level_start + child_index, child.hash().clone());
+                    } else {
+                        next_frontier.push((&**child, child_index, child_indices));
+                    }
+                }
+            }
+
+            frontier = next_frontier;
+        }
+
+        Ok(MultiProofHashes::from_leaf_count_and_nodes(
+            self.leaf_count().get(),
+            nodes,
+        ))
+    }
+}
+
+impl<T: Clone, H: PairHasher<NodeType = T>> PersistentMerkleTree<T, H> {
+    fn branch(
+        left: Arc<PersistentNode<T>>,
+        right: Arc<PersistentNode<T>>,
+    ) -> Arc<PersistentNode<T>> {
+        Arc::new(PersistentNode::Branch {
+            hash: H::hash_pair(left.hash(), right.hash()),
+            left,
+            right,
+        })
+    }
+
+    /// Create a new tree from a list of leaves, with the same incremental padding as `MerkleTree::from_leaves`.
+    /// Larger inputs than a tree can have are rejected, like in `MerkleTree::from_leaves_in_store`.
+    pub fn from_leaves(leaves: impl IntoIterator<Item = T>) -> Result<Self, MerkleTreeFormError> {
+        let (mut level, real_leaf_count) =
+            with_padded_leaves::<T, H, _>(leaves, MAX_LEAF_COUNT, |padded| {
+                Ok(padded
+                    .map(|leaf| Arc::new(PersistentNode::Leaf(leaf)))
+                    .collect::<Vec<_>>())
+            })?;
+
+        let tree_size = checked_tree_size_for_leaf_count(level.len())?;
+
+        while level.len() > 1 {
+            level = level
+                .into_iter()
+                .tuples()
+                .map(|(left, right)| Self::branch(left, right))
+                .collect();
+        }
+
+        Ok(Self {
+            root: level.pop().expect("Checked to be non-empty"),
+            tree_size,
+            real_leaf_count: u32::try_from(real_leaf_count)
+                .ok()
+                .and_then(NonZeroU32::new)
+                .expect("The real leaves are at most the checked padded leaves, and at least one"),
+            _hasher: core::marker::PhantomData,
+        })
+    }
+
+    /// Creates a new version of the tree, with the leaf at the given index replaced. See `update_leaves`.
+    pub fn update_leaf(&self, leaf_index: u32, leaf: T) -> Result<Self, MerkleTreeUpdateError> {
+        self.update_leaves(core::iter::once((leaf_index, leaf)))
+    }
+
+    /// Creates a new version of the tree, with the leaves at the given indices replaced, which shares
+    /// all the subtrees without updated leaves with this version. If an index is repeated, the last leaf wins.
+    /// Padding leaves can't be updated; they are recomputed when the last real leaf is updated.
+    pub fn update_leaves(
+        &self,
+        updates: impl IntoIterator<Item = (u32, T)>,
+    ) -> Result<Self, MerkleTreeUpdateError> {
+        let leaf_count = self.leaf_count().get();
+        let real_leaf_count = self.real_leaf_count.get();
+
+        let mut updates = updates.into_iter().collect::<BTreeMap<_, _>>();
+        for leaf_index in updates.keys() {
+            if *leaf_index >= leaf_count {
+                return Err(MerkleTreeUpdateError::LeafIndexOutOfRange(
+                    *leaf_index,
+                    leaf_count,
+                ));
+            }
+            if *leaf_index >= real_leaf_count {
+                return Err(MerkleTreeUpdateError::PaddingLeaf(
+                    *leaf_index,
+                    real_leaf_count,
+                ));
+            }
+        }
+
+        // The padding is derived from the last real leaf, so it changes with it
+        if let Some(last_leaf) = updates.get(&(real_leaf_count - 1)).cloned() {
+            let padding = (real_leaf_count..leaf_count).scan(last_leaf, |last, index| {
+                *last = H::hash_single(last);
+                Some((index, last.clone()))
+            });
+            updates.extend(padding.collect::<Vec<_>>());
+        }
+
+        let updates = updates.into_iter().collect::<Vec<_>>();
+        Ok(Self {
+            root: Self::updated_node(&self.root, 0, self.level_count().get() - 1, &updates),
+            tree_size: self.tree_size,
+            real_leaf_count: self.real_leaf_count,
+            _hasher: core::marker::PhantomData,
+        })
+    }
+
+    /// Returns the node with the given updates applied, where the node is at the given level, and its leftmost leaf
+    /// has the given index. The updates are sorted by leaf index, and are all under the node.
+    fn updated_node(
+        node: &Arc<PersistentNode<T>>,
+        first_leaf_index: u32,
+        level: u32,
+        updates: &[(u32, T)],
+    ) -> Arc<PersistentNode<T>> {
+        let (left, right) = match (updates, node.children()) {
+            ([], _) => return Arc::clone(node),
+            ([(_, leaf)], None) => return Arc::new(PersistentNode::Leaf(leaf.clone())),
+            (_, Some(children)) => children,
+            (_, None) => unreachable!("Updates of a leaf are deduplicated"),
+        };
+
+        let middle_leaf_index = first_leaf_index + (1 << (level - 1));
+        let (left_updates, right_updates) =
+            updates.split_at(updates.partition_point(|(index, _)| *index < middle_leaf_index));
+
+        Self::branch(
+            Self::updated_node(left, first_leaf_index, level - 1, left_updates),
+            Self::updated_node(right, middle_leaf_index, level - 1, right_updates),
+        )
+    }
+}
+
+/// Converts a tree, without hashing, by reusing the nodes of every level.
+impl<T: Clone, H, S: NodeStore<T>> From<&MerkleTree<T, H, S>> for PersistentMerkleTree<T, H> {
+    fn from(tree: &MerkleTree<T, H, S>) -> Self {
+        let mut levels = tree.levels();
+        let mut level = levels
+            .next()
+            .expect("There's always a level of leaves")
+            .iter()
+            .map(|leaf| Arc::new(PersistentNode::Leaf(leaf.clone())))
+            .collect::<Vec<_>>();
+
+        for hashes in levels {
+            level = level
+                .into_iter()
+                .tuples()
+                .zip(hashes)
+                .map(|((left, right), hash)| {
+                    Arc::new(PersistentNode::Branch {
+                        hash: hash.clone(),
+                        left,
+                        right,
+                    })
+                })
+                .collect();
+        }
+
+        Self {
+            root: level.pop().expect("There's always a root"),
+            tree_size: tree.total_node_count(),
+            real_leaf_count: tree.real_leaf_count(),
+            _hasher: core::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,318 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::num::NonZeroUsize;
+
+use rand::Rng;
+use rstest::rstest;
+
+use crate::{
+    internal::{hash_data, HashAlgo, HashedData},
+    proof::{multi::MultiProofNodes, single::SingleProofNodes},
+    rand_tools::{make_seedable_rng, Seed},
+};
+
+use super::{versions::PersistentTreeVersions, *};
+
+fn gen_leaves(n: u32) -> Vec<HashedData> {
+    (0..n)
+        .map(|i| hash_data(HashedData::from_low_u64_be(i as u64)))
+        .collect()
+}
+
+fn assert_same_tree(
+    persistent: &PersistentMerkleTree<HashedData, HashAlgo>,
+    tree: &MerkleTree<HashedData, HashAlgo>,
+) {
+    assert_eq!(persistent.root(), tree.root());
+    assert_eq!(persistent.total_node_count(), tree.total_node_count());
+    assert_eq!(persistent.real_leaf_count(), tree.real_leaf_count());
+    assert_eq!(persistent.leaves(), tree.leaves());
+    for (level, nodes) in tree.levels().enumerate() {
+        for (index, node) in nodes.iter().enumerate() {
+            assert_eq!(
+                persistent.node_value_from_bottom(level as u32, index as u32),
+                Some(*node)
+            );
+        }
+        assert_eq!(
+            persistent.node_value_from_bottom(level as u32, nodes.len() as u32),
+            None
+        );
+    }
+    assert_eq!(
+        persistent.node_value_from_bottom(tree.level_count().get(), 0),
+        None
+    );
+
+    for leaf_index in 0..tree.real_leaf_count().get() {
+        let proof = persistent.single_proof(leaf_index).unwrap();
+        let expected = SingleProofNodes::from_tree_leaf(tree, leaf_index)
+            .unwrap()
+            .into_values();
+        assert_eq!(proof.leaf_index_in_level(), expected.leaf_index_in_level());
+        assert_eq!(proof.branch(), expected.branch());
+    }
+}
+
+#[test]
+fn same_as_tree() {
+    assert_eq!(
+        PersistentMerkleTree::<HashedData, HashAlgo>::from_leaves([]).err(),
+        Some(MerkleTreeFormError::TooSmall(0))
+    );
+
+    for leaf_count in 1..=33 {
+        let leaves = gen_leaves(leaf_count);
+        let tree = MerkleTree::from_leaves(leaves.clone()).unwrap();
+
+        assert_same_tree(&PersistentMerkleTree::from_leaves(leaves).unwrap(), &tree);
+        assert_same_tree(&PersistentMerkleTree::from(&tree), &tree);
+    }
+}
+
+#[test]
+fn too_many_leaves_rejected_before_collecting() {
+    for leaf_count in [MAX_LEAF_COUNT + 1, 1 << 31, usize::MAX] {
+        let leaves = core::iter::repeat(HashedData::zero()).take(leaf_count);
+        assert_eq!(
+            PersistentMerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).err(),
+            Some(MerkleTreeFormError::TooLarge(
+                leaf_count.checked_next_power_of_two().unwrap_or(usize::MAX)
+            ))
+        );
+    }
+}
+
+#[test]
+fn proof_errors() {
+    let t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(gen_leaves(5)).unwrap();
+
+    assert_eq!(
+        t.single_proof(5).err(),
+        Some(MerkleTreeProofExtractionError::PaddingLeaf(5, 5))
+    );
+    assert_eq!(
+        t.single_proof(8).err(),
+        Some(MerkleTreeProofExtractionError::LeafIndexOutOfRange(8, 8))
+    );
+    assert_eq!(
+        t.multi_proof(&[]).err(),
+        Some(MerkleTreeProofExtractionError::NoLeavesToCreateProof)
+    );
+    assert_eq!(
+        t.multi_proof(&[2, 1]).err(),
+        Some(MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices(vec![2, 1]))
+    );
+    assert_eq!(
+        t.multi_proof(&[1, 1]).err(),
+        Some(MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices(vec![1, 1]))
+    );
+    assert_eq!(
+        t.multi_proof(&[1, 8]).err(),
+        Some(MerkleTreeProofExtractionError::IndexOutOfRange(
+            vec![1, 8],
+            8
+        ))
+    );
+    assert_eq!(
+        t.multi_proof(&[1, 6]).err(),
+        Some(MerkleTreeProofExtractionError::PaddingLeaf(6, 5))
+    );
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn multi_proof_same_as_tree(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for leaf_count in 1..=20 {
+        let leaves = gen_leaves(leaf_count);
+        let tree = MerkleTree::<_, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+        let t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(leaves).unwrap();
+
+        // Random leaves, and all the real leaves, which need no proof nodes
+        let all_indices = (0..leaf_count).collect::<Vec<_>>();
+        for indices in (0..10)
+            .map(|_| {
+                (0..leaf_count)
+                    .filter(|_| rng.gen_bool(0.3))
+                    .collect::<Vec<_>>()
+            })
+            .chain([all_indices])
+        {
+            if indices.is_empty() {
+                continue;
+            }
+
+            let proof = t.multi_proof(&indices).unwrap();
+            let expected = MultiProofNodes::from_tree_leaves(&tree, &indices)
+                .unwrap()
+                .into_values();
+            assert_eq!(proof.nodes(), expected.nodes());
+            assert_eq!(proof.tree_leaf_count(), expected.tree_leaf_count());
+        }
+    }
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn updates_same_as_new_tree(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for leaf_count in 1..=20 {
+        let mut leaves = gen_leaves(leaf_count);
+        let mut t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+        for _ in 0..10 {
+            let old_t = t.clone();
+            let old_leaves = leaves.clone();
+
+            let updates = (0..rng.gen_range(1..=3))
+                .map(|_| {
+                    let index = rng.gen_range(0..leaf_count);
+                    (index, hash_data(HashedData::from_low_u64_be(rng.gen())))
+                })
+                .collect::<Vec<_>>();
+            for (index, leaf) in &updates {
+                leaves[*index as usize] = *leaf;
+            }
+
+            t = t.update_leaves(updates).unwrap();
+            assert_same_tree(&t, &MerkleTree::from_leaves(leaves.clone()).unwrap());
+
+            // The old version is unchanged
+            assert_same_tree(&old_t, &MerkleTree::from_leaves(old_leaves).unwrap());
+        }
+    }
+}
+
+#[test]
+fn update_last_leaf_updates_padding() {
+    let mut leaves = gen_leaves(5);
+    let t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    leaves[4] = HashedData::zero();
+    let t = t.update_leaf(4, HashedData::zero()).unwrap();
+    assert_same_tree(&t, &MerkleTree::from_leaves(leaves).unwrap());
+}
+
+#[test]
+fn update_errors() {
+    let t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(gen_leaves(5)).unwrap();
+
+    assert_eq!(
+        t.update_leaf(5, HashedData::zero()).err(),
+        Some(MerkleTreeUpdateError::PaddingLeaf(5, 5))
+    );
+    assert_eq!(
+        t.update_leaves([(0, HashedData::zero()), (8, HashedData::zero())])
+            .err(),
+        Some(MerkleTreeUpdateError::LeafIndexOutOfRange(8, 8))
+    );
+
+    // Repeated indices: the last leaf wins
+    let updated = t
+        .update_leaves([(1, HashedData::zero()), (1, HashedData::repeat_byte(1))])
+        .unwrap();
+    assert_eq!(
+        updated.node_value_from_bottom(0, 1),
+        Some(HashedData::repeat_byte(1))
+    );
+}
+
+#[test]
+fn updates_share_unchanged_subtrees() {
+    let t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(gen_leaves(16)).unwrap();
+    let updated = t.update_leaf(5, HashedData::zero()).unwrap();
+
+    let (old_left, old_right) = t.root.children().unwrap();
+    let (new_left, new_right) = updated.root.children().unwrap();
+    assert!(Arc::ptr_eq(old_right, new_right));
+    assert!(!Arc::ptr_eq(old_left, new_left));
+
+    // Only the nodes on the path from the updated leaf to the root are new
+    let old_nodes = [old_left, old_right]
+        .into_iter()
+        .flat_map(|node| node.children().map(|(l, r)| [l, r]).unwrap());
+    let new_nodes = [new_left, new_right]
+        .into_iter()
+        .flat_map(|node| node.children().map(|(l, r)| [l, r]).unwrap());
+    let shared = old_nodes
+        .zip(new_nodes)
+        .map(|(old, new)| Arc::ptr_eq(old, new))
+        .collect::<Vec<_>>();
+    assert_eq!(shared, [true, false, true, true]);
+}
+
+#[test]
+fn versions() {
+    let t = PersistentMerkleTree::<_, HashAlgo>::from_leaves(gen_leaves(8)).unwrap();
+    let mut versions = PersistentTreeVersions::new(t.clone());
+    assert_eq!(versions.first_version(), 0);
+    assert_eq!(versions.latest_version(), 0);
+    assert_eq!(versions.latest().root(), t.root());
+
+    for i in 1..=5u8 {
+        let version = versions
+            .update_leaves([(i as u32, HashedData::repeat_byte(i))])
+            .unwrap();
+        assert_eq!(version, i as u64);
+    }
+    assert_eq!(versions.version_count().get(), 6);
+    assert_eq!(versions.get(0).unwrap().root(), t.root());
+    assert_eq!(
+        versions.get(3).unwrap().node_value_from_bottom(0, 3),
+        Some(HashedData::repeat_byte(3))
+    );
+    assert_eq!(
+        versions.get(3).unwrap().node_value_from_bottom(0, 4),
+        t.node_value_from_bottom(0, 4)
+    );
+    assert!(versions.get(6).is_none());
+    assert_eq!(
+        versions.update_leaves([(8, HashedData::zero())]).err(),
+        Some(MerkleTreeUpdateError::LeafIndexOutOfRange(8, 8))
+    );
+    assert_eq!(versions.latest_version(), 5);
+
+    // The root of version 0 is shared with the local clone only, after dropping it from the versions
+    assert_eq!(Arc::strong_count(&t.root), 2);
+    assert_eq!(versions.drop_versions_before(2), 2);
+    assert_eq!(Arc::strong_count(&t.root), 1);
+    assert_eq!(versions.first_version(), 2);
+    assert!(versions.get(1).is_none());
+    assert!(versions.get(2).is_some());
+    assert_eq!(versions.drop_versions_before(1), 0);
+
+    assert_eq!(versions.retain_last(NonZeroUsize::new(3).unwrap()), 1);
+    assert_eq!(versions.first_version(), 3);
+    assert_eq!(versions.retain_last(NonZeroUsize::new(10).unwrap()), 0);
+
+    assert_eq!(versions.rollback_to(2), None);
+    assert_eq!(versions.rollback_to(4), Some(1));
+    assert_eq!(versions.latest_version(), 4);
+    let version = versions.push(t.clone());
+    assert_eq!(version, 5);
+    assert_eq!(versions.latest().root(), t.root());
+
+    // The latest version is never dropped
+    assert_eq!(versions.drop_versions_before(100), 2);
+    assert_eq!(versions.first_version(), 5);
+    assert_eq!(versions.version_count().get(), 1);
+    assert_eq!(versions.rollback_to(5), Some(0));
+}
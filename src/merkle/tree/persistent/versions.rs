@@ -0,0 +1,131 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::collections::VecDeque;
+use core::{fmt::Debug, num::NonZeroUsize};
+
+use crate::merkle::{hasher::PairHasher, MerkleTreeUpdateError};
+
+use super::PersistentMerkleTree;
+
+/// Consecutive versions of a persistent tree, numbered from 0, where every version is usually created by updating
+/// the leaves of the latest one. Unchanged subtrees are shared between the versions, and the nodes of a dropped
+/// version are freed, unless other versions still share them. There's always at least one version.
+#[must_use]
+pub struct PersistentTreeVersions<T, H> {
+    /// The kept versions, where the first one has the number `first_version`, and the next ones follow it
+    versions: VecDeque<PersistentMerkleTree<T, H>>,
+    first_version: u64,
+}
+
+impl<T, H> Clone for PersistentTreeVersions<T, H> {
+    fn clone(&self) -> Self {
+        Self {
+            versions: self.versions.clone(),
+            first_version: self.first_version,
+        }
+    }
+}
+
+impl<T: Debug, H> Debug for PersistentTreeVersions<T, H> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PersistentTreeVersions")
+            .field("versions", &self.versions)
+            .field("first_version", &self.first_version)
+            .finish()
+    }
+}
+
+impl<T, H> PersistentTreeVersions<T, H> {
+    /// Starts with the given tree as version 0.
+    pub fn new(tree: PersistentMerkleTree<T, H>) -> Self {
+        Self {
+            versions: VecDeque::from([tree]),
+            first_version: 0,
+        }
+    }
+
+    /// The number of the oldest kept version.
+    pub fn first_version(&self) -> u64 {
+        self.first_version
+    }
+
+    /// The number of the latest version.
+    pub fn latest_version(&self) -> u64 {
+        self.first_version + self.versions.len() as u64 - 1
+    }
+
+    /// The number of kept versions, which is never zero.
+    pub fn version_count(&self) -> NonZeroUsize {
+        NonZeroUsize::new(self.versions.len()).expect("There's always a version")
+    }
+
+    pub fn latest(&self) -> &PersistentMerkleTree<T, H> {
+        self.versions.back().expect("There's always a version")
+    }
+
+    /// The tree of the given version, or None if it was dropped or not created yet.
+    pub fn get(&self, version: u64) -> Option<&PersistentMerkleTree<T, H>> {
+        let offset = version.checked_sub(self.first_version)?;
+        self.versions.get(usize::try_from(offset).ok()?)
+    }
+
+    /// Adds the given tree as the next version, and returns its number.
+    pub fn push(&mut self, tree: PersistentMerkleTree<T, H>) -> u64 {
+        self.versions.push_back(tree);
+        self.latest_version()
+    }
+
+    /// Drops the versions older than the given one, but never the latest version.
+    /// Returns the number of dropped versions.
+    pub fn drop_versions_before(&mut self, version: u64) -> usize {
+        let count = version
+            .min(self.latest_version())
+            .saturating_sub(self.first_version);
+        let count = usize::try_from(count).expect("At most the number of versions");
+        self.versions.drain(..count);
+        self.first_version += count as u64;
+        count
+    }
+
+    /// Keeps only the given number of the latest versions. Returns the number of dropped versions.
+    pub fn retain_last(&mut self, count: NonZeroUsize) -> usize {
+        let dropped_count = self.versions.len().saturating_sub(count.get());
+        self.drop_versions_before(self.first_version + dropped_count as u64)
+    }
+
+    /// Drops the versions newer than the given one, which becomes the latest, e.g., to undo the latest blocks in a reorg.
+    /// The numbers of the dropped versions are reused by the next pushed versions.
+    /// Returns the number of dropped versions, or None if the given version isn't kept, in which case nothing is dropped.
+    pub fn rollback_to(&mut self, version: u64) -> Option<usize> {
+        self.get(version)?;
+        let dropped_count = usize::try_from(self.latest_version() - version)
+            .expect("At most the number of versions");
+        self.versions.truncate(self.versions.len() - dropped_count);
+        Some(dropped_count)
+    }
+}
+
+impl<T: Clone, H: PairHasher<NodeType = T>> PersistentTreeVersions<T, H> {
+    /// Adds a new version, which is the latest version with the given leaves updated, and returns its number.
+    /// See `PersistentMerkleTree::update_leaves`.
+    pub fn update_leaves(
+        &mut self,
+        updates: impl IntoIterator<Item = (u32, T)>,
+    ) -> Result<u64, MerkleTreeUpdateError> {
+        let tree = self.latest().update_leaves(updates)?;
+        Ok(self.push(tree))
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs::File, ops::Range};
+
+use memmap2::MmapMut;
+
+use crate::merkle::{tree::tree_size::TreeSize, NodeStoreError};
+
+use super::NodeStore;
+
+/// The minimum number of nodes the file grows to, when it's first written to.
+const MIN_CAPACITY: usize = 64;
+
+/// A node store in a memory-mapped file, for nodes of a fixed size of `N` bytes, such as `Blake2bNode`.
+/// The nodes are stored one after the other by absolute index, and the file grows as nodes are written.
+/// The file may be larger than the stored nodes, as it grows in steps, until it's shrunk by `into_file`.
+#[derive(Debug)]
+pub struct MmapNodeStore<const N: usize> {
+    file: File,
+    mmap: Option<MmapMut>,
+    len: usize,
+}
+
+impl<const N: usize> MmapNodeStore<N> {
+    /// Creates an empty store in the given file, which must be opened for reading and writing.
+    /// The file is truncated.
+    pub fn create(file: File) -> Result<Self, NodeStoreError> {
+        file.set_len(0)?;
+        Ok(Self {
+            file,
+            mmap: None,
+            len: 0,
+        })
+    }
+
+    /// Opens a store in a file that has the nodes of a whole tree, such as one returned by `into_file`.
+    /// The file must be opened for reading and writing, and its length must be the size of a supported tree.
+    pub fn open(file: File) -> Result<Self, NodeStoreError> {
+        let file_length = file.metadata()?.len();
+        let invalid_length = || NodeStoreError::InvalidFileLength(file_length, N);
+        let node_count = usize::try_from(file_length)
+            .ok()
+            .filter(|length| N != 0 && length % N == 0)
+            .map(|length| length / N)
+            .ok_or_else(invalid_length)?;
+        TreeSize::try_from(node_count).map_err(|_| invalid_length())?;
+
+        // SAFETY: See `reserve`
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Self {
+            file,
+            mmap: Some(mmap),
+            len: node_count,
+        })
+    }
+
+    /// Flushes the written nodes, shrinks the file to them, and returns it, so that it can be reopened with `open`.
+    pub fn into_file(mut self) -> Result<File, NodeStoreError> {
+        self.flush()?;
+        // The map is dropped before the file is resized
+        self.mmap = None;
+        let byte_count = u64::try_from(self.len * N).expect("The nodes fit in the file");
+        self.file.set_len(byte_count)?;
+        Ok(self.file)
+    }
+
+    /// The number of nodes that fit in the file without growing it.
+    pub fn capacity(&self) -> usize {
+        self.mmap.as_ref().map_or(0, |mmap| mmap.len() / N)
+    }
+
+    /// Flushes the written nodes to the file.
+    pub fn flush(&self) -> Result<(), NodeStoreError> {
+        if let Some(mmap) = &self.mmap {
+            mmap.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Grows the file, so that at least `node_count` nodes fit in it.
+    fn reserve(&mut self, node_count: usize) -> Result<(), NodeStoreError> {
+        if node_count <= self.capacity() {
+            return Ok(());
+        }
+
+        let capacity = node_count.max(self.capacity() * 2).max(MIN_CAPACITY);
+        let byte_count = capacity
+            .checked_mul(N)
+            .and_then(|bytes| u64::try_from(bytes).ok())
+            .ok_or(NodeStoreError::Io(std::io::ErrorKind::OutOfMemory))?;
+
+        // The old map is dropped before the file is resized
+        self.mmap = None;
+        self.file.set_len(byte_count)?;
+        // SAFETY: The file is owned by the store, so it's not modified by anything else through this process.
+        // Modifying the file externally while it's mapped is not supported, as with any memory-mapped file.
+        self.mmap = Some(unsafe { MmapMut::map_mut(&self.file)? });
+        Ok(())
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.mmap.as_ref().map_or(&[], |mmap| &mmap[..self.len * N])
+    }
+}
+
+impl<const N: usize> NodeStore<[u8; N]> for MmapNodeStore<N> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get_range(&self, range: Range<usize>) -> Option<&[[u8; N]]> {
+        if range.start > range.end || range.end > self.len {
+            return None;
+        }
+
+        let bytes = &self.bytes()[range.start * N..range.end * N];
+        // SAFETY: `[u8; N]` has the size of N bytes and an alignment of 1, so any N * k bytes are valid as k nodes,
+        // and the returned slice borrows the map, so it can't be unmapped or written while it's alive.
+        let nodes =
+            unsafe { core::slice::from_raw_parts(bytes.as_ptr().cast::<[u8; N]>(), range.len()) };
+        Some(nodes)
+    }
+
+    fn put(&mut self, abs_index: usize, node: [u8; N]) -> Result<(), NodeStoreError> {
+        if abs_index > self.len {
+            return Err(NodeStoreError::NonContiguousWrite(abs_index, self.len));
+        }
+
+        self.reserve(abs_index + 1)?;
+        let mmap = self.mmap.as_mut().expect("Reserved above");
+        mmap[abs_index * N..(abs_index + 1) * N].copy_from_slice(&node);
+        self.len = self.len.max(abs_index + 1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mmap_store() {
+        let mut store = MmapNodeStore::<4>::create(tempfile::tempfile().unwrap()).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.capacity(), 0);
+        assert_eq!(store.get(0), None);
+        assert_eq!(store.get_range(0..0), Some([].as_slice()));
+
+        let nodes = (0..200u32).map(|i| i.to_le_bytes()).collect::<Vec<_>>();
+        store.put_batch(0, nodes.clone()).unwrap();
+        assert_eq!(store.len(), 200);
+        assert!(store.capacity() >= 200);
+        assert_eq!(store.get_range(0..200), Some(nodes.as_slice()));
+        assert_eq!(store.get(199), Some(&nodes[199]));
+        assert_eq!(store.get(200), None);
+        assert_eq!(store.get_range(150..201), None);
+
+        store.put(5, [9; 4]).unwrap();
+        assert_eq!(store.get(5), Some(&[9; 4]));
+        assert_eq!(store.len(), 200);
+        assert_eq!(
+            store.put(201, [0; 4]),
+            Err(NodeStoreError::NonContiguousWrite(201, 200))
+        );
+        store.flush().unwrap();
+    }
+
+    #[cfg(feature = "blake2b")]
+    #[test]
+    fn tree_in_mmap_store() {
+        use crate::{
+            hasher::blake2b::{Blake2bHasher, Blake2bNode},
+            proof::{multi::MultiProofNodes, single::SingleProofNodes},
+            tree::MerkleTree,
+        };
+
+        let leaves = (0..13u8)
+            .map(|i| Blake2bHasher::hash_data([i]))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+
+        let store = MmapNodeStore::<32>::create(tempfile::tempfile().unwrap()).unwrap();
+        let t_mmap =
+            MerkleTree::<_, Blake2bHasher, _>::from_leaves_in_store(store, leaves).unwrap();
+        assert_eq!(t_mmap.root(), t.root());
+        assert_eq!(t_mmap.real_leaf_count(), t.real_leaf_count());
+        assert_eq!(
+            t_mmap.levels().collect::<Vec<_>>(),
+            t.levels().collect::<Vec<_>>()
+        );
+
+        for leaf_index in 0..13 {
+            let proof = SingleProofNodes::from_tree_leaf(&t_mmap, leaf_index)
+                .unwrap()
+                .into_values();
+            let expected = SingleProofNodes::from_tree_leaf(&t, leaf_index)
+                .unwrap()
+                .into_values();
+            assert_eq!(proof, expected);
+            assert!(proof
+                .verify(t.leaves()[leaf_index as usize], t.root())
+                .passed_decisively());
+        }
+
+        let proof = MultiProofNodes::from_tree_leaves(&t_mmap, &[0, 5, 12])
+            .unwrap()
+            .into_values();
+        let expected = MultiProofNodes::from_tree_leaves(&t, &[0, 5, 12])
+            .unwrap()
+            .into_values();
+        assert_eq!(proof.nodes(), expected.nodes());
+        t_mmap.store().flush().unwrap();
+    }
+
+    #[cfg(feature = "blake2b")]
+    #[test]
+    fn reopen_tree() {
+        use crate::{
+            hasher::blake2b::{Blake2bHasher, Blake2bNode},
+            tree::MerkleTree,
+            MerkleTreeFormError,
+        };
+
+        for leaf_count in [1u8, 2, 13, 100] {
+            let leaves = (0..leaf_count)
+                .map(|i| Blake2bHasher::hash_data([i]))
+                .collect::<Vec<_>>();
+            let t = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+
+            let store = MmapNodeStore::<32>::create(tempfile::tempfile().unwrap()).unwrap();
+            let t_mmap =
+                MerkleTree::<_, Blake2bHasher, _>::from_leaves_in_store(store, leaves).unwrap();
+            let real_leaf_count = t_mmap.real_leaf_count().get();
+            let file = t_mmap.into_store().into_file().unwrap();
+            assert_eq!(
+                file.metadata().unwrap().len(),
+                u64::from(t.total_node_count().get()) * 32
+            );
+
+            let store = MmapNodeStore::<32>::open(file).unwrap();
+            assert_eq!(store.len(), t.total_node_count().get() as usize);
+            let reopened =
+                MerkleTree::<_, Blake2bHasher, _>::from_store(store, real_leaf_count).unwrap();
+            assert_eq!(reopened.root(), t.root());
+            assert_eq!(reopened.real_leaf_count(), t.real_leaf_count());
+            assert_eq!(
+                reopened.levels().collect::<Vec<_>>(),
+                t.levels().collect::<Vec<_>>()
+            );
+
+            let store =
+                MmapNodeStore::<32>::open(reopened.into_store().into_file().unwrap()).unwrap();
+            assert_eq!(
+                MerkleTree::<_, Blake2bHasher, _>::from_store(store, 0).unwrap_err(),
+                MerkleTreeFormError::RealLeafCountMismatch(0, t.leaf_count().get())
+            );
+        }
+    }
+
+    #[test]
+    fn open_invalid_file_length() {
+        use std::io::Write;
+
+        // Empty, not a whole number of nodes, and a whole number of nodes that isn't a tree size
+        for length in [0, 5, 8, 16] {
+            let mut file = tempfile::tempfile().unwrap();
+            file.write_all(&vec![0; length]).unwrap();
+            assert_eq!(
+                MmapNodeStore::<4>::open(file).unwrap_err(),
+                NodeStoreError::InvalidFileLength(length as u64, 4)
+            );
+        }
+
+        // A tree of 3 nodes, in a file that was never shrunk, isn't a tree size
+        let mut store = MmapNodeStore::<4>::create(tempfile::tempfile().unwrap()).unwrap();
+        store.put_batch(0, [[1; 4], [2; 4], [3; 4]]).unwrap();
+        let file = store.file.try_clone().unwrap();
+        drop(store);
+        assert_eq!(
+            MmapNodeStore::<4>::open(file).unwrap_err(),
+            NodeStoreError::InvalidFileLength(MIN_CAPACITY as u64 * 4, 4)
+        );
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The storage of the nodes of a tree. A tree stores its nodes by absolute index, see `MerkleTree`.
+//! By default, nodes are stored in memory in a `Vec`, but any `NodeStore` can be used, such as
+//! a memory-mapped file for trees that don't fit in memory.
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::merkle::NodeStoreError;
+
+/// A store of the nodes of a tree, by absolute index. Nodes are written contiguously, i.e.,
+/// a node can be written at an index that's already stored (overwriting it), or at the end of the store.
+pub trait NodeStore<T> {
+    /// The number of nodes in the store.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The nodes in the given range of absolute indices, or None if the range is beyond the stored nodes.
+    fn get_range(&self, range: Range<usize>) -> Option<&[T]>;
+
+    /// The node at the given absolute index, or None if it's beyond the stored nodes.
+    fn get(&self, abs_index: usize) -> Option<&T> {
+        self.get_range(abs_index..abs_index + 1)
+            .and_then(|nodes| nodes.first())
+    }
+
+    /// Writes the node at the given absolute index, which must be at most the number of nodes in the store.
+    fn put(&mut self, abs_index: usize, node: T) -> Result<(), NodeStoreError>;
+
+    /// Writes the nodes at consecutive absolute indices, starting from the given one.
+    /// Stores can override this to write in batches.
+    fn put_batch(
+        &mut self,
+        start_abs_index: usize,
+        nodes: impl IntoIterator<Item = T>,
+    ) -> Result<(), NodeStoreError> {
+        nodes
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(offset, node)| self.put(start_abs_index + offset, node))
+    }
+}
+
+impl<T> NodeStore<T> for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn get_range(&self, range: Range<usize>) -> Option<&[T]> {
+        self.as_slice().get(range)
+    }
+
+    fn put(&mut self, abs_index: usize, node: T) -> Result<(), NodeStoreError> {
+        match abs_index.cmp(&Vec::len(self)) {
+            core::cmp::Ordering::Less => self[abs_index] = node,
+            core::cmp::Ordering::Equal => self.push(node),
+            core::cmp::Ordering::Greater => {
+                return Err(NodeStoreError::NonContiguousWrite(
+                    abs_index,
+                    Vec::len(self),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn put_batch(
+        &mut self,
+        start_abs_index: usize,
+        nodes: impl IntoIterator<Item = T>,
+    ) -> Result<(), NodeStoreError> {
+        if start_abs_index != Vec::len(self) {
+            let mut nodes = nodes.into_iter().enumerate();
+            // Overwrite the stored nodes one by one, then append the rest
+            for (offset, node) in nodes.by_ref() {
+                let index = start_abs_index + offset;
+                self.put(index, node)?;
+                if index + 1 == Vec::len(self) {
+                    break;
+                }
+            }
+            self.extend(nodes.map(|(_, node)| node));
+            return Ok(());
+        }
+
+        self.extend(nodes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vec_store() {
+        let mut store = Vec::new();
+        assert!(NodeStore::is_empty(&store));
+        assert_eq!(NodeStore::get(&store, 0), None);
+
+        store.put(0, 10).unwrap();
+        store.put_batch(1, [11, 12, 13]).unwrap();
+        assert_eq!(NodeStore::len(&store), 4);
+        assert_eq!(store.get_range(1..3), Some([11, 12].as_slice()));
+        assert_eq!(store.get_range(3..5), None);
+
+        // Overwriting and appending in one batch
+        store.put_batch(2, [22, 23, 24, 25]).unwrap();
+        assert_eq!(store, vec![10, 11, 22, 23, 24, 25]);
+        store.put(0, 20).unwrap();
+        assert_eq!(NodeStore::get(&store, 0), Some(&20));
+
+        assert_eq!(
+            store.put(7, 0),
+            Err(NodeStoreError::NonContiguousWrite(7, 6))
+        );
+        assert_eq!(
+            store.put_batch(7, [0]),
+            Err(NodeStoreError::NonContiguousWrite(7, 6))
+        );
+        assert_eq!(NodeStore::len(&store), 6);
+    }
+}
@@ -15,6 +15,11 @@
 
 use super::*;
 use crate::internal::{hash_data, HashAlgo, HashedData};
+use crate::rand_tools::{make_seedable_rng, Seed};
+use rand::Rng;
+use rstest::rstest;
+
+use self::traversal::DepthFirstOrder;
 
 #[test]
 fn merkletree_too_small() {
@@ -810,3 +815,413 @@ fn node_and_siblings_eight_leaves() {
         assert!(node.sibling().is_none());
     }
 }
+
+#[test]
+fn real_leaf_count_and_padding_leaves() {
+    for leaf_count in 1..40u32 {
+        let leaves = (0..leaf_count)
+            .map(|i| HashedData::from_low_u64_be(i as u64))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+        assert_eq!(t.real_leaf_count().get(), leaf_count);
+        assert_eq!(t.leaf_count().get(), leaf_count.next_power_of_two());
+        for index in 0..t.leaf_count().get() + 2 {
+            assert_eq!(
+                t.is_padding_leaf(index),
+                index >= leaf_count && index < t.leaf_count().get()
+            );
+        }
+    }
+
+    // Trees with the same nodes but different real leaves are different
+    let leaves = (0..3)
+        .map(|i| HashedData::from_low_u64_be(i as u64))
+        .collect::<Vec<_>>();
+    let t3 = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+    let padded_leaves = (0..4)
+        .map(|i| t3.node_value_from_bottom(0, i).unwrap())
+        .collect::<Vec<_>>();
+    let t4 = MerkleTree::<HashedData, HashAlgo>::from_leaves(padded_leaves).unwrap();
+    assert_eq!(t3.root(), t4.root());
+    assert_ne!(t3, t4);
+}
+
+#[test]
+fn count_committed_root() {
+    for leaf_count in 1..20u32 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i as u64)))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+        assert_eq!(
+            t.count_committed_root(),
+            HashAlgo::commit_leaf_count(&t.root(), leaf_count)
+        );
+        assert_ne!(t.count_committed_root(), t.root());
+    }
+}
+
+#[test]
+fn node_children_and_descendants() {
+    let leaves = (0..6u64)
+        .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+        .collect::<Vec<_>>();
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+    for abs_index in 0..t.total_node_count().get() {
+        let (level, index) = NodePosition::from_abs_index(t.total_node_count(), abs_index)
+            .unwrap()
+            .position();
+        let node = t.node_from_bottom(level, index).unwrap();
+
+        match node.children() {
+            Some((left, right)) => {
+                assert_eq!(node.left_child(), Some(left));
+                assert_eq!(node.right_child(), Some(right));
+                assert_eq!(HashAlgo::hash_pair(left.hash(), right.hash()), *node.hash());
+                assert_eq!(left.parent(), Some(node));
+            }
+            None => {
+                assert_eq!(level, 0);
+                assert_eq!(node.left_child(), None);
+                assert_eq!(node.right_child(), None);
+            }
+        }
+
+        let leaves_under = node
+            .descendants_at_level(0)
+            .unwrap()
+            .map(|leaf| leaf.abs_index())
+            .collect::<Vec<_>>();
+        assert_eq!(leaves_under, node.leaf_range().collect::<Vec<_>>());
+        assert!(node.descendants_at_level(level + 1).is_none());
+    }
+
+    let root = t.node_from_bottom(3, 0).unwrap();
+    assert_eq!(root.leaf_range(), 0..8);
+
+    let leaf1 = t.node_from_bottom(0, 1).unwrap();
+    let leaf2 = t.node_from_bottom(0, 2).unwrap();
+    let leaf3 = t.node_from_bottom(0, 3).unwrap();
+    let leaf5 = t.node_from_bottom(0, 5).unwrap();
+    assert_eq!(
+        leaf2.lowest_common_ancestor(&leaf3),
+        t.node_from_bottom(1, 1)
+    );
+    assert_eq!(
+        leaf1.lowest_common_ancestor(&leaf2),
+        t.node_from_bottom(2, 0)
+    );
+    assert_eq!(leaf1.lowest_common_ancestor(&leaf5), Some(root));
+    assert_eq!(leaf1.lowest_common_ancestor(&root), Some(root));
+    assert_eq!(leaf1.lowest_common_ancestor(&leaf1), Some(leaf1));
+
+    let other = MerkleTree::<HashedData, HashAlgo>::from_leaves(
+        (0..6u64).map(|i| hash_data(HashedData::from_low_u64_be(i))),
+    )
+    .unwrap();
+    let other_leaf = other.node_from_bottom(0, 2).unwrap();
+    assert_eq!(leaf1.lowest_common_ancestor(&other_leaf), None);
+}
+
+#[test]
+fn level_slices() {
+    for leaf_count in 1..40u64 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+        assert_eq!(t.leaves().len() as u32, t.leaf_count().get());
+        assert_eq!(&t.leaves()[..leaves.len()], leaves.as_slice());
+        assert_eq!(t.level(0), Some(t.leaves()));
+        assert_eq!(t.level(t.level_count().get()), None);
+        assert_eq!(t.levels().count() as u32, t.level_count().get());
+        assert_eq!(t.levels().last(), Some([t.root()].as_slice()));
+
+        for (level, nodes) in t.levels().enumerate() {
+            assert_eq!(t.level(level as u32), Some(nodes));
+            assert_eq!(nodes.len() as u32, t.leaf_count().get() >> level);
+            for (index, node) in nodes.iter().enumerate() {
+                assert_eq!(
+                    t.node_value_from_bottom(level as u32, index as u32),
+                    Some(*node)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn breadth_first_iteration() {
+    for leaf_count in 1..20u64 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+
+        let expected = t
+            .levels()
+            .enumerate()
+            .rev()
+            .flat_map(|(level, nodes)| {
+                (0..nodes.len()).map(move |index| (level as u32, index as u32))
+            })
+            .collect::<Vec<_>>();
+        let nodes = t.iter_breadth_first().collect::<Vec<_>>();
+        assert_eq!(
+            nodes
+                .iter()
+                .map(|(_, pos)| pos.position())
+                .collect::<Vec<_>>(),
+            expected
+        );
+        for (node, pos) in nodes {
+            assert_eq!(node.into_position(), pos);
+            let (level, index) = pos.position();
+            assert_eq!(node, t.node_from_bottom(level, index).unwrap());
+        }
+    }
+}
+
+#[test]
+fn depth_first_iteration() {
+    // Recursive reference implementation
+    fn visit(pos: NodePosition, order: DepthFirstOrder, result: &mut Vec<u32>) {
+        match pos.children() {
+            None => result.push(pos.abs_index()),
+            Some((left, right)) => {
+                if order == DepthFirstOrder::PreOrder {
+                    result.push(pos.abs_index());
+                }
+                visit(left, order, result);
+                if order == DepthFirstOrder::InOrder {
+                    result.push(pos.abs_index());
+                }
+                visit(right, order, result);
+                if order == DepthFirstOrder::PostOrder {
+                    result.push(pos.abs_index());
+                }
+            }
+        }
+    }
+
+    for leaf_count in 1..20u64 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves).unwrap();
+        let root =
+            NodePosition::from_abs_index(t.total_node_count(), t.total_node_count().get() - 1)
+                .unwrap();
+
+        for order in [
+            DepthFirstOrder::PreOrder,
+            DepthFirstOrder::InOrder,
+            DepthFirstOrder::PostOrder,
+        ] {
+            let mut expected = Vec::new();
+            visit(root, order, &mut expected);
+
+            let nodes = t.iter_depth_first(order).collect::<Vec<_>>();
+            assert_eq!(
+                nodes
+                    .iter()
+                    .map(|(node, _)| node.abs_index())
+                    .collect::<Vec<_>>(),
+                expected
+            );
+            for (node, pos) in nodes {
+                assert_eq!(node.into_position(), pos);
+            }
+        }
+    }
+
+    // In-order traversal of a leaf-only tree is the leaf itself
+    let t = MerkleTree::<HashedData, HashAlgo>::from_leaves([HashedData::zero()]).unwrap();
+    let mut iter = t.iter_depth_first(DepthFirstOrder::InOrder);
+    assert_eq!(
+        iter.next().map(|(node, _)| *node.hash()),
+        Some(HashedData::zero())
+    );
+    assert!(iter.next().is_none());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn tree_size_for_leaf_count() {
+    assert_eq!(
+        checked_tree_size_for_leaf_count(0),
+        Err(MerkleTreeFormError::TooSmall(0))
+    );
+    for log_leaf_count in 0..=30 {
+        let leaf_count = 1usize << log_leaf_count;
+        assert_eq!(
+            checked_tree_size_for_leaf_count(leaf_count)
+                .unwrap()
+                .leaf_count()
+                .get() as usize,
+            leaf_count
+        );
+    }
+    for leaf_count in [1usize << 31, 1 << 32, usize::MAX / 2 + 1] {
+        assert_eq!(
+            checked_tree_size_for_leaf_count(leaf_count),
+            Err(MerkleTreeFormError::TooLarge(leaf_count))
+        );
+    }
+}
+
+#[test]
+fn too_many_leaves_rejected_before_storing() {
+    // Storing this many leaves would take all the memory, so they must be rejected by their count alone
+    for leaf_count in [MAX_LEAF_COUNT + 1, 1 << 31, usize::MAX] {
+        let leaves = core::iter::repeat(HashedData::zero()).take(leaf_count);
+        assert_eq!(
+            MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves),
+            Err(MerkleTreeFormError::TooLarge(
+                leaf_count.checked_next_power_of_two().unwrap_or(usize::MAX)
+            ))
+        );
+    }
+}
+
+#[test]
+fn tree_in_store() {
+    for leaf_count in 1..=9 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<_, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+        let store = Vec::with_capacity(t.total_node_count().get() as usize);
+        let t_in_store =
+            MerkleTree::<_, HashAlgo, Vec<_>>::from_leaves_in_store(store, leaves.clone()).unwrap();
+        assert_eq!(t_in_store, t);
+        assert_eq!(t_in_store.store(), &t.clone().into_store());
+
+        let stored_nodes = vec![HashedData::zero()];
+        assert_eq!(
+            MerkleTree::<_, HashAlgo, Vec<_>>::from_leaves_in_store(stored_nodes, leaves)
+                .unwrap_err(),
+            MerkleTreeFormError::StoreError(NodeStoreError::NotEmpty(1))
+        );
+    }
+
+    assert_eq!(
+        MerkleTree::<_, HashAlgo, Vec<HashedData>>::from_leaves_in_store(Vec::new(), [])
+            .unwrap_err(),
+        MerkleTreeFormError::TooSmall(0)
+    );
+}
+
+#[test]
+fn tree_from_store() {
+    for leaf_count in 1..=9u32 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i.into())))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<_, HashAlgo>::from_leaves(leaves).unwrap();
+        let padded_leaf_count = t.leaf_count().get();
+
+        let t_from_store =
+            MerkleTree::<_, HashAlgo, Vec<_>>::from_store(t.clone().into_store(), leaf_count)
+                .unwrap();
+        assert_eq!(t_from_store, t);
+        assert_eq!(t_from_store.real_leaf_count(), t.real_leaf_count());
+
+        for wrong_count in [0, padded_leaf_count / 2, padded_leaf_count + 1, u32::MAX] {
+            if wrong_count.checked_next_power_of_two() == Some(padded_leaf_count) {
+                continue;
+            }
+            assert_eq!(
+                MerkleTree::<_, HashAlgo, Vec<_>>::from_store(t.clone().into_store(), wrong_count),
+                Err(MerkleTreeFormError::RealLeafCountMismatch(
+                    wrong_count,
+                    padded_leaf_count
+                ))
+            );
+        }
+    }
+
+    for store_length in [0, 2, 4, 6] {
+        assert_eq!(
+            MerkleTree::<_, HashAlgo, Vec<_>>::from_store(
+                vec![HashedData::zero(); store_length],
+                1
+            ),
+            Err(MerkleTreeFormError::InvalidStoreSize(store_length))
+        );
+    }
+}
+
+#[test]
+fn diff_same_and_mismatching_trees() {
+    let leaves = (0..5).map(|i| hash_data(HashedData::from_low_u64_be(i)));
+    let t = MerkleTree::<_, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+    assert_eq!(t.diff_counting_comparisons(&t.clone()), Ok((vec![], 1)));
+
+    let t_bigger = MerkleTree::<_, HashAlgo>::from_leaves(leaves.clone().chain(leaves)).unwrap();
+    assert_eq!(
+        t.diff(&t_bigger),
+        Err(MerkleTreeDiffError::LeafCountMismatch(8, 16))
+    );
+}
+
+#[test]
+fn diff_different_real_leaf_counts() {
+    let leaves = (0..7).map(|i| hash_data(HashedData::from_low_u64_be(i)));
+    let t5 = MerkleTree::<_, HashAlgo>::from_leaves(leaves.clone().take(5)).unwrap();
+    let t7 = MerkleTree::<_, HashAlgo>::from_leaves(leaves).unwrap();
+
+    // Leaf 7 is padding in both trees, so only the leaves that are real in one of them are reported
+    assert_eq!(t5.diff(&t7), Ok(vec![5, 6]));
+    assert_eq!(t7.diff(&t5), Ok(vec![5, 6]));
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn diff_random_changes(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+
+    for leaf_count in 1..=40 {
+        let leaves = (0..leaf_count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i)))
+            .collect::<Vec<_>>();
+        let t = MerkleTree::<_, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+        for _ in 0..10 {
+            let mut changed_leaves = leaves.clone();
+            let mut changed_indices = (0..leaf_count as u32)
+                .filter(|_| rng.gen_bool(0.1))
+                .collect::<Vec<_>>();
+            for index in &changed_indices {
+                changed_leaves[*index as usize] = HashedData::repeat_byte(rng.gen_range(1..=255));
+            }
+            changed_indices
+                .retain(|index| changed_leaves[*index as usize] != leaves[*index as usize]);
+
+            let changed_t = MerkleTree::<_, HashAlgo>::from_leaves(changed_leaves).unwrap();
+            let (diff, comparison_count) = t.diff_counting_comparisons(&changed_t).unwrap();
+            assert_eq!(diff, changed_indices);
+            assert_eq!(changed_t.diff(&t).unwrap(), changed_indices);
+
+            // Every differing leaf makes at most two comparisons per level, beyond the root
+            let padding_changed = changed_indices.last() == Some(&(leaf_count as u32 - 1));
+            let differing_leaf_count = changed_indices.len()
+                + if padding_changed {
+                    t.leaf_count().get() as usize - leaf_count as usize
+                } else {
+                    0
+                };
+            assert!(
+                comparison_count
+                    <= 1 + 2 * differing_leaf_count * (t.level_count().get() as usize - 1)
+            );
+        }
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{vec, vec::Vec};
+
+use crate::merkle::pos::NodePosition;
+
+use super::{store::NodeStore, MerkleTree, Node};
+
+/// The order, in which a depth-first traversal yields the nodes of a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFirstOrder {
+    /// A node is yielded before its children.
+    PreOrder,
+    /// A node is yielded after its left child, and before its right child.
+    InOrder,
+    /// A node is yielded after its children.
+    PostOrder,
+}
+
+/// An iterator over the nodes of a tree, depth-first, starting from the root and going left first.
+#[must_use]
+pub struct DepthFirstIterator<'a, T, H, S = Vec<T>> {
+    tree: &'a MerkleTree<T, H, S>,
+    order: DepthFirstOrder,
+    // The nodes left to visit, each with whether its children were already pushed to the stack
+    stack: Vec<(NodePosition, bool)>,
+}
+
+impl<'a, T, H, S: NodeStore<T>> DepthFirstIterator<'a, T, H, S> {
+    pub(super) fn new(tree: &'a MerkleTree<T, H, S>, order: DepthFirstOrder) -> Self {
+        let root = NodePosition::from_abs_index(
+            tree.store
+                .len()
+                .try_into()
+                .expect("Tree size is by design valid"),
+            tree.store.len() as u32 - 1,
+        )
+        .expect("The root is always in the tree");
+
+        Self {
+            tree,
+            order,
+            stack: vec![(root, false)],
+        }
+    }
+
+    fn node(&self, position: NodePosition) -> (Node<'a, T, H, S>, NodePosition) {
+        let node = Node {
+            tree_ref: self.tree,
+            absolute_index: position.abs_index(),
+        };
+        (node, position)
+    }
+}
+
+impl<'a, T, H, S: NodeStore<T>> Iterator for DepthFirstIterator<'a, T, H, S> {
+    type Item = (Node<'a, T, H, S>, NodePosition);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (position, expanded) = self.stack.pop()?;
+
+            let (left, right) = match position.children() {
+                Some(children) if !expanded => children,
+                _ => return Some(self.node(position)),
+            };
+
+            // The stack is last-in first-out, so the nodes are pushed in the reverse order of visiting
+            match self.order {
+                DepthFirstOrder::PreOrder => {
+                    self.stack.push((right, false));
+                    self.stack.push((left, false));
+                    return Some(self.node(position));
+                }
+                DepthFirstOrder::InOrder => {
+                    self.stack.push((right, false));
+                    self.stack.push((position, true));
+                    self.stack.push((left, false));
+                }
+                DepthFirstOrder::PostOrder => {
+                    self.stack.push((position, true));
+                    self.stack.push((right, false));
+                    self.stack.push((left, false));
+                }
+            }
+        }
+    }
+}
+
+impl<T, H, S: NodeStore<T>> core::iter::FusedIterator for DepthFirstIterator<'_, T, H, S> {}
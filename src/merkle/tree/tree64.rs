@@ -0,0 +1,359 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::{
+    fmt::Debug,
+    num::{NonZeroU32, NonZeroU64},
+};
+
+use crate::merkle::{
+    hasher::PairHasher, pos::position64::NodePosition64, MerkleTreeFormError, NodeStoreError,
+};
+
+use super::{
+    hash_internal_nodes, store::NodeStore, tree_size::TreeSize64, with_padded_leaves, MerkleTree,
+};
+
+/// The largest number of leaves, including padding, that a tree of `TreeSize64` can have,
+/// unless the absolute indices of the nodes don't fit in a `usize`, as on 32-bit platforms.
+const MAX_LEAF_COUNT: u64 = if (usize::MAX as u64) < (1 << 63) {
+    usize::MAX as u64 / 2 + 1
+} else {
+    1 << 62
+};
+
+/// Same as `MerkleTree`, but with 64-bit leaf indices and tree sizes, for trees that have more leaves than
+/// `MerkleTree` supports, such as archival commitments. The nodes are laid out in the store the same way,
+/// so a tree that fits in both has the same nodes, and its proofs are `SingleProofHashes64` and `MultiProofHashes64`.
+#[derive(Clone)]
+pub struct MerkleTree64<T, H, S = Vec<T>> {
+    store: S,
+    real_leaf_count: NonZeroU64,
+    _phantom: core::marker::PhantomData<(T, H)>,
+}
+
+impl<T: PartialEq, H, S: NodeStore<T>> PartialEq for MerkleTree64<T, H, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.store.get_range(0..self.store.len()) == other.store.get_range(0..other.store.len())
+            && self.real_leaf_count == other.real_leaf_count
+    }
+}
+
+impl<T: Eq, H, S: NodeStore<T>> Eq for MerkleTree64<T, H, S> {}
+
+impl<T, H, S: NodeStore<T>> Debug for MerkleTree64<T, H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // The tree may be too large to print its nodes
+        f.debug_struct("MerkleTree64")
+            .field("total_node_count", &self.store.len())
+            .field("real_leaf_count", &self.real_leaf_count)
+            .finish()
+    }
+}
+
+impl<T, H, S: NodeStore<T>> MerkleTree64<T, H, S> {
+    /// The store that holds the nodes of the tree.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Consumes the tree and returns the store that holds its nodes.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn total_node_count(&self) -> TreeSize64 {
+        TreeSize64::from_u64(self.store.len() as u64).expect("By design, the tree size is valid")
+    }
+
+    pub fn leaf_count(&self) -> NonZeroU64 {
+        self.total_node_count().leaf_count()
+    }
+
+    /// The number of leaves that the tree was created from, before padding.
+    pub fn real_leaf_count(&self) -> NonZeroU64 {
+        self.real_leaf_count
+    }
+
+    /// See `MerkleTree::is_padding_leaf`.
+    pub fn is_padding_leaf(&self, leaf_index: u64) -> bool {
+        leaf_index >= self.real_leaf_count.get() && leaf_index < self.leaf_count().get()
+    }
+
+    pub fn level_count(&self) -> NonZeroU32 {
+        self.total_node_count().level_count()
+    }
+
+    /// The node at the given absolute index, or None if it's beyond the tree.
+    pub(crate) fn node(&self, abs_index: u64) -> Option<&T> {
+        usize::try_from(abs_index)
+            .ok()
+            .and_then(|abs_index| self.store.get(abs_index))
+    }
+}
+
+impl<T: Clone, H, S: NodeStore<T>> MerkleTree64<T, H, S> {
+    pub fn root(&self) -> T {
+        self.node(self.total_node_count().get() - 1)
+            .cloned()
+            .expect("By design, at least one element must exist")
+    }
+
+    pub fn node_value_from_bottom(&self, level_from_bottom: u32, index_in_level: u64) -> Option<T> {
+        let position = NodePosition64::from_position(
+            self.total_node_count(),
+            level_from_bottom,
+            index_in_level,
+        )?;
+        self.node(position.abs_index()).cloned()
+    }
+}
+
+impl<T: Clone, H: PairHasher<NodeType = T>> MerkleTree64<T, H> {
+    /// Same as `MerkleTree::from_leaves`, for up to 2^62 leaves.
+    pub fn from_leaves(leaves: impl IntoIterator<Item = T>) -> Result<Self, MerkleTreeFormError> {
+        Self::from_leaves_in_store(Vec::new(), leaves)
+    }
+}
+
+impl<T: Clone, H: PairHasher<NodeType = T>, S: NodeStore<T>> MerkleTree64<T, H, S> {
+    /// Like `from_leaves`, but the nodes are written to the given store, which must be empty.
+    /// Larger inputs than a tree can have are rejected with `MerkleTreeFormError::TooLarge`.
+    pub fn from_leaves_in_store(
+        mut store: S,
+        leaves: impl IntoIterator<Item = T>,
+    ) -> Result<Self, MerkleTreeFormError> {
+        if !store.is_empty() {
+            return Err(NodeStoreError::NotEmpty(store.len()).into());
+        }
+
+        let ((), real_leaf_count) =
+            with_padded_leaves::<T, H, _>(leaves, MAX_LEAF_COUNT as usize, |padded| {
+                Ok(store.put_batch(0, padded)?)
+            })?;
+        if store.is_empty() {
+            return Err(MerkleTreeFormError::TooSmall(0));
+        }
+
+        hash_internal_nodes::<T, H, S>(&mut store)?;
+
+        Ok(Self {
+            store,
+            real_leaf_count: NonZeroU64::new(real_leaf_count as u64)
+                .expect("The store has at least one leaf"),
+            _phantom: core::marker::PhantomData,
+        })
+    }
+
+    /// Same as `MerkleTree::from_store`, for a store that has the nodes of a tree of up to 2^62 leaves.
+    pub fn from_store(store: S, real_leaf_count: u64) -> Result<Self, MerkleTreeFormError> {
+        let tree_size = TreeSize64::from_u64(store.len() as u64)
+            .map_err(|_| MerkleTreeFormError::InvalidStoreSize(store.len()))?;
+        let leaf_count = tree_size.leaf_count().get();
+
+        let real_leaf_count = NonZeroU64::new(real_leaf_count)
+            .filter(|count| count.get().checked_next_power_of_two() == Some(leaf_count))
+            .ok_or(MerkleTreeFormError::RealLeafCountMismatch64(
+                real_leaf_count,
+                leaf_count,
+            ))?;
+
+        Ok(Self {
+            store,
+            real_leaf_count,
+            _phantom: core::marker::PhantomData,
+        })
+    }
+}
+
+/// Converts a tree, without hashing, since the nodes are laid out the same way.
+impl<T, H, S> From<MerkleTree<T, H, S>> for MerkleTree64<T, H, S> {
+    fn from(tree: MerkleTree<T, H, S>) -> Self {
+        Self {
+            real_leaf_count: tree.real_leaf_count.into(),
+            store: tree.store,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+
+    use crate::{
+        internal::{hash_data, HashAlgo, HashedData},
+        proof::{
+            multi::{proof64::MultiProofHashes64, MultiProofNodes},
+            single::{proof64::SingleProofHashes64, SingleProofNodes},
+        },
+        MerkleTreeProofExtractionError,
+    };
+
+    use super::*;
+
+    fn leaves(count: u32) -> Vec<HashedData> {
+        (0..count)
+            .map(|i| hash_data(HashedData::from_low_u64_be(i.into())))
+            .collect()
+    }
+
+    #[test]
+    fn same_as_32_bit_tree() {
+        for leaf_count in 1..=17u32 {
+            let leaves = leaves(leaf_count);
+            let t = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+            let t64 = MerkleTree64::<HashedData, HashAlgo>::from_leaves(leaves.clone()).unwrap();
+
+            assert_eq!(t64.root(), t.root());
+            assert_eq!(
+                t64.total_node_count().get(),
+                u64::from(t.total_node_count().get())
+            );
+            assert_eq!(t64.real_leaf_count().get(), u64::from(leaf_count));
+            assert_eq!(t64.level_count(), t.level_count());
+            assert_eq!(t64.store(), &t.nodes().to_vec());
+            assert_eq!(t64, MerkleTree64::from(t.clone()));
+
+            for level in 0..t.level_count().get() + 1 {
+                for index in 0..t.leaf_count().get() + 1 {
+                    assert_eq!(
+                        t64.node_value_from_bottom(level, index.into()),
+                        t.node_value_from_bottom(level, index)
+                    );
+                }
+            }
+
+            for index in 0..t.leaf_count().get() {
+                assert_eq!(t64.is_padding_leaf(index.into()), t.is_padding_leaf(index));
+
+                let proof = SingleProofNodes::from_tree_leaf_including_padding(&t, index)
+                    .unwrap()
+                    .into_values();
+                assert_eq!(
+                    SingleProofHashes64::from_tree_leaf_including_padding(&t64, index.into()),
+                    Ok(SingleProofHashes64::try_from(proof).ok().unwrap())
+                );
+            }
+
+            // Every window of up to 3 consecutive leaves
+            for start in 0..t.leaf_count().get() {
+                for end in (start + 1)..=(start + 3).min(t.leaf_count().get()) {
+                    let indices = (start..end).collect::<Vec<_>>();
+                    let indices64 = indices.iter().map(|i| u64::from(*i)).collect::<Vec<_>>();
+                    let proof = MultiProofNodes::from_tree_leaves_including_padding(&t, &indices)
+                        .unwrap()
+                        .into_values();
+                    let proof64 =
+                        MultiProofHashes64::from_tree_leaves_including_padding(&t64, &indices64)
+                            .unwrap();
+                    assert_eq!(proof64, MultiProofHashes64::from(proof));
+
+                    let proven = indices64
+                        .iter()
+                        .map(|i| (*i, t64.node_value_from_bottom(0, *i).unwrap()))
+                        .collect::<BTreeMap<_, _>>();
+                    assert!(!proof64.verify(proven, t64.root()).unwrap().failed());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn proofs_of_padding_and_invalid_leaves() {
+        let t64 = MerkleTree64::<HashedData, HashAlgo>::from_leaves(leaves(5)).unwrap();
+
+        assert_eq!(
+            SingleProofHashes64::from_tree_leaf(&t64, 5),
+            Err(MerkleTreeProofExtractionError::PaddingLeaf64(5, 5))
+        );
+        assert_eq!(
+            SingleProofHashes64::from_tree_leaf(&t64, 8),
+            Err(MerkleTreeProofExtractionError::LeafIndexOutOfRange64(8, 8))
+        );
+        assert_eq!(
+            MultiProofHashes64::from_tree_leaves(&t64, &[0, 7]),
+            Err(MerkleTreeProofExtractionError::PaddingLeaf64(7, 5))
+        );
+        assert_eq!(
+            MultiProofHashes64::from_tree_leaves(&t64, &[]),
+            Err(MerkleTreeProofExtractionError::NoLeavesToCreateProof)
+        );
+        assert_eq!(
+            MultiProofHashes64::from_tree_leaves(&t64, &[2, 2]),
+            Err(MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices64(vec![2, 2]))
+        );
+        assert_eq!(
+            MultiProofHashes64::from_tree_leaves(&t64, &[3, 1]),
+            Err(MerkleTreeProofExtractionError::UnsortedOrUniqueLeavesIndices64(vec![3, 1]))
+        );
+        assert_eq!(
+            MultiProofHashes64::from_tree_leaves_including_padding(&t64, &[1, 8]),
+            Err(MerkleTreeProofExtractionError::IndexOutOfRange64(
+                vec![1, 8],
+                8
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_leaves_and_stores() {
+        assert_eq!(
+            MerkleTree64::<HashedData, HashAlgo>::from_leaves([]).err(),
+            Some(MerkleTreeFormError::TooSmall(0))
+        );
+        assert_eq!(
+            MerkleTree64::<HashedData, HashAlgo>::from_leaves_in_store(
+                vec![HashedData::zero()],
+                leaves(1)
+            )
+            .err(),
+            Some(NodeStoreError::NotEmpty(1).into())
+        );
+
+        // Rejected from the size hint, before anything is stored
+        let leaf = hash_data(HashedData::zero());
+        assert_eq!(
+            MerkleTree64::<HashedData, HashAlgo>::from_leaves(
+                core::iter::repeat(leaf).take(MAX_LEAF_COUNT as usize + 1)
+            )
+            .err(),
+            Some(MerkleTreeFormError::TooLarge(
+                (MAX_LEAF_COUNT as usize + 1).next_power_of_two()
+            ))
+        );
+
+        let nodes = MerkleTree::<HashedData, HashAlgo>::from_leaves(leaves(5))
+            .unwrap()
+            .into_store();
+        let t64 = MerkleTree64::<HashedData, HashAlgo>::from_store(nodes.clone(), 5).unwrap();
+        assert_eq!(t64.real_leaf_count().get(), 5);
+        for real_leaf_count in [0, 4, 9] {
+            assert_eq!(
+                MerkleTree64::<HashedData, HashAlgo>::from_store(nodes.clone(), real_leaf_count)
+                    .err(),
+                Some(MerkleTreeFormError::RealLeafCountMismatch64(
+                    real_leaf_count,
+                    8
+                ))
+            );
+        }
+        assert_eq!(
+            MerkleTree64::<HashedData, HashAlgo>::from_store(nodes[..14].to_vec(), 5).err(),
+            Some(MerkleTreeFormError::InvalidStoreSize(14))
+        );
+    }
+}
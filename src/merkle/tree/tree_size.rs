@@ -13,9 +13,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{
+use core::{
     fmt::{Display, Formatter},
-    num::NonZeroU32,
+    num::{NonZeroU32, NonZeroU64},
 };
 
 use itertools::Itertools;
@@ -25,6 +25,8 @@ pub struct TreeSize(u32);
 
 const MAX_TREE_SIZE: u32 = 1 << 31;
 
+const MAX_TREE_SIZE_64: u64 = 1 << 63;
+
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 pub enum TreeSizeError {
     #[error("Zero is invalid size for tree")]
@@ -33,6 +35,8 @@ pub enum TreeSizeError {
     InvalidSize(u32),
     #[error("Tree with this huge size is not supported: {0}")]
     HugeTreeUnsupported(u64),
+    #[error("Tree size must be power of two minus one; this value was found: {0}")]
+    InvalidSize64(u64),
 }
 
 impl TreeSize {
@@ -41,7 +45,8 @@ impl TreeSize {
     }
 
     pub fn leaf_count(&self) -> NonZeroU32 {
-        ((self.0 + 1) / 2)
+        self.0
+            .div_ceil(2)
             .try_into()
             .expect("Guaranteed by construction")
     }
@@ -65,7 +70,12 @@ impl TreeSize {
         if leaf_count == 0 {
             return Err(TreeSizeError::ZeroSize);
         }
-        Self::try_from(leaf_count * 2 - 1)
+        let node_count = leaf_count
+            .checked_mul(2)
+            .ok_or(TreeSizeError::HugeTreeUnsupported(
+                leaf_count as u64 * 2 - 1,
+            ))?;
+        Self::try_from(node_count - 1)
     }
 
     /// The absolute index, at which the first node at level `level_from_bottom` starts.
@@ -102,10 +112,10 @@ impl TryFrom<u32> for TreeSize {
     fn try_from(value: u32) -> Result<Self, Self::Error> {
         if value == 0 {
             Err(TreeSizeError::ZeroSize)
-        } else if !(value + 1).is_power_of_two() {
-            Err(TreeSizeError::InvalidSize(value))
         } else if value > MAX_TREE_SIZE {
             Err(TreeSizeError::HugeTreeUnsupported(value as u64))
+        } else if !(value + 1).is_power_of_two() {
+            Err(TreeSizeError::InvalidSize(value))
         } else {
             Ok(Self(value))
         }
@@ -139,7 +149,103 @@ impl AsRef<u32> for TreeSize {
 }
 
 impl Display for TreeSize {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Same as `TreeSize`, but with 64-bit indices, for trees that have more leaves than `TreeSize` supports,
+/// such as trees that are never held in memory, but their proofs are verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TreeSize64(u64);
+
+impl TreeSize64 {
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+
+    pub fn leaf_count(&self) -> NonZeroU64 {
+        self.0
+            .div_ceil(2)
+            .try_into()
+            .expect("Guaranteed by construction")
+    }
+
+    pub fn level_count(&self) -> NonZeroU32 {
+        self.0
+            .count_ones()
+            .try_into()
+            .expect("Guaranteed by construction")
+    }
+
+    pub fn from_u64(value: u64) -> Result<Self, TreeSizeError> {
+        Self::try_from(value)
+    }
+
+    pub fn from_leaf_count(leaf_count: u64) -> Result<Self, TreeSizeError> {
+        if leaf_count == 0 {
+            return Err(TreeSizeError::ZeroSize);
+        }
+        let node_count = leaf_count
+            .checked_mul(2)
+            .ok_or(TreeSizeError::HugeTreeUnsupported(u64::MAX))?;
+        Self::try_from(node_count - 1)
+    }
+
+    /// The absolute index, at which the first node at level `level_from_bottom` starts.
+    /// See `TreeSize::level_start` for how this is calculated.
+    pub fn level_start(&self, level_from_bottom: u32) -> Option<u64> {
+        let level_count = self.level_count().get();
+        if level_from_bottom >= level_count {
+            return None;
+        }
+
+        let level_from_top = level_count - level_from_bottom;
+        let level_start = (self.0 >> level_from_top) << level_from_top;
+        Some(level_start)
+    }
+}
+
+impl TryFrom<u64> for TreeSize64 {
+    type Error = TreeSizeError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        if value == 0 {
+            Err(TreeSizeError::ZeroSize)
+        } else if value >= MAX_TREE_SIZE_64 {
+            Err(TreeSizeError::HugeTreeUnsupported(value))
+        } else if !(value + 1).is_power_of_two() {
+            Err(TreeSizeError::InvalidSize64(value))
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl From<TreeSize> for TreeSize64 {
+    fn from(tree_size: TreeSize) -> Self {
+        Self(tree_size.get() as u64)
+    }
+}
+
+impl TryFrom<TreeSize64> for TreeSize {
+    type Error = TreeSizeError;
+
+    fn try_from(tree_size: TreeSize64) -> Result<Self, Self::Error> {
+        let size = u32::try_from(tree_size.get())
+            .map_err(|_| TreeSizeError::HugeTreeUnsupported(tree_size.get()))?;
+        Self::try_from(size)
+    }
+}
+
+impl From<TreeSize64> for u64 {
+    fn from(tree_size: TreeSize64) -> Self {
+        tree_size.0
+    }
+}
+
+impl Display for TreeSize64 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
@@ -364,6 +470,11 @@ mod tests {
             TreeSize::try_from(huge_tree_size).unwrap_err(),
             TreeSizeError::HugeTreeUnsupported(huge_tree_size as u64)
         );
+        // the leaf count of a huge tree must not overflow when computing the tree size
+        assert_eq!(
+            TreeSize::from_leaf_count(1 << 31).unwrap_err(),
+            TreeSizeError::HugeTreeUnsupported((1 << 32) - 1)
+        );
     }
 
     #[test]
@@ -424,4 +535,58 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn tree_size_64() {
+        assert_eq!(TreeSize64::from_u64(0), Err(TreeSizeError::ZeroSize));
+        assert_eq!(
+            TreeSize64::from_u64(2),
+            Err(TreeSizeError::InvalidSize64(2))
+        );
+        assert_eq!(
+            TreeSize64::from_u64(u64::MAX),
+            Err(TreeSizeError::HugeTreeUnsupported(u64::MAX))
+        );
+        assert_eq!(
+            TreeSize64::from_leaf_count(1 << 63),
+            Err(TreeSizeError::HugeTreeUnsupported(u64::MAX))
+        );
+        assert_eq!(
+            TreeSize64::from_leaf_count(3),
+            Err(TreeSizeError::InvalidSize64(5))
+        );
+
+        // Same as the 32-bit tree size, where both are supported
+        for i in 1..=MAX_TREE_SIZE.ilog2() {
+            let t = TreeSize::from_u32((1 << i) - 1).unwrap();
+            let t64 = TreeSize64::from(t);
+            assert_eq!(t64.get(), t.get() as u64);
+            assert_eq!(t64.leaf_count().get(), t.leaf_count().get() as u64);
+            assert_eq!(t64.level_count(), t.level_count());
+            for level in 0..t.level_count().get() + 2 {
+                assert_eq!(
+                    t64.level_start(level),
+                    t.level_start(level).map(|start| start as u64)
+                );
+            }
+            assert_eq!(TreeSize::try_from(t64), Ok(t));
+        }
+
+        // Beyond the 32-bit tree size
+        for i in MAX_TREE_SIZE.ilog2() + 1..=MAX_TREE_SIZE_64.ilog2() {
+            let leaf_count = 1u64 << (i - 1);
+            let t64 = TreeSize64::from_leaf_count(leaf_count).unwrap();
+            assert_eq!(t64.get(), (1 << i) - 1);
+            assert_eq!(t64.leaf_count().get(), leaf_count);
+            assert_eq!(t64.level_count().get(), i);
+            assert_eq!(t64.level_start(0), Some(0));
+            assert_eq!(t64.level_start(1), Some(leaf_count));
+            assert_eq!(t64.level_start(i - 1), Some(t64.get() - 1));
+            assert_eq!(t64.level_start(i), None);
+            assert_eq!(
+                TreeSize::try_from(t64),
+                Err(TreeSizeError::HugeTreeUnsupported(t64.get()))
+            );
+        }
+    }
 }
@@ -0,0 +1,168 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! WebAssembly bindings for computing roots and verifying proofs in the browser, with 32-byte nodes
+//! and the built-in Blake2b hasher. Proofs are expected to be SCALE encoded, as produced by the Mintlayer node.
+
+use std::collections::BTreeMap;
+
+use parity_scale_codec::DecodeAll;
+use wasm_bindgen::prelude::*;
+
+use crate::merkle::{
+    hasher::blake2b::{Blake2bHasher, Blake2bNode},
+    proof::{multi::MultiProofHashes, single::SingleProofHashes, verify_result::ProofVerifyResult},
+    tree::MerkleTree,
+    MerkleProofVerificationError, MerkleTreeFormError,
+};
+
+const NODE_SIZE: usize = core::mem::size_of::<Blake2bNode>();
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum WasmBindingsError {
+    #[error("Invalid node size; buffer size must be a multiple of {NODE_SIZE}, found {0}")]
+    InvalidNodesBufferSize(usize),
+    #[error("The number of leaves {0} doesn't match the number of leaf indices {1}")]
+    LeavesAndIndicesCountMismatch(usize, usize),
+    #[error("The leaf index {0} is given more than once")]
+    DuplicateLeafIndex(u32),
+    #[error("Proof decoding error: {0}")]
+    ProofDecoding(String),
+    #[error("Tree creation error: {0}")]
+    TreeForm(#[from] MerkleTreeFormError),
+    #[error("Proof verification error: {0}")]
+    ProofVerification(#[from] MerkleProofVerificationError),
+}
+
+/// The result of verifying a proof. See `ProofVerifyResult` for more details.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyResult {
+    Failed = 0,
+    PassedDecisively = 1,
+    PassedTrivially = 2,
+}
+
+impl From<ProofVerifyResult> for VerifyResult {
+    fn from(result: ProofVerifyResult) -> Self {
+        match result {
+            ProofVerifyResult::Failed => VerifyResult::Failed,
+            ProofVerifyResult::PassedDecisively => VerifyResult::PassedDecisively,
+            ProofVerifyResult::PassedTrivially => VerifyResult::PassedTrivially,
+        }
+    }
+}
+
+fn node_from_bytes(bytes: &[u8]) -> Result<Blake2bNode, WasmBindingsError> {
+    bytes
+        .try_into()
+        .map_err(|_| WasmBindingsError::InvalidNodesBufferSize(bytes.len()))
+}
+
+fn nodes_from_bytes(bytes: &[u8]) -> Result<Vec<Blake2bNode>, WasmBindingsError> {
+    if bytes.len() % NODE_SIZE != 0 {
+        return Err(WasmBindingsError::InvalidNodesBufferSize(bytes.len()));
+    }
+    bytes.chunks_exact(NODE_SIZE).map(node_from_bytes).collect()
+}
+
+/// Decodes the whole buffer, so that trailing bytes are rejected rather than ignored.
+fn decode<P: DecodeAll>(mut encoded: &[u8]) -> Result<P, WasmBindingsError> {
+    P::decode_all(&mut encoded).map_err(|e| WasmBindingsError::ProofDecoding(e.to_string()))
+}
+
+fn compute_root_impl(leaves: &[u8]) -> Result<Vec<u8>, WasmBindingsError> {
+    let leaves = nodes_from_bytes(leaves)?;
+    let tree = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves)?;
+    Ok(tree.root().to_vec())
+}
+
+fn verify_single_proof_impl(
+    encoded_proof: &[u8],
+    leaf: &[u8],
+    root: &[u8],
+) -> Result<VerifyResult, WasmBindingsError> {
+    let proof: SingleProofHashes<Blake2bNode, Blake2bHasher> = decode(encoded_proof)?;
+    let result = proof.verify(node_from_bytes(leaf)?, node_from_bytes(root)?);
+    Ok(result.into())
+}
+
+fn verify_multi_proof_impl(
+    encoded_proof: &[u8],
+    leaf_indices: &[u32],
+    leaves: &[u8],
+    root: &[u8],
+) -> Result<VerifyResult, WasmBindingsError> {
+    let proof: MultiProofHashes<Blake2bNode, Blake2bHasher> = decode(encoded_proof)?;
+    let leaves = nodes_from_bytes(leaves)?;
+    if leaves.len() != leaf_indices.len() {
+        return Err(WasmBindingsError::LeavesAndIndicesCountMismatch(
+            leaves.len(),
+            leaf_indices.len(),
+        ));
+    }
+    // A duplicate would otherwise replace the leaf before it, which would then not be verified
+    let mut indexed_leaves = BTreeMap::new();
+    for (index, leaf) in leaf_indices.iter().copied().zip(leaves) {
+        if indexed_leaves.insert(index, leaf).is_some() {
+            return Err(WasmBindingsError::DuplicateLeafIndex(index));
+        }
+    }
+    let result = proof.verify_by_levels(indexed_leaves, node_from_bytes(root)?)?;
+    Ok(result.into())
+}
+
+/// Hash arbitrary data with Blake2b, to create a leaf.
+#[wasm_bindgen(js_name = hashData)]
+pub fn hash_data(data: &[u8]) -> Vec<u8> {
+    Blake2bHasher::hash_data(data).to_vec()
+}
+
+/// Compute the root of the tree created from the given leaves, which are concatenated 32-byte nodes.
+#[wasm_bindgen(js_name = computeRoot)]
+pub fn compute_root(leaves: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(compute_root_impl(leaves)?)
+}
+
+/// Verify a SCALE encoded `SingleProofHashes` for the given leaf against the given root.
+#[wasm_bindgen(js_name = verifySingleProof)]
+pub fn verify_single_proof(
+    encoded_proof: &[u8],
+    leaf: &[u8],
+    root: &[u8],
+) -> Result<VerifyResult, JsError> {
+    Ok(verify_single_proof_impl(encoded_proof, leaf, root)?)
+}
+
+/// Verify a SCALE encoded `MultiProofHashes` for the given leaves against the given root.
+/// The leaves are concatenated 32-byte nodes, whose indices are given in `leaf_indices`, in the same order.
+/// Every leaf index must be given once.
+#[wasm_bindgen(js_name = verifyMultiProof)]
+pub fn verify_multi_proof(
+    encoded_proof: &[u8],
+    leaf_indices: &[u32],
+    leaves: &[u8],
+    root: &[u8],
+) -> Result<VerifyResult, JsError> {
+    Ok(verify_multi_proof_impl(
+        encoded_proof,
+        leaf_indices,
+        leaves,
+        root,
+    )?)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,169 @@
+// Copyright (c) 2024 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/merkletree-mintlayer/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use parity_scale_codec::Encode;
+
+use crate::merkle::proof::{multi::MultiProofNodes, single::SingleProofNodes};
+
+use super::*;
+
+fn gen_leaves(n: u32) -> Vec<Blake2bNode> {
+    (0..n)
+        .map(|i| Blake2bHasher::hash_data(i.to_be_bytes()))
+        .collect()
+}
+
+#[test]
+fn root() {
+    for leaf_count in 1..20 {
+        let leaves = gen_leaves(leaf_count);
+        let tree = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+
+        assert_eq!(
+            compute_root_impl(&leaves.concat()).unwrap(),
+            tree.root().to_vec()
+        );
+    }
+
+    assert_eq!(
+        compute_root_impl(&[]).unwrap_err(),
+        WasmBindingsError::TreeForm(MerkleTreeFormError::TooSmall(0))
+    );
+    assert_eq!(
+        compute_root_impl(&[0; 33]).unwrap_err(),
+        WasmBindingsError::InvalidNodesBufferSize(33)
+    );
+}
+
+#[test]
+fn single_proof() {
+    let leaves = gen_leaves(11);
+    let tree = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+    let root = tree.root();
+
+    for (leaf_index, leaf) in leaves.iter().enumerate() {
+        let encoded = SingleProofNodes::from_tree_leaf(&tree, leaf_index as u32)
+            .unwrap()
+            .into_values()
+            .encode();
+
+        assert_eq!(
+            verify_single_proof_impl(&encoded, leaf, &root).unwrap(),
+            VerifyResult::PassedDecisively
+        );
+        assert_eq!(
+            verify_single_proof_impl(&encoded, &hash_data(leaf), &root).unwrap(),
+            VerifyResult::Failed
+        );
+        assert!(matches!(
+            verify_single_proof_impl(&encoded[..encoded.len() - 1], leaf, &root).unwrap_err(),
+            WasmBindingsError::ProofDecoding(_)
+        ));
+        assert!(matches!(
+            verify_single_proof_impl(&[encoded.as_slice(), &[0]].concat(), leaf, &root)
+                .unwrap_err(),
+            WasmBindingsError::ProofDecoding(_)
+        ));
+        assert_eq!(
+            verify_single_proof_impl(&encoded, &leaf[1..], &root).unwrap_err(),
+            WasmBindingsError::InvalidNodesBufferSize(31)
+        );
+    }
+}
+
+#[test]
+fn multi_proof() {
+    let leaves = gen_leaves(11);
+    let tree = MerkleTree::<Blake2bNode, Blake2bHasher>::from_leaves(leaves.clone()).unwrap();
+    let root = tree.root();
+
+    let cases: &[&[u32]] = &[&[0], &[3, 4], &[1, 2, 9], &[0, 5, 6, 10]];
+    for leaf_indices in cases {
+        let encoded = MultiProofNodes::from_tree_leaves(&tree, leaf_indices)
+            .unwrap()
+            .into_values()
+            .encode();
+        let proven_leaves = leaf_indices
+            .iter()
+            .map(|i| leaves[*i as usize])
+            .collect::<Vec<_>>()
+            .concat();
+
+        assert_eq!(
+            verify_multi_proof_impl(&encoded, leaf_indices, &proven_leaves, &root).unwrap(),
+            VerifyResult::PassedDecisively
+        );
+
+        let mut tampered_leaves = proven_leaves.clone();
+        tampered_leaves[0] ^= 1;
+        assert_eq!(
+            verify_multi_proof_impl(&encoded, leaf_indices, &tampered_leaves, &root).unwrap(),
+            VerifyResult::Failed
+        );
+
+        // A wrong leaf must not be hidden by a duplicate index with the right leaf
+        let with_duplicate_indices = [&[leaf_indices[0]], *leaf_indices].concat();
+        let with_duplicate_leaves = [&[0u8; NODE_SIZE], proven_leaves.as_slice()].concat();
+        assert_eq!(
+            verify_multi_proof_impl(
+                &encoded,
+                &with_duplicate_indices,
+                &with_duplicate_leaves,
+                &root
+            )
+            .unwrap_err(),
+            WasmBindingsError::DuplicateLeafIndex(leaf_indices[0])
+        );
+
+        assert!(matches!(
+            verify_multi_proof_impl(
+                &[encoded.as_slice(), &[0]].concat(),
+                leaf_indices,
+                &proven_leaves,
+                &root
+            )
+            .unwrap_err(),
+            WasmBindingsError::ProofDecoding(_)
+        ));
+
+        assert_eq!(
+            verify_multi_proof_impl(&encoded, &leaf_indices[1..], &proven_leaves, &root)
+                .unwrap_err(),
+            WasmBindingsError::LeavesAndIndicesCountMismatch(
+                leaf_indices.len(),
+                leaf_indices.len() - 1
+            )
+        );
+    }
+}
+
+#[test]
+fn multi_proof_with_huge_leaf_count() {
+    let leaf = Blake2bHasher::hash_data("a");
+    for tree_leaf_count in [1 << 31, u32::MAX] {
+        let encoded = MultiProofHashes::<Blake2bNode, Blake2bHasher>::from_leaf_count_and_nodes(
+            tree_leaf_count,
+            BTreeMap::new(),
+        )
+        .encode();
+
+        assert_eq!(
+            verify_multi_proof_impl(&encoded, &[0], &leaf, &leaf).unwrap_err(),
+            WasmBindingsError::ProofVerification(
+                MerkleProofVerificationError::InvalidTreeLeavesCount(tree_leaf_count)
+            )
+        );
+    }
+}